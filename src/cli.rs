@@ -0,0 +1,363 @@
+use crate::data::{check_title, serialize_date, split_tags, Handler, Knowledge};
+use crate::editor;
+use crate::error::{Error, Result};
+use chrono::{DateTime, Local};
+use crossterm::tty::IsTty;
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+use structopt::StructOpt;
+
+/// Output format of the commands printing notes or tags.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    /// aligned columns for people, `show` prints the note as stored
+    Table,
+    /// one pretty printed JSON array, or object for `show`
+    Json,
+    /// one JSON object per line
+    Jsonl,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            _ => Err(format!(
+                "unknown format {:?}, expected table, json or jsonl",
+                s
+            )),
+        }
+    }
+}
+
+/// A note as printed by `--format json|jsonl`. Fields are only ever added to this schema,
+/// never renamed or removed, so scripts can rely on it.
+#[derive(serde::Serialize)]
+pub struct NoteRecord {
+    /// path of the note file, as found under the data directory
+    pub path: PathBuf,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub description: String,
+    /// RFC 3339, or null if the note has no date
+    #[serde(serialize_with = "serialize_date")]
+    pub created: Option<DateTime<Local>>,
+    #[serde(serialize_with = "serialize_date")]
+    pub updated: Option<DateTime<Local>>,
+    /// number of whitespace separated words in the text
+    pub words: usize,
+    /// targets of the wiki and markdown links in the text
+    pub links: Vec<String>,
+    /// search rank, higher is better. Only set by `search`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// full text of the note. Only set by `show`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+impl From<&Knowledge> for NoteRecord {
+    fn from(k: &Knowledge) -> Self {
+        NoteRecord {
+            path: k.path.clone(),
+            title: k.title.clone(),
+            tags: k.tag.clone(),
+            description: k.descriptions.clone(),
+            created: k.created,
+            updated: k.updated,
+            words: k.word_count(),
+            links: k.links(),
+            score: None,
+            text: None,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct TagRecord {
+    pub tag: String,
+    /// number of notes carrying the tag
+    pub notes: usize,
+}
+
+#[derive(StructOpt)]
+#[structopt(name = "kb", about = "personal knowledge management in the terminal")]
+pub struct Opt {
+    /// Config file to read
+    #[structopt(long, global = true, parse(from_os_str), default_value = "kb.conf")]
+    pub config: PathBuf,
+    /// Data directory to use instead of the ones in the config file
+    #[structopt(long, global = true)]
+    pub dir: Option<String>,
+    /// Output of list, show, search and tags: table, json or jsonl
+    #[structopt(long, global = true, default_value = "table")]
+    pub format: Format,
+    /// Without a command the TUI is started
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Create a note, the text is read from stdin unless --text is given
+    New {
+        title: String,
+        /// Comma separated tags
+        #[structopt(short, long, default_value = "")]
+        tags: String,
+        #[structopt(short, long, default_value = "")]
+        description: String,
+        #[structopt(long)]
+        text: Option<String>,
+    },
+    /// List the titles of all notes
+    List {
+        /// Only list notes with this tag
+        #[structopt(short, long)]
+        tag: Option<String>,
+    },
+    /// Print a note
+    Show { title: String },
+    /// Change the title, tags, description or text of a note, text is read from stdin with
+    /// --stdin. Without any of them the note is opened in $VISUAL or $EDITOR.
+    Edit {
+        title: String,
+        #[structopt(long)]
+        new_title: Option<String>,
+        /// Comma separated tags, replacing the current ones
+        #[structopt(short, long)]
+        tags: Option<String>,
+        #[structopt(short, long)]
+        description: Option<String>,
+        #[structopt(long)]
+        text: Option<String>,
+        #[structopt(long)]
+        stdin: bool,
+    },
+    /// Move a note to the trash
+    Rm { title: String },
+    /// Search notes, see the README for the query language
+    Search { query: Vec<String> },
+    /// List all tags with the number of notes carrying them
+    Tags,
+    /// Rewrite the notes with legacy `# Title:` header lines with a YAML front matter
+    Migrate,
+}
+
+fn read_stdin() -> Result<String> {
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Compact JSON for jsonl, pretty printed otherwise.
+fn to_json<T: serde::Serialize + ?Sized>(value: &T, format: Format) -> Result<String> {
+    let json = if format == Format::Jsonl {
+        serde_json::to_string(value)?
+    } else {
+        serde_json::to_string_pretty(value)?
+    };
+    Ok(json)
+}
+
+fn print_json<T: serde::Serialize>(records: &[T], format: Format) -> Result<()> {
+    if format == Format::Jsonl {
+        for record in records {
+            println!("{}", to_json(record, format)?);
+        }
+    } else {
+        println!("{}", to_json(records, format)?);
+    }
+    Ok(())
+}
+
+/// Print `rows` under `header` with every column padded to its widest cell.
+fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows.iter() {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let print_row = |cells: Vec<&str>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(header.to_vec());
+    for row in rows.iter() {
+        print_row(row.iter().map(|e| e.as_str()).collect());
+    }
+}
+
+fn print_notes(records: Vec<NoteRecord>, format: Format) -> Result<()> {
+    if format != Format::Table {
+        return print_json(&records, format);
+    }
+    let rows = records
+        .into_iter()
+        .map(|r| {
+            vec![
+                r.title,
+                r.tags.join(", "),
+                r.updated
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+                r.path.display().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["TITLE", "TAGS", "UPDATED", "PATH"], rows);
+    Ok(())
+}
+
+/// The single note called `title`, or an error if there is none or it is ambiguous.
+fn find_one(handler: &mut Handler, title: &str) -> Result<Knowledge> {
+    let found = handler.find_by_title(title)?;
+    match found.len() {
+        0 => Err(Error::NotFound(title.to_owned())),
+        1 => Knowledge::from_file(&found[0].path),
+        _ => Err(Error::Ambiguous(
+            title.to_owned(),
+            found.iter().map(|k| k.path.clone()).collect(),
+        )),
+    }
+}
+
+pub fn run(command: Command, format: Format, handler: &mut Handler) -> Result<()> {
+    match command {
+        Command::New {
+            title,
+            tags,
+            description,
+            text,
+        } => {
+            check_title(&title)?;
+            let text = match text {
+                Some(text) => text,
+                None if !std::io::stdin().is_tty() => read_stdin()?,
+                None => String::new(),
+            };
+            if !handler.find_by_title(&title)?.is_empty() {
+                return Err(Error::Exists(title));
+            }
+            let knowledge = Knowledge::new(title, text, description, tags);
+            knowledge.write(&handler.config)?;
+        }
+        Command::List { tag } => {
+            handler.read_all_files()?;
+            let mut notes: Vec<&Knowledge> = handler
+                .data
+                .iter()
+                .filter(|k| match &tag {
+                    Some(tag) => k.tag.iter().any(|t| t.eq_ignore_ascii_case(tag)),
+                    None => true,
+                })
+                .collect();
+            notes.sort_by(|a, b| a.title.cmp(&b.title));
+            print_notes(notes.into_iter().map(NoteRecord::from).collect(), format)?;
+        }
+        Command::Show { title } => {
+            let knowledge = find_one(handler, &title)?;
+            if format == Format::Table {
+                let content = std::fs::read_to_string(&knowledge.path)
+                    .map_err(Error::file(&knowledge.path))?;
+                print!("{}", content);
+            } else {
+                let mut record = NoteRecord::from(&knowledge);
+                record.text = Some(knowledge.text.clone());
+                println!("{}", to_json(&record, format)?);
+            }
+        }
+        Command::Edit {
+            title,
+            new_title,
+            tags,
+            description,
+            text,
+            stdin,
+        } => {
+            let mut knowledge = find_one(handler, &title)?;
+            let old_path = knowledge.path.clone();
+            let old_title = knowledge.title.clone();
+            if new_title.is_none()
+                && tags.is_none()
+                && description.is_none()
+                && text.is_none()
+                && !stdin
+            {
+                editor::edit_note(handler, &old_path)?;
+                return Ok(());
+            }
+            if let Some(new_title) = new_title {
+                knowledge.title = new_title;
+            }
+            if let Some(tags) = tags {
+                knowledge.tag = split_tags(&tags);
+            }
+            if let Some(description) = description {
+                knowledge.descriptions = description;
+            }
+            if let Some(text) = text {
+                knowledge.text = text;
+            } else if stdin {
+                knowledge.text = read_stdin()?;
+            }
+            // a note without front matter doesn't get one for the time
+            if knowledge.has_front_matter() {
+                knowledge.updated = Some(Local::now());
+            }
+            handler.save_note(knowledge, &old_title)?;
+        }
+        Command::Rm { title } => {
+            let knowledge = find_one(handler, &title)?;
+            handler.trash(&knowledge.path)?;
+        }
+        Command::Search { query } => {
+            handler.update_index()?;
+            let results = handler.search(&query.join(" "))?;
+            let mut records = vec![];
+            for result in results.iter() {
+                let mut record = NoteRecord::from(&Knowledge::from_file(&result.path)?);
+                record.score = Some(result.score);
+                records.push(record);
+            }
+            print_notes(records, format)?;
+        }
+        Command::Tags => {
+            handler.read_all_files()?;
+            let mut tags: Vec<TagRecord> = handler
+                .get_mapping()
+                .into_iter()
+                .map(|(tag, notes)| TagRecord {
+                    tag,
+                    notes: notes.len(),
+                })
+                .collect();
+            tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+            if format == Format::Table {
+                let rows = tags
+                    .into_iter()
+                    .map(|t| vec![t.tag, t.notes.to_string()])
+                    .collect();
+                print_table(&["TAG", "NOTES"], rows);
+            } else {
+                print_json(&tags, format)?;
+            }
+        }
+        Command::Migrate => {
+            handler.read_all_files()?;
+            let migrated = handler.migrate_legacy()?;
+            println!("migrated {} notes", migrated);
+        }
+    }
+    Ok(())
+}
@@ -1,26 +1,75 @@
+use crate::error::{Error, Result};
 use serde_derive::Deserialize;
+use std::collections::BTreeMap;
 use std::fs::read_to_string;
 use std::path::PathBuf;
-use toml::Value;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Default)]
 pub struct Config {
     pub data_directories: Vec<String>,
     pub extension: String,
+    /// also copy to the terminal clipboard with the OSC 52 escape sequence
+    #[serde(default)]
+    pub osc52: bool,
+    /// wrap long lines in the editor instead of scrolling sideways
+    #[serde(default = "enabled")]
+    pub soft_wrap: bool,
+    /// show line numbers next to the text in the editor
+    #[serde(default = "enabled")]
+    pub line_numbers: bool,
+    /// vim-style modal editing in the editor
+    #[serde(default)]
+    pub vim: bool,
+    /// key bindings replacing the defaults, see `keymap`
+    #[serde(default)]
+    pub keys: BTreeMap<String, Binding>,
+    /// the key `<leader>` stands for in key bindings
+    #[serde(default = "default_leader")]
+    pub leader: String,
+    /// milliseconds to wait for the next key of a sequence like `gg`
+    #[serde(default = "default_key_timeout")]
+    pub key_timeout: u64,
+    /// days deleted notes stay in the trash before they are purged, 0 keeps them
+    #[serde(default = "default_trash_retention")]
+    pub trash_retention: u64,
+}
+
+/// An entry of the `[keys]` section: a key bound to an action of every view having it, or
+/// the table of bindings of one view.
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Binding {
+    Action(String),
+    View(BTreeMap<String, String>),
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn default_leader() -> String {
+    String::from("<Space>")
+}
+
+fn default_key_timeout() -> u64 {
+    1000
+}
+
+fn default_trash_retention() -> u64 {
+    30
 }
 
 impl Config {
-    pub fn new(config_file: &PathBuf) -> std::io::Result<Self> {
-        let config = read_to_string(config_file)?;
-        let toml_value = config.parse::<Value>().unwrap();
-        let s = toml_value["data_directories"].as_array().unwrap();
-        let data_directories_file: Vec<String> = s
-            .iter()
-            .map(|x| String::from(x.as_str().unwrap()))
-            .collect();
-        Ok(Config {
-            data_directories: data_directories_file,
-            extension: String::from(toml_value["extension"].as_str().unwrap()),
-        })
+    pub fn new(config_file: &PathBuf) -> Result<Self> {
+        let config = read_to_string(config_file).map_err(Error::file(config_file))?;
+        let config: Config = toml::from_str(&config)
+            .map_err(|e| Error::Config(format!("{}: {}", config_file.display(), e)))?;
+        if config.data_directories.is_empty() {
+            return Err(Error::Config(format!(
+                "{}: data_directories is empty",
+                config_file.display()
+            )));
+        }
+        Ok(config)
     }
 }
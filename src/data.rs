@@ -1,9 +1,29 @@
 use crate::config::Config;
-use core::fmt;
-use glob::glob;
-use std::collections::HashMap;
+use crate::error::{Error, Result};
+use crate::index::{self, Index};
+use crate::query::{Field, Query};
+use crate::trash::{self, TrashEntry};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone};
+use glob::{glob, Pattern};
+use serde::de::Error as _;
+use serde::Deserialize as _;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::create_dir;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+
+/// How the metadata of a note is stored on disk.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum FrontMatterFormat {
+    /// No metadata, the whole file is text.
+    #[default]
+    Plain,
+    /// `---` delimited YAML block at the top of the file.
+    Yaml,
+    /// `+++` delimited TOML block at the top of the file.
+    Toml,
+    /// `# Title:`, `# Descriptions:` and `# Tags:` header lines. Written back as YAML.
+    Legacy,
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
 pub struct Knowledge {
@@ -11,106 +31,1309 @@ pub struct Knowledge {
     pub tag: Vec<String>,
     pub text: String,
     pub descriptions: String,
+    pub created: Option<DateTime<Local>>,
+    pub updated: Option<DateTime<Local>>,
+    pub aliases: Vec<String>,
+    /// front matter keys that `Knowledge` doesn't know about, kept so they are written back
+    pub extra: BTreeMap<String, serde_json::Value>,
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub format: FrontMatterFormat,
+}
+
+/// A `[[target#heading|label]]` link to another note, or to a heading of the same note when
+/// the target is empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikiLink {
+    pub target: String,
+    pub heading: Option<String>,
+    /// the text shown for the link
+    pub label: String,
+}
+
+impl WikiLink {
+    /// Parse the text between `[[` and `]]`.
+    pub fn parse(inner: &str) -> Self {
+        let (link, label) = match inner.find('|') {
+            Some(i) => (&inner[..i], &inner[i + 1..]),
+            None => (inner, inner),
+        };
+        let (target, heading) = match link.find('#') {
+            Some(i) => (&link[..i], Some(link[i + 1..].trim().to_owned())),
+            None => (link, None),
+        };
+        WikiLink {
+            target: target.trim().to_owned(),
+            heading: heading.filter(|h| !h.is_empty()),
+            label: label.to_owned(),
+        }
+    }
+}
+
+/// The `[[wiki links]]` of `text` with the byte range of their markup, in order of
+/// appearance.
+fn find_wiki_links(text: &str) -> Vec<((usize, usize), WikiLink)> {
+    let mut links = vec![];
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("[[") {
+        let start = offset + start;
+        let inner = &text[start + 2..];
+        match inner.find("]]") {
+            Some(end) if !inner[..end].is_empty() && !inner[..end].contains('\n') => {
+                let range = (start, start + 2 + end + 2);
+                links.push((range, WikiLink::parse(&inner[..end])));
+                offset = range.1;
+            }
+            _ => offset = start + 2,
+        }
+    }
+    links
+}
+
+/// Byte ranges of the targets of the markdown `[text](links)` of `text`, without the title
+/// that may follow a target.
+fn find_markdown_links(text: &str) -> Vec<(usize, usize)> {
+    let mut links = vec![];
+    let mut offset = 0;
+    while let Some(i) = text[offset..].find("](") {
+        let start = offset + i + 2;
+        offset = start;
+        let inner = match text[start..].find(')') {
+            Some(end) if !text[start..start + end].contains('\n') => &text[start..start + end],
+            _ => continue,
+        };
+        offset = start + inner.len() + 1;
+        let target = inner.trim_start();
+        let start = start + inner.len() - target.len();
+        let len = target.find(char::is_whitespace).unwrap_or(target.len());
+        if len > 0 {
+            links.push((start, start + len));
+        }
+    }
+    links
+}
+
+/// Byte ranges of the fenced code blocks and the code spans of `text`, found like the reader
+/// renders them. Links in them are text.
+fn code_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = vec![];
+    // marker, length and start of the fence of the code block being read
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let trimmed = line.trim();
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~');
+        let length = marker.map_or(0, |m| trimmed.chars().take_while(|&c| c == m).count());
+        match (fence, marker) {
+            (Some((m, l, begin)), _) => {
+                if marker == Some(m) && length >= l && length == trimmed.chars().count() {
+                    ranges.push((begin, offset));
+                    fence = None;
+                }
+            }
+            (None, Some(m)) if length >= 3 && line.len() - line.trim_start().len() <= 3 => {
+                fence = Some((m, length, start));
+            }
+            (None, _) => {
+                let mut i = 0;
+                while let Some(tick) = line[i..].find('`') {
+                    let open = i + tick;
+                    let ticks = line[open..].chars().take_while(|&c| c == '`').count();
+                    let delimiter = &line[open..open + ticks];
+                    i = open + ticks;
+                    if let Some(end) = line[i..].find(delimiter) {
+                        i += end + ticks;
+                        ranges.push((start + open, start + i));
+                    }
+                }
+            }
+        }
+    }
+    // a code block nobody closed goes on to the end
+    if let Some((_, _, begin)) = fence {
+        ranges.push((begin, text.len()));
+    }
+    ranges
+}
+
+/// `s` with its `%XX` escapes decoded, like the spaces of `[text](a%20note.md)`.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = s
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `path` with `.` and `..` resolved without looking at the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The `/` separated path leading from the directory `dir` to `path`, both normalized.
+fn relative_path(dir: &Path, path: &Path) -> String {
+    let dir: Vec<_> = dir.components().collect();
+    let path: Vec<_> = path.components().collect();
+    let common = dir.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![String::from(".."); dir.len() - common];
+    parts.extend(
+        path[common..]
+            .iter()
+            .map(|part| part.as_os_str().to_string_lossy().into_owned()),
+    );
+    parts.join("/")
+}
+
+/// The front matter or legacy header of the content of a note file, and its text.
+fn split_text(content: &str) -> (&str, &str) {
+    // the text is always what is left after the metadata
+    let text = Knowledge::parse(content).text.len();
+    content.split_at(content.len() - text)
+}
+
+/// Rewrite the links of `text` for the note at `from` moving to `to` under the new `title`:
+/// the `[[wiki links]]` to it, found by their target with `refers`, and the relative markdown
+/// links to it. `text` is in the directory `dir` and moves to `new_dir`, its other relative
+/// markdown links are rewritten too if that is another directory. Links in code are left
+/// alone. Returns the text and the number of links rewritten.
+fn rewrite_links(
+    text: &str,
+    (dir, new_dir): (&Path, &Path),
+    (from, to): (&Path, &Path),
+    title: &str,
+    refers: &dyn Fn(&str) -> bool,
+) -> (String, usize) {
+    let mut edits: Vec<(usize, usize, String)> = vec![];
+    let code = code_ranges(text);
+    let in_code = |i: usize| code.iter().any(|(start, end)| *start <= i && i < *end);
+    let wiki_links = find_wiki_links(text);
+    for ((start, end), link) in &wiki_links {
+        if link.target.is_empty() || !refers(&link.target) || in_code(*start) {
+            continue;
+        }
+        // keep the heading and the label
+        let inner = &text[start + 2..end - 2];
+        let rest = inner.find(['#', '|']).unwrap_or(inner.len());
+        let link = format!("[[{}{}]]", title, &inner[rest..]);
+        if link != text[*start..*end] {
+            edits.push((*start, *end, link));
+        }
+    }
+    for (start, end) in find_markdown_links(text) {
+        if in_code(start)
+            || wiki_links
+                .iter()
+                .any(|((s, e), _)| *s <= start && end <= *e)
+        {
+            continue;
+        }
+        let target = &text[start..end];
+        let (path, heading) = target.split_at(target.find('#').unwrap_or(target.len()));
+        // leave out URLs like https: and mailto: and absolute paths
+        if path.is_empty() || path.contains(':') || path.starts_with('/') {
+            continue;
+        }
+        let resolved = normalize(&dir.join(percent_decode(path)));
+        let resolved = if resolved == from { to } else { &resolved };
+        // a space would end the link
+        let mut link = relative_path(new_dir, resolved).replace(' ', "%20");
+        link.push_str(heading);
+        if link != target && (resolved == to || dir != new_dir) {
+            edits.push((start, end, link));
+        }
+    }
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut rewritten = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (start, end, link) in &edits {
+        rewritten.push_str(&text[offset..*start]);
+        rewritten.push_str(link);
+        offset = *end;
+    }
+    rewritten.push_str(&text[offset..]);
+    (rewritten, edits.len())
+}
+
+/// The on-disk shape of the front matter block.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+struct FrontMatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(
+        default,
+        alias = "tag",
+        deserialize_with = "deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    tags: Vec<String>,
+    #[serde(
+        default,
+        alias = "descriptions",
+        skip_serializing_if = "String::is_empty"
+    )]
+    description: String,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_date",
+        serialize_with = "serialize_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    created: Option<DateTime<Local>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_date",
+        serialize_with = "serialize_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    updated: Option<DateTime<Local>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    aliases: Vec<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// Check that `title` can name the file of a note, and be linked to.
+pub fn check_title(title: &str) -> Result<()> {
+    let invalid = |reason| Err(Error::InvalidName(title.to_owned(), reason));
+    if title.trim().is_empty() {
+        return invalid("the name is empty");
+    }
+    if title.contains(['/', '\\']) || title == "." || title == ".." {
+        return invalid("a title can't name a directory, no / \\ . or ..");
+    }
+    if title.contains(['#', '|', '[', ']']) {
+        return invalid("# | [ and ] can't be linked to");
+    }
+    Ok(())
+}
+
+/// Split a comma (or newline) separated list of tags, dropping empty entries.
+pub fn split_tags(s: &str) -> Vec<String> {
+    s.split([',', '\n'])
+        .map(|e| e.trim().to_owned())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Parse the date formats accepted in front matter: RFC 3339, `YYYY-MM-DD HH:MM:SS` and
+/// `YYYY-MM-DD`. Dates without an offset are taken as local time.
+pub fn parse_date(s: &str) -> Option<DateTime<Local>> {
+    let s = s.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(s) {
+        return Some(date.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    Local.from_local_datetime(&naive).single()
+}
+
+fn deserialize_list<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match Option::<List>::deserialize(d)? {
+        None => vec![],
+        Some(List::One(s)) => split_tags(&s),
+        Some(List::Many(items)) => items
+            .iter()
+            .map(|e| e.trim().to_owned())
+            .filter(|e| !e.is_empty())
+            .collect(),
+    })
+}
+
+fn deserialize_date<'de, D: serde::Deserializer<'de>>(
+    d: D,
+) -> std::result::Result<Option<DateTime<Local>>, D::Error> {
+    // TOML has a native datetime type, YAML dates are plain strings
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum RawDate {
+        Text(String),
+        Toml(toml::value::Datetime),
+    }
+    let raw = match Option::<RawDate>::deserialize(d)? {
+        None => return Ok(None),
+        Some(RawDate::Text(s)) => s,
+        Some(RawDate::Toml(t)) => t.to_string(),
+    };
+    parse_date(&raw)
+        .map(Some)
+        .ok_or_else(|| D::Error::custom(format!("invalid date: {}", raw)))
+}
+
+pub fn serialize_date<S: serde::Serializer>(
+    date: &Option<DateTime<Local>>,
+    s: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match date {
+        Some(date) => s.serialize_str(&date.to_rfc3339_opts(SecondsFormat::Secs, false)),
+        None => s.serialize_none(),
+    }
+}
+
+/// `content` with the `updated` key of its YAML or TOML front matter set to `date`. Only that
+/// line changes, or one is added at the top of the front matter. None without front matter.
+pub fn set_updated(content: &str, date: &DateTime<Local>) -> Option<String> {
+    let (format, block, _) = split_front_matter(content)?;
+    if Knowledge::parse(content).format != format {
+        // front matter that fails to parse is text
+        return None;
+    }
+    let date = date.to_rfc3339_opts(SecondsFormat::Secs, false);
+    let (separator, line) = match format {
+        FrontMatterFormat::Toml => ('=', format!("updated = {}\n", date)),
+        _ => (':', format!("updated: {}\n", date)),
+    };
+    let start = content.find('\n')? + 1;
+    let mut lines: Vec<&str> = block.split_inclusive('\n').collect();
+    // the keys of the note come before the first table in TOML
+    let keys = match format {
+        FrontMatterFormat::Toml => lines.iter().position(|l| l.starts_with('[')),
+        _ => None,
+    };
+    let key = lines[..keys.unwrap_or(lines.len())].iter().position(|l| {
+        l.strip_prefix("updated")
+            .is_some_and(|rest| rest.trim_start().starts_with(separator))
+    });
+    match key {
+        Some(i) => lines[i] = &line,
+        None => lines.insert(0, &line),
+    }
+    let end = start + block.len();
+    Some(format!(
+        "{}{}{}",
+        &content[..start],
+        lines.concat(),
+        &content[end..]
+    ))
+}
+
+/// Split `content` into (format, front matter, body) if it starts with a `---` or `+++` block.
+fn split_front_matter(content: &str) -> Option<(FrontMatterFormat, &str, &str)> {
+    let (format, delimiter) = if content.starts_with("---") {
+        (FrontMatterFormat::Yaml, "---")
+    } else if content.starts_with("+++") {
+        (FrontMatterFormat::Toml, "+++")
+    } else {
+        return None;
+    };
+    let mut lines = content.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != delimiter {
+        return None;
+    }
+    let mut offset = first.len();
+    for line in lines {
+        let end = offset + line.len();
+        let closing = line.trim_end();
+        if closing == delimiter || (format == FrontMatterFormat::Yaml && closing == "...") {
+            return Some((format, &content[first.len()..offset], &content[end..]));
+        }
+        offset = end;
+    }
+    None
+}
+
+/// Read the legacy `# Title:`/`# Descriptions:`/`# Tags:` lines at the top of `content`.
+fn split_legacy_header(content: &str) -> Option<(FrontMatter, &str)> {
+    let mut front_matter = FrontMatter::default();
+    let mut found = false;
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if let Some(t) = trimmed.strip_prefix("# Title:") {
+            front_matter.title = Some(t.trim().to_owned()).filter(|t| !t.is_empty());
+        } else if let Some(t) = trimmed.strip_prefix("# Descriptions:") {
+            front_matter.description = t.trim().to_owned();
+        } else if let Some(t) = trimmed.strip_prefix("# Tags:") {
+            front_matter.tags = split_tags(t);
+        } else {
+            // the old writer separated the header from the text with one empty line
+            if found && trimmed.is_empty() {
+                offset += line.len();
+            }
+            break;
+        }
+        found = true;
+        offset += line.len();
+    }
+    if found {
+        Some((front_matter, &content[offset..]))
+    } else {
+        None
+    }
 }
 
 impl Knowledge {
+    /// `tag` is a comma separated list of tags, as typed in the tag box.
     pub fn new(title: String, text: String, descriptions: String, tag: String) -> Self {
+        let now = Local::now();
         Knowledge {
             title,
-            tag: vec![tag],
+            tag: split_tags(&tag),
             text,
             descriptions,
+            created: Some(now),
+            updated: Some(now),
+            format: FrontMatterFormat::Yaml,
+            ..Knowledge::default()
         }
     }
 
-    pub fn from_file<P: Into<PathBuf>>(file: P) -> Self {
+    pub fn from_file<P: Into<PathBuf>>(file: P) -> Result<Self> {
         let f = file.into();
-        let res = std::fs::read_to_string(&f).unwrap();
-        let title = f.file_stem().and_then(|e| e.to_str()).unwrap_or("INVALID_TITLE");
-        let mut descriptions = String::new();
-        let mut tags: Vec<String> = vec![];
-        for line in res.split("\n").into_iter() {
-            if line.contains("# Title:") {
-                // let t: String = line.replace("# Title:", "");
-                // title = t.trim().into();
-                continue;
-            } else if line.contains("# Descriptions:") {
-                let t: String = line.replace("# Descriptions:", "");
-                descriptions = t.trim().into();
-                continue;
-            } else if line.contains("# Tags: ") {
-                let t = line.replace("# Tags:", "");
-                tags = t.split(",").map(|e| e.trim().to_owned()).collect();
+        let res = std::fs::read_to_string(&f).map_err(Error::file(&f))?;
+        Ok(Knowledge::from_content(&res, f))
+    }
+
+    /// Parse `content` read from `file`. The title falls back to the file name.
+    pub fn from_content<P: Into<PathBuf>>(content: &str, file: P) -> Self {
+        let f = file.into();
+        let mut knowledge = Knowledge::parse(content);
+        if knowledge.title.is_empty() {
+            knowledge.title = f
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .unwrap_or("INVALID_TITLE")
+                .to_string();
+        }
+        knowledge.path = f;
+        knowledge
+    }
+
+    /// Parse the content of a note. Front matter that fails to parse is kept as text.
+    pub fn parse(content: &str) -> Self {
+        let parsed = match split_front_matter(content) {
+            Some((format, block, body)) => {
+                let front_matter = if block.trim().is_empty() {
+                    Ok(FrontMatter::default())
+                } else if format == FrontMatterFormat::Yaml {
+                    serde_yaml::from_str::<FrontMatter>(block).map_err(|e| e.to_string())
+                } else {
+                    toml::from_str::<FrontMatter>(block).map_err(|e| e.to_string())
+                };
+                front_matter.ok().map(|fm| (format, fm, body))
             }
+            None => {
+                split_legacy_header(content).map(|(fm, body)| (FrontMatterFormat::Legacy, fm, body))
+            }
+        };
+        match parsed {
+            Some((format, front_matter, body)) => Knowledge {
+                title: front_matter.title.unwrap_or_default(),
+                tag: front_matter.tags,
+                text: body.to_string(),
+                descriptions: front_matter.description,
+                created: front_matter.created,
+                updated: front_matter.updated,
+                aliases: front_matter.aliases,
+                extra: front_matter.extra,
+                path: PathBuf::new(),
+                format,
+            },
+            None => Knowledge {
+                text: content.to_string(),
+                ..Knowledge::default()
+            },
         }
-        Knowledge {
-            title: title.to_string(),
-            descriptions: descriptions.to_string(),
-            tag: tags,
-            text: res,
+    }
+
+    pub fn word_count(&self) -> usize {
+        self.text.split_whitespace().count()
+    }
+
+    /// The `[[wiki links]]` of the text in order of appearance, including those to the same
+    /// note more than once.
+    pub fn wiki_links(&self) -> Vec<WikiLink> {
+        find_wiki_links(&self.text)
+            .into_iter()
+            .map(|(_, link)| link)
+            .collect()
+    }
+
+    /// Targets of the `[[wiki links]]` and markdown `[text](links)` in the text, in order of
+    /// appearance without duplicates. Aliases and headings (`[[target|alias]]`,
+    /// `[[target#heading]]`) are stripped from wiki links.
+    pub fn links(&self) -> Vec<String> {
+        let text = self.text.as_str();
+        let wiki = find_wiki_links(text);
+        // a `](` inside the markup of a wiki link is part of it
+        let in_wiki = |i: usize| {
+            wiki.iter()
+                .any(|((start, end), _)| (*start..*end).contains(&i))
+        };
+        let mut found: Vec<(usize, &str)> = wiki
+            .iter()
+            .map(|((start, _), link)| (*start, link.target.as_str()))
+            .chain(
+                find_markdown_links(text)
+                    .into_iter()
+                    .filter(|(start, _)| !in_wiki(*start))
+                    .map(|(start, end)| (start, &text[start..end])),
+            )
+            .collect();
+        found.sort_by_key(|(start, _)| *start);
+        let mut links: Vec<String> = vec![];
+        for (_, link) in found {
+            if !link.is_empty() && !links.iter().any(|l| l == link) {
+                links.push(link.to_owned());
+            }
         }
+        links
     }
 
-    pub fn write(&self, config: &Config) -> std::io::Result<()> {
-        let parent = &config
+    fn front_matter(&self) -> FrontMatter {
+        FrontMatter {
+            title: Some(self.title.clone()).filter(|title| !title.is_empty()),
+            tags: self.tag.clone(),
+            description: self.descriptions.clone(),
+            created: self.created,
+            updated: self.updated,
+            aliases: self.aliases.clone(),
+            extra: self.extra.clone(),
+        }
+    }
+
+    /// Whether the file of the note starts with a YAML or TOML front matter.
+    pub fn has_front_matter(&self) -> bool {
+        matches!(
+            self.format,
+            FrontMatterFormat::Yaml | FrontMatterFormat::Toml
+        )
+    }
+
+    fn has_metadata(&self) -> bool {
+        !self.tag.is_empty()
+            || !self.descriptions.is_empty()
+            || !self.aliases.is_empty()
+            || !self.extra.is_empty()
+            || self.created.is_some()
+            || self.updated.is_some()
+    }
+
+    pub fn write(&self, config: &Config) -> Result<()> {
+        let parent = config
             .data_directories
-            .get(0)
-            .expect("Must contain 1 data directories");
+            .first()
+            .ok_or_else(|| Error::Config(String::from("no data directory to write to")))?;
         let mut path = PathBuf::new();
         path.push(parent);
         if !path.exists() {
-            create_dir(&path).expect(format!("Failed to create directory: {:?}", path).as_str());
+            create_dir(&path).map_err(Error::file(&path))?;
         }
         self.write_to_file(path, &config.extension)
     }
 
-    pub fn write_to_file<T: Into<PathBuf>>(&self, parent_dir: T, ext: &str) -> std::io::Result<()> {
+    pub fn write_to_file<T: Into<PathBuf>>(&self, parent_dir: T, ext: &str) -> Result<()> {
+        check_title(&self.title)?;
         let mut path = parent_dir.into();
         let file: String = self.title.clone() + "." + ext;
         path.push(file);
-        std::fs::write(&path, &format!("{}", self))?;
+        std::fs::write(&path, self.to_content()?).map_err(Error::file(&path))?;
+        Ok(())
+    }
+
+    /// The content of the note's file: the front matter in the format it was read in, YAML
+    /// for new and legacy notes, followed by the text. A plain note without any metadata is
+    /// only its text.
+    pub fn to_content(&self) -> Result<String> {
+        let (delimiter, mut block) = match self.format {
+            FrontMatterFormat::Plain if !self.has_metadata() => return Ok(self.text.clone()),
+            FrontMatterFormat::Toml => ("+++", toml_front_matter(self.front_matter())?),
+            _ => {
+                let block = serde_yaml::to_string(&self.front_matter())
+                    .map_err(|e| Error::FrontMatter(e.to_string()))?;
+                ("---", block.trim_start_matches("---\n").to_owned())
+            }
+        };
+        if !block.ends_with('\n') {
+            block.push('\n');
+        }
+        Ok(format!(
+            "{}\n{}{}\n{}",
+            delimiter, block, delimiter, self.text
+        ))
+    }
+}
+
+/// Key of the object a TOML datetime is read into when it is not deserialized as one.
+const TOML_DATETIME: &str = "$__toml_private_datetime";
+
+/// Write `front_matter` as a TOML block. Datetimes among its extra keys were read as objects
+/// holding `TOML_DATETIME`, and are written back as datetimes.
+fn toml_front_matter(mut front_matter: FrontMatter) -> Result<String> {
+    let error = |e: toml::ser::Error| Error::FrontMatter(e.to_string());
+    let extra = std::mem::take(&mut front_matter.extra);
+    let mut table = match toml::Value::try_from(front_matter).map_err(error)? {
+        toml::Value::Table(table) => table,
+        _ => toml::value::Table::new(),
+    };
+    for (key, value) in &extra {
+        if let Some(value) = toml_value(value) {
+            table.insert(key.clone(), value);
+        }
+    }
+    toml::to_string(&table).map_err(error)
+}
+
+/// `value` as TOML, None for null which TOML doesn't have.
+fn toml_value(value: &serde_json::Value) -> Option<toml::Value> {
+    use serde_json::Value as Json;
+    Some(match value {
+        Json::Null => return None,
+        Json::Bool(b) => toml::Value::Boolean(*b),
+        Json::Number(n) => match n.as_i64() {
+            Some(n) => toml::Value::Integer(n),
+            None => toml::Value::Float(n.as_f64()?),
+        },
+        Json::String(s) => toml::Value::String(s.clone()),
+        Json::Array(items) => toml::Value::Array(items.iter().filter_map(toml_value).collect()),
+        Json::Object(map) => {
+            if let (1, Some(Json::String(date))) = (map.len(), map.get(TOML_DATETIME)) {
+                if let Ok(date) = date.parse() {
+                    return Some(toml::Value::Datetime(date));
+                }
+            }
+            toml::Value::Table(
+                map.iter()
+                    .filter_map(|(key, value)| Some((key.clone(), toml_value(value)?)))
+                    .collect(),
+            )
+        }
+    })
+}
+
+/// A note matching a search query.
+pub struct SearchResult {
+    pub title: String,
+    pub path: PathBuf,
+    /// first tag of the note containing the query
+    pub tag: Option<String>,
+    /// first line of the note containing the query, or its first non-empty line
+    pub line: String,
+    /// byte range of the match inside `line`
+    pub matched: Option<(usize, usize)>,
+    pub score: f64,
+}
+
+/// Moving a note to another name or directory, with the links to it rewritten in the other
+/// notes. Made by `Handler::plan_rename`, nothing is written before `apply`.
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// new content of the note, None if only its file moves
+    pub content: Option<String>,
+    /// number of links rewritten in the note itself
+    pub own_links: usize,
+    /// (path, new content, number of links rewritten) of the other notes linking to the note
+    pub rewrites: Vec<(PathBuf, String, usize)>,
+}
+
+/// Hidden file next to `path` its new content is written to before it replaces it.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", name))
+}
+
+impl Rename {
+    /// Move the note and write the notes with rewritten links. Every new content is written
+    /// to a temporary file first, so a failing write leaves all notes as they were. Only then
+    /// are the notes replaced and the note moved.
+    pub fn apply(&self) -> Result<()> {
+        if let Some(dir) = self.to.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::file(dir))?;
+        }
+        let mut writes: Vec<(&Path, &str)> = self
+            .rewrites
+            .iter()
+            .map(|(path, content, _)| (path.as_path(), content.as_str()))
+            .collect();
+        if let Some(content) = &self.content {
+            writes.push((&self.to, content));
+        }
+        let mut written: Vec<(PathBuf, &Path)> = vec![];
+        for (path, content) in writes {
+            let temp = temp_path(path);
+            let result = std::fs::write(&temp, content);
+            written.push((temp, path));
+            if let Err(e) = result {
+                for (temp, _) in &written {
+                    let _ = std::fs::remove_file(temp);
+                }
+                return Err(Error::File(path.to_path_buf(), e));
+            }
+        }
+
+        for (temp, path) in written.iter().filter(|(_, path)| *path != self.to) {
+            std::fs::rename(temp, path).map_err(Error::file(*path))?;
+        }
+        if self.content.is_some() {
+            let temp = temp_path(&self.to);
+            std::fs::rename(&temp, &self.to).map_err(Error::file(&self.to))?;
+            if self.from != self.to {
+                std::fs::remove_file(&self.from).map_err(Error::file(&self.from))?;
+            }
+        } else {
+            std::fs::rename(&self.from, &self.to).map_err(Error::file(&self.from))?;
+        }
         Ok(())
     }
 }
 
-impl fmt::Display for Knowledge {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut result = String::new();
-        // result.push_str(&format!("# Title: {}\n", self.title));
-        // result.push_str(&format!("# Descriptions: {}\n\n", self.descriptions));
-        result.push_str(&self.text);
-        write!(f, "{}", result)
+/// A `[[wiki link]]` to a note, seen from the note it is in.
+#[derive(Clone)]
+pub struct Backlink {
+    /// title and path of the note linking here
+    pub title: String,
+    pub path: PathBuf,
+    /// line of the text with the link
+    pub line: String,
+    /// byte range of the link inside `line`
+    pub matched: (usize, usize),
+}
+
+/// Case insensitive `str::find`, returning the byte range of the match in `haystack`.
+pub fn find_ignore_case(haystack: &str, needle: &str) -> Option<(usize, usize)> {
+    if needle.is_empty() {
+        return None;
+    }
+    let needle = needle.to_lowercase();
+    // lowercasing can change the byte length of a char, so remember where each byte came from
+    let mut lowered = String::with_capacity(haystack.len());
+    let mut offsets = Vec::with_capacity(haystack.len());
+    for (i, c) in haystack.char_indices() {
+        for l in c.to_lowercase() {
+            lowered.push(l);
+            offsets.resize(lowered.len(), i);
+        }
+    }
+    let start = lowered.find(&needle)?;
+    let last = offsets[start + needle.len() - 1];
+    let last_len = haystack[last..].chars().next().map_or(0, |c| c.len_utf8());
+    Some((offsets[start], last + last_len))
+}
+
+/// Cut the beginning of a long line so the match at `matched` stays visible.
+fn snippet(line: &str, matched: (usize, usize)) -> (String, (usize, usize)) {
+    const CONTEXT: usize = 20;
+    let line = line.trim_end();
+    let prefix_len = line[..matched.0].chars().count();
+    if prefix_len <= 2 * CONTEXT {
+        return (line.to_string(), matched);
     }
+    let cut = line[..matched.0]
+        .char_indices()
+        .nth(prefix_len - CONTEXT)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let ellipsis = "…";
+    let offset = ellipsis.len();
+    (
+        String::from(ellipsis) + &line[cut..],
+        (matched.0 - cut + offset, matched.1 - cut + offset),
+    )
 }
 
-pub struct Handler<'a> {
+/// Looks up the notes a `[[wiki link]]` target refers to: those with exactly that title,
+/// falling back to a case insensitive match of the title or the file name.
+struct Titles {
+    exact: HashMap<String, Vec<usize>>,
+    /// lowercase titles and file names
+    folded: HashMap<String, Vec<usize>>,
+}
+
+impl Titles {
+    fn new(data: &[Knowledge]) -> Self {
+        let mut titles = Titles {
+            exact: HashMap::new(),
+            folded: HashMap::new(),
+        };
+        for (i, k) in data.iter().enumerate() {
+            titles.exact.entry(k.title.clone()).or_default().push(i);
+            let title = k.title.to_lowercase();
+            let stem = k
+                .path
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if stem != title {
+                titles.folded.entry(stem).or_default().push(i);
+            }
+            titles.folded.entry(title).or_default().push(i);
+        }
+        titles
+    }
+
+    /// Indices in the notes of those `target` refers to.
+    fn get(&self, target: &str) -> &[usize] {
+        self.exact
+            .get(target)
+            .or_else(|| self.folded.get(&target.to_lowercase()))
+            .map_or(&[], |notes| notes.as_slice())
+    }
+}
+
+/// Only this many results are returned by `Handler::search`, each of them reads its file for
+/// the snippet.
+const SEARCH_LIMIT: usize = 200;
+
+/// The line of `text` to show for a search result: the first one where `find` matches, or
+/// the first non-empty one.
+fn result_line<F>(text: &str, find: F) -> (String, Option<(usize, usize)>)
+where
+    F: Fn(&str) -> Option<(usize, usize)>,
+{
+    match text.lines().find_map(|l| find(l).map(|m| (l, m))) {
+        Some((l, m)) => {
+            let (l, m) = snippet(l, m);
+            (l, Some(m))
+        }
+        None => (
+            text.lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or("")
+                .to_string(),
+            None,
+        ),
+    }
+}
+
+#[derive(Default)]
+pub struct Handler {
     pub data: Vec<Knowledge>,
-    pub config: &'a Config,
+    pub config: Config,
+    /// search index of every data directory, filled by `update_index`
+    pub index: Vec<Index>,
+    /// notes linking to each note, by its path, filled by `read_all_files`
+    pub backlinks: HashMap<PathBuf, Vec<Backlink>>,
 }
 
-impl<'a> Handler<'a> {
-    pub fn new(config: &'a Config) -> Self {
+impl Handler {
+    pub fn new(config: &Config) -> Self {
         Handler {
             data: vec![],
-            config,
+            config: config.clone(),
+            index: vec![],
+            backlinks: HashMap::new(),
         }
     }
 
-    pub fn read_all_files(&mut self) {
+    pub fn read_all_files(&mut self) -> Result<()> {
+        self.data.clear();
         for dir in self.config.data_directories.iter() {
-            let mut glob_pattern = dir.clone();
-            glob_pattern = glob_pattern + "/**/" + "*." + &self.config.extension;
-            for entry in glob(&glob_pattern).expect("Failed to read glob pattern") {
-                match entry {
-                    Ok(f) => {
-                        let k = Knowledge::from_file(f);
-                        self.data.push(k);
+            let glob_pattern = format!(
+                "{}/**/*.{}",
+                Pattern::escape(dir),
+                Pattern::escape(&self.config.extension)
+            );
+            let entries = glob(&glob_pattern)
+                .map_err(|e| Error::Config(format!("{}: {}", glob_pattern, e)))?;
+            let trashed = |path: &PathBuf| trash::is_trashed(Path::new(dir), path);
+            for entry in entries.filter(|entry| !matches!(entry, Ok(path) if trashed(path))) {
+                // not valid UTF-8 or unreadable, like the index leave it out
+                if let Ok(Ok(k)) = entry.map(Knowledge::from_file) {
+                    self.data.push(k);
+                }
+            }
+        }
+        self.index_backlinks();
+        Ok(())
+    }
+
+    /// Fill `backlinks` from the `[[wiki links]]` of the notes read. Targets are matched like
+    /// `find_by_title` does, a link matching several notes is a backlink of each of them.
+    /// Several links on a line to the same note are one backlink.
+    fn index_backlinks(&mut self) {
+        let titles = Titles::new(&self.data);
+        let mut backlinks: HashMap<PathBuf, Vec<Backlink>> = HashMap::new();
+        for k in self.data.iter() {
+            for line in k.text.lines() {
+                let mut linked: Vec<usize> = vec![];
+                for ((start, end), link) in find_wiki_links(line) {
+                    for &i in titles.get(&link.target) {
+                        let note = &self.data[i];
+                        // links to the note itself or to one of its headings are left out
+                        if note.path == k.path || linked.contains(&i) {
+                            continue;
+                        }
+                        linked.push(i);
+                        let trimmed = line.trim_start();
+                        let indent = line.len() - trimmed.len();
+                        let (line, matched) = snippet(trimmed, (start - indent, end - indent));
+                        backlinks
+                            .entry(note.path.clone())
+                            .or_default()
+                            .push(Backlink {
+                                title: k.title.clone(),
+                                path: k.path.clone(),
+                                line,
+                                matched,
+                            });
                     }
-                    _ => {}
                 }
             }
         }
+        for entries in backlinks.values_mut() {
+            // stable, the links of a note stay in the order of its lines
+            entries.sort_by(|a, b| a.title.cmp(&b.title));
+        }
+        self.backlinks = backlinks;
+    }
+
+    /// The data directory `path` is in, or its parent directory if it is in none.
+    pub fn data_directory(&self, path: &Path) -> PathBuf {
+        self.config
+            .data_directories
+            .iter()
+            .map(PathBuf::from)
+            .find(|dir| path.starts_with(dir))
+            .or_else(|| path.parent().map(Path::to_path_buf))
+            .unwrap_or_default()
+    }
+
+    /// Move the note or directory at `path` to the trash of its data directory.
+    pub fn trash(&self, path: &Path) -> Result<TrashEntry> {
+        trash::move_to_trash(&self.data_directory(path), path)
+    }
+
+    /// The entries in the trash of every data directory, the most recently deleted first.
+    pub fn trash_entries(&self) -> Vec<TrashEntry> {
+        let mut entries: Vec<TrashEntry> = self
+            .config
+            .data_directories
+            .iter()
+            .flat_map(|dir| trash::entries(Path::new(dir)))
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.deleted));
+        entries
+    }
+
+    /// Purge what was deleted longer ago than the `trash_retention` of the config. Returns
+    /// how many entries were purged.
+    pub fn purge_trash(&self) -> Result<usize> {
+        if self.config.trash_retention == 0 {
+            return Ok(0);
+        }
+        let mut purged = 0;
+        for dir in self.config.data_directories.iter() {
+            purged += trash::purge_expired(Path::new(dir), self.config.trash_retention)?;
+        }
+        Ok(purged)
+    }
+
+    /// Where `plan_rename` moves the note at `from` for `name`, and its new title. Fails if
+    /// `name` can't name a note or another note is there already.
+    fn rename_target(&self, from: &Path, name: &str) -> Result<(PathBuf, String)> {
+        let name = name.trim();
+        let invalid = |reason| Error::InvalidName(name.to_owned(), reason);
+        let mut parts = vec![];
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+                _ => return Err(invalid("only directories and the note name, no . or ..")),
+            }
+        }
+        if parts.len() > 1 && parts[0] == trash::TRASH_DIR {
+            return Err(invalid("notes in the trash are left out"));
+        }
+        let title = parts.pop().ok_or_else(|| invalid("the name is empty"))?;
+        check_title(&title)?;
+        let mut to = self.data_directory(from);
+        to.extend(&parts);
+        to.push(format!("{}.{}", title, self.config.extension));
+        if to != from && to.exists() {
+            return Err(Error::Exists(title));
+        }
+        Ok((to, title))
+    }
+
+    /// The name for `plan_rename` giving the note at `from` the title `title` in the same
+    /// directory.
+    fn retitle_name(&self, from: &Path, title: &str) -> Result<String> {
+        check_title(title)?;
+        let root = self.data_directory(from);
+        let dir = from
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&root).ok())
+            .unwrap_or_else(|| Path::new(""));
+        Ok(dir.join(title).to_string_lossy().into_owned())
+    }
+
+    /// Write `knowledge`, the note at its `path` with changes, which was titled `old_title`.
+    /// The file name follows the title: if it changes the note is renamed with `plan_rename`,
+    /// rewriting the links to it. Nothing is written if the new title can't name a note or
+    /// another note has its file name. Returns where the note is now.
+    pub fn save_note(&mut self, mut knowledge: Knowledge, old_title: &str) -> Result<PathBuf> {
+        let path = knowledge.path.clone();
+        let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let title = std::mem::replace(&mut knowledge.title, old_title.to_owned());
+        if parent.join(format!("{}.{}", title, self.config.extension)) == path {
+            knowledge.title = title;
+            std::fs::write(&path, knowledge.to_content()?).map_err(Error::file(&path))?;
+            return Ok(path);
+        }
+        let name = self.retitle_name(&path, &title)?;
+        self.rename_target(&path, &name)?;
+        // the links to the note still use the old title, the rename gives it the new one
+        std::fs::write(&path, knowledge.to_content()?).map_err(Error::file(&path))?;
+        let rename = self.plan_rename(&path, &name)?;
+        rename.apply()?;
+        Ok(rename.to)
+    }
+
+    /// Move the note at `path`, whose file already has the new `title`, to the file name of
+    /// the title with `plan_rename`. Returns where the note is now.
+    pub fn retitle(&mut self, path: &Path, title: &str) -> Result<PathBuf> {
+        let name = self.retitle_name(path, title)?;
+        let rename = self.plan_rename(path, &name)?;
+        rename.apply()?;
+        Ok(rename.to)
+    }
+
+    /// Plan moving the note at `from` to `name`, a path in its data directory without the
+    /// extension like `projects/kb`. The last part of it becomes the title. Every `[[wiki link]]`
+    /// to the note gets the new title, keeping its heading and label, and every relative
+    /// markdown link to it leads to the new path. The notes are read again for it.
+    pub fn plan_rename(&mut self, from: &Path, name: &str) -> Result<Rename> {
+        let (to, title) = self.rename_target(from, name)?;
+        self.read_all_files()?;
+        let titles = Titles::new(&self.data);
+        let note = self.data.iter().position(|k| k.path == from);
+        let refers = |target: &str| matches!(note, Some(i) if titles.get(target).contains(&i));
+        let (normalized_from, normalized_to) = (normalize(from), normalize(&to));
+        let moved = (normalized_from.as_path(), normalized_to.as_path());
+        let parent = |path: &Path| normalize(path.parent().unwrap_or_else(|| Path::new("")));
+        let mut rewrites = vec![];
+        for k in self.data.iter().filter(|k| k.path != from) {
+            let dir = parent(&k.path);
+            let dirs = (dir.as_path(), dir.as_path());
+            if rewrite_links(&k.text, dirs, moved, &title, &refers).1 == 0 {
+                continue;
+            }
+            let content = std::fs::read_to_string(&k.path).map_err(Error::file(&k.path))?;
+            let (header, text) = split_text(&content);
+            let (text, links) = rewrite_links(text, dirs, moved, &title, &refers);
+            rewrites.push((k.path.clone(), String::from(header) + &text, links));
+        }
+        rewrites.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let content = std::fs::read_to_string(from).map_err(Error::file(from))?;
+        let (header, text) = split_text(&content);
+        let dirs = (parent(from), parent(&to));
+        let dirs = (dirs.0.as_path(), dirs.1.as_path());
+        let (text, own_links) = rewrite_links(text, dirs, moved, &title, &refers);
+        let mut knowledge = Knowledge::from_content(&content, from);
+        let content = if knowledge.title != title {
+            knowledge.title = title;
+            knowledge.text = text;
+            Some(knowledge.to_content()?)
+        } else if own_links > 0 {
+            Some(String::from(header) + &text)
+        } else {
+            None
+        };
+        Ok(Rename {
+            from: from.to_path_buf(),
+            to,
+            content,
+            own_links,
+            rewrites,
+        })
+    }
+
+    /// The backlinks of the note at `path`. Notes are read with `read_all_files` if they are
+    /// not loaded yet.
+    pub fn backlinks_to(&mut self, path: &Path) -> Result<&[Backlink]> {
+        if self.data.is_empty() {
+            self.read_all_files()?;
+        }
+        Ok(self
+            .backlinks
+            .get(path)
+            .map_or(&[], |entries| entries.as_slice()))
+    }
+
+    /// Rewrite every note still using the legacy header lines with a YAML front matter.
+    /// Returns the number of migrated notes.
+    pub fn migrate_legacy(&mut self) -> Result<usize> {
+        let mut migrated = 0;
+        for k in self
+            .data
+            .iter_mut()
+            .filter(|k| k.format == FrontMatterFormat::Legacy)
+        {
+            k.format = FrontMatterFormat::Yaml;
+            std::fs::write(&k.path, k.to_content()?).map_err(Error::file(&k.path))?;
+            migrated += 1;
+        }
+        Ok(migrated)
+    }
+
+    /// Load the search index of every data directory and update it with the files changed
+    /// since it was written. The in-memory index is up to date even if saving it fails.
+    pub fn update_index(&mut self) -> Result<()> {
+        if self.index.is_empty() {
+            self.index = self
+                .config
+                .data_directories
+                .iter()
+                .map(Index::load)
+                .collect();
+        }
+        let mut result = Ok(());
+        for (index, dir) in self
+            .index
+            .iter_mut()
+            .zip(self.config.data_directories.iter())
+        {
+            if index.update(&self.config.extension)? {
+                let saved = index
+                    .save()
+                    .map_err(Error::file(PathBuf::from(dir).join(index::INDEX_FILE)));
+                result = result.and(saved);
+            }
+        }
+        result
     }
+
+    /// Update the index and drop the notes loaded by `read_all_files` with their backlinks,
+    /// they are read again the next time a query needs them.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.data.clear();
+        self.backlinks.clear();
+        self.update_index()
+    }
+
+    /// Search the notes with the query language of `query::Query`. Plain words are answered
+    /// by the BM25 ranked index, queries with fields or operators are evaluated against every
+    /// note, which are read on first use.
+    pub fn search(&mut self, query: &str) -> Result<Vec<SearchResult>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        let parsed = Query::parse(query)?;
+        if parsed.is_plain() {
+            return Ok(self.search_index(query));
+        }
+        if self.data.is_empty() {
+            self.read_all_files()?;
+        }
+        let mut results: Vec<SearchResult> = self
+            .data
+            .iter()
+            .filter(|k| parsed.matches(k))
+            .map(|k| {
+                let tag = k
+                    .tag
+                    .iter()
+                    .find(|t| parsed.find_in(Field::Tag, t).is_some())
+                    .cloned();
+                let (line, matched) = result_line(&k.text, |l| parsed.find_in(Field::Body, l));
+                SearchResult {
+                    title: k.title.clone(),
+                    path: k.path.clone(),
+                    tag,
+                    line,
+                    matched,
+                    score: 0.0,
+                }
+            })
+            .collect();
+        results.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(results)
+    }
+
+    /// BM25 ranked search over title, tags and text, using the index built by `update_index`.
+    fn search_index(&self, query: &str) -> Vec<SearchResult> {
+        let words: Vec<&str> = query.split_whitespace().collect();
+        index::search(&self.index, query)
+            .into_iter()
+            .take(SEARCH_LIMIT)
+            .map(|(doc, score)| {
+                let tag = doc
+                    .tags
+                    .iter()
+                    .find(|t| words.iter().any(|w| find_ignore_case(t, w).is_some()))
+                    .cloned();
+                let text = std::fs::read_to_string(&doc.path)
+                    .map(|content| Knowledge::parse(&content).text)
+                    .unwrap_or_default();
+                let (line, matched) =
+                    result_line(&text, |l| words.iter().find_map(|w| find_ignore_case(l, w)));
+                SearchResult {
+                    title: doc.title.clone(),
+                    path: doc.path.clone(),
+                    tag,
+                    line,
+                    matched,
+                    score,
+                }
+            })
+            .collect()
+    }
+
+    /// Notes whose title is `title`, falling back to a case insensitive match of the title
+    /// or the file name. Notes are read with `read_all_files` if they are not loaded yet.
+    pub fn find_by_title(&mut self, title: &str) -> Result<Vec<&Knowledge>> {
+        if self.data.is_empty() {
+            self.read_all_files()?;
+        }
+        let titles = Titles::new(&self.data);
+        let data = &self.data;
+        Ok(titles.get(title).iter().map(|&i| &data[i]).collect())
+    }
+
     pub fn get_mapping(&self) -> HashMap<String, Vec<&Knowledge>> {
         let mut mapping: HashMap<String, Vec<&Knowledge>> = HashMap::new();
         for k in self.data.iter() {
@@ -122,3 +1345,254 @@ impl<'a> Handler<'a> {
         mapping
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `content`, write it back and parse that again.
+    fn round_trip(content: &str) -> (Knowledge, String, Knowledge) {
+        let first = Knowledge::parse(content);
+        let written = first.to_content().unwrap();
+        let second = Knowledge::parse(&written);
+        (first, written, second)
+    }
+
+    #[test]
+    fn yaml_front_matter_round_trip() {
+        let content = "---\ntitle: Rust\ntags:\n  - lang\n  - systems\ndescription: notes\n\
+                       aliases:\n  - rs\ncreated: 2026-01-02T03:04:05+00:00\nsource: book\n---\n\
+                       Rust is a systems language.\n";
+        let (first, written, second) = round_trip(content);
+        assert_eq!(first.format, FrontMatterFormat::Yaml);
+        assert_eq!(first.title, "Rust");
+        assert_eq!(first.tag, vec!["lang", "systems"]);
+        assert_eq!(first.descriptions, "notes");
+        assert_eq!(first.aliases, vec!["rs"]);
+        assert_eq!(first.created, parse_date("2026-01-02T03:04:05+00:00"));
+        assert_eq!(first.extra.get("source"), Some(&serde_json::json!("book")));
+        assert_eq!(first.text, "Rust is a systems language.\n");
+        assert!(written.starts_with("---\n"));
+        assert_eq!(second.format, FrontMatterFormat::Yaml);
+        assert_eq!(second.title, first.title);
+        assert_eq!(second.tag, first.tag);
+        assert_eq!(second.aliases, first.aliases);
+        assert_eq!(second.created, first.created);
+        assert_eq!(second.extra, first.extra);
+        assert_eq!(second.text, first.text);
+        assert_eq!(second.to_content().unwrap(), written);
+    }
+
+    #[test]
+    fn toml_front_matter_round_trip() {
+        let content = "+++\ntitle = \"deep\"\ntags = [\"systems\"]\n\
+                       updated = 2026-03-04T05:06:07Z\n+++\nA deep note.\n";
+        let (first, written, second) = round_trip(content);
+        assert_eq!(first.format, FrontMatterFormat::Toml);
+        assert_eq!(first.title, "deep");
+        assert_eq!(first.tag, vec!["systems"]);
+        assert_eq!(first.updated, parse_date("2026-03-04T05:06:07Z"));
+        assert_eq!(first.text, "A deep note.\n");
+        assert!(written.starts_with("+++\n"));
+        assert_eq!(second.format, FrontMatterFormat::Toml);
+        assert_eq!(second.title, first.title);
+        assert_eq!(second.tag, first.tag);
+        assert_eq!(second.updated, first.updated);
+        assert_eq!(second.text, first.text);
+        assert_eq!(second.to_content().unwrap(), written);
+    }
+
+    #[test]
+    fn toml_datetime_keys_stay_datetimes() {
+        let content = "+++\ntitle = \"deep\"\nreviewed = 2021-01-01T00:00:00Z\n+++\nA deep note.\n";
+        let (first, written, second) = round_trip(content);
+        assert!(written.contains("reviewed = 2021-01-01T00:00:00Z\n"));
+        assert!(!written.contains(TOML_DATETIME));
+        assert_eq!(second.extra, first.extra);
+        assert_eq!(second.to_content().unwrap(), written);
+    }
+
+    #[test]
+    fn empty_title_is_not_written() {
+        let content = "---\ntags:\n  - a\n---\nbody\n";
+        let (first, written, _) = round_trip(content);
+        assert_eq!(first.title, "");
+        assert!(!written.contains("title"));
+    }
+
+    #[test]
+    fn set_updated_changes_only_that_key() {
+        let date = parse_date("2026-05-06T07:08:09+00:00").unwrap();
+        let written = date.to_rfc3339_opts(SecondsFormat::Secs, false);
+        let yaml = "---\n# kept\ntitle: a\nupdated: 2020-01-01T00:00:00+00:00\ntags: [x]\n\
+                    ---\nupdated: text\n";
+        let expected = yaml.replacen("2020-01-01T00:00:00+00:00", &written, 1);
+        assert_eq!(set_updated(yaml, &date).unwrap(), expected);
+        let toml = "+++\ntitle = \"a\"\n[extra]\nupdated = 1\n+++\nbody\n";
+        let expected = format!("+++\nupdated = {}\n{}", written, &toml[4..]);
+        assert_eq!(set_updated(toml, &date).unwrap(), expected);
+        assert_eq!(set_updated("just text\n", &date), None);
+        assert_eq!(set_updated("# Title: old\n\nbody\n", &date), None);
+        assert_eq!(set_updated("---\n: [\n---\nbroken\n", &date), None);
+    }
+
+    #[test]
+    fn legacy_header_is_written_back_as_yaml() {
+        let content = "# Title: old note\n# Descriptions: from before\n# Tags: a, b\n\nbody\n";
+        let (first, written, second) = round_trip(content);
+        assert_eq!(first.format, FrontMatterFormat::Legacy);
+        assert_eq!(first.title, "old note");
+        assert_eq!(first.descriptions, "from before");
+        assert_eq!(first.tag, vec!["a", "b"]);
+        assert_eq!(first.text, "body\n");
+        assert!(written.starts_with("---\n"));
+        assert_eq!(second.format, FrontMatterFormat::Yaml);
+        assert_eq!(second.title, first.title);
+        assert_eq!(second.descriptions, first.descriptions);
+        assert_eq!(second.tag, first.tag);
+        assert_eq!(second.text, first.text);
+    }
+
+    #[test]
+    fn plain_text_stays_plain() {
+        let content = "just text\n---\nnot front matter\n";
+        let (first, written, _) = round_trip(content);
+        assert_eq!(first.format, FrontMatterFormat::Plain);
+        assert_eq!(first.text, content);
+        assert_eq!(written, content);
+    }
+
+    #[test]
+    fn invalid_front_matter_is_kept_as_text() {
+        let content = "---\ntitle: [unclosed\n---\ntext\n";
+        let knowledge = Knowledge::parse(content);
+        assert_eq!(knowledge.format, FrontMatterFormat::Plain);
+        assert_eq!(knowledge.text, content);
+    }
+
+    #[test]
+    fn links_in_order_without_duplicates() {
+        let knowledge = Knowledge::parse(
+            "see [[Rust#Intro|the rust note]] and [docs](a%20b.md \"title\")\n\
+             then [[python]], [[#local]], [again](python) and [[Rust]]\n",
+        );
+        assert_eq!(knowledge.links(), vec!["Rust", "a%20b.md", "python"]);
+    }
+
+    /// Rewrite the links of a note in `notes` to `notes/old.md`, titled Old, for its move
+    /// to `notes/new.md` titled New.
+    fn rewrite(text: &str) -> (String, usize) {
+        let dir = Path::new("notes");
+        let moved = (Path::new("notes/old.md"), Path::new("notes/new.md"));
+        let refers = |target: &str| target.eq_ignore_ascii_case("old");
+        rewrite_links(text, (dir, dir), moved, "New", &refers)
+    }
+
+    #[test]
+    fn rewrite_links_keeps_headings_and_labels() {
+        assert_eq!(
+            rewrite("[[Old]] and [[old]]"),
+            ("[[New]] and [[New]]".into(), 2)
+        );
+        assert_eq!(
+            rewrite("[[Old|the old one]]"),
+            ("[[New|the old one]]".into(), 1)
+        );
+        assert_eq!(rewrite("[[Old#Usage]]"), ("[[New#Usage]]".into(), 1));
+        assert_eq!(
+            rewrite("[[ OLD #Usage|see]]"),
+            ("[[New#Usage|see]]".into(), 1)
+        );
+        assert_eq!(
+            rewrite("[[Older]] [[New]]"),
+            ("[[Older]] [[New]]".into(), 0)
+        );
+        assert_eq!(
+            rewrite("[a](old.md) [b](./old.md#top \"title\") [c](other.md) [d](https://x/old.md)"),
+            (
+                "[a](new.md) [b](new.md#top \"title\") [c](other.md) [d](https://x/old.md)".into(),
+                2
+            )
+        );
+    }
+
+    #[test]
+    fn rewrite_links_leaves_code_alone() {
+        assert_eq!(
+            rewrite("`[[Old]]` ``a ` [[Old]]`` [[Old]] `[b](old.md)`"),
+            ("`[[Old]]` ``a ` [[Old]]`` [[New]] `[b](old.md)`".into(), 1)
+        );
+        // a backtick nobody closes is text
+        assert_eq!(rewrite("`[[Old]]"), ("`[[New]]".into(), 1));
+        let text = "```rust\n[[Old]]\n```\n[[Old]]\n~~~~\n[a](old.md)\n~~~\nstill code [[Old]]\n";
+        let expected = text.replacen("[[Old]]\n~", "[[New]]\n~", 1);
+        assert_eq!(rewrite(text), (expected, 1));
+    }
+
+    #[test]
+    fn failed_rename_changes_nothing() {
+        let dir = std::env::temp_dir().join(format!("kb-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let from = dir.join("old.md");
+        let linking = dir.join("linking.md");
+        std::fs::write(&from, "old").unwrap();
+        std::fs::write(&linking, "[[old]]").unwrap();
+        let rename = Rename {
+            from: from.clone(),
+            to: dir.join("new.md"),
+            content: Some("new".into()),
+            own_links: 0,
+            rewrites: vec![
+                (linking.clone(), "[[new]]".into(), 1),
+                (dir.join("missing").join("note.md"), "[[new]]".into(), 1),
+            ],
+        };
+        assert!(rename.apply().is_err());
+        let mut left: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["linking.md", "old.md"]);
+        assert_eq!(std::fs::read_to_string(&linking).unwrap(), "[[old]]");
+
+        let rename = Rename {
+            rewrites: rename.rewrites[..1].to_vec(),
+            ..rename
+        };
+        rename.apply().unwrap();
+        assert!(!from.exists());
+        assert_eq!(std::fs::read_to_string(dir.join("new.md")).unwrap(), "new");
+        assert_eq!(std::fs::read_to_string(&linking).unwrap(), "[[new]]");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrate_legacy_rewrites_legacy_notes() {
+        let dir = std::env::temp_dir().join(format!("kb-migrate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("old.md");
+        let yaml = dir.join("new.md");
+        std::fs::write(&legacy, "# Title: old\n# Tags: a\n\nbody\n").unwrap();
+        std::fs::write(&yaml, "---\ntitle: new\n---\nbody\n").unwrap();
+        let config = Config {
+            data_directories: vec![dir.to_string_lossy().into_owned()],
+            extension: "md".into(),
+            ..Config::default()
+        };
+        let mut handler = Handler::new(&config);
+        handler.read_all_files().unwrap();
+        assert_eq!(handler.migrate_legacy().unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&legacy).unwrap(),
+            "---\ntitle: old\ntags:\n  - a\n---\nbody\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&yaml).unwrap(),
+            "---\ntitle: new\n---\nbody\n"
+        );
+        handler.read_all_files().unwrap();
+        assert_eq!(handler.migrate_legacy().unwrap(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::data::{set_updated, Handler, Knowledge};
+use crate::error::{Error, Result};
+use chrono::Local;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The editor notes are opened with: `$VISUAL`, then `$EDITOR`, then vi.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"))
+}
+
+/// Open `path` in the editor of the user and wait until it exits. The terminal must be in
+/// its normal mode.
+pub fn open(path: &Path) -> Result<()> {
+    let editor = editor();
+    // the variable may carry arguments, like "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|e| Error::Editor(format!("failed to start {}: {}", editor, e)))?;
+    if !status.success() {
+        return Err(Error::Editor(format!("{} exited with {}", editor, status)));
+    }
+    Ok(())
+}
+
+/// Edit the note at `path` in the editor of the user, then re-read it. If it changed and has
+/// a front matter its `updated` key gets the current time, the rest of the file is kept as
+/// written. It is renamed with the links to it if the title was changed. A title naming
+/// another note is refused, the note then keeps its file name. Returns where the note is now.
+pub fn edit_note(handler: &mut Handler, path: &Path) -> Result<PathBuf> {
+    let before = std::fs::read(path).map_err(Error::file(path))?;
+    open(path)?;
+    let after = std::fs::read(path).map_err(Error::file(path))?;
+    if before == after {
+        return Ok(path.to_path_buf());
+    }
+    if let Some(content) = set_updated(&String::from_utf8_lossy(&after), &Local::now()) {
+        std::fs::write(path, content).map_err(Error::file(path))?;
+    }
+    let old_title = Knowledge::from_content(&String::from_utf8_lossy(&before), path).title;
+    let knowledge = Knowledge::from_file(path)?;
+    if knowledge.title == old_title {
+        return Ok(path.to_path_buf());
+    }
+    handler.retitle(path, &knowledge.title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn edited_notes_keep_their_text() {
+        let dir = std::env::temp_dir().join(format!("kb-editor-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // an editor appending a line to the file
+        let script = dir.join("append.sh");
+        std::fs::write(&script, "#!/bin/sh\necho more >> \"$1\"\n").unwrap();
+        Command::new("chmod")
+            .arg("+x")
+            .arg(&script)
+            .status()
+            .unwrap();
+        std::env::set_var("VISUAL", &script);
+        let config = Config {
+            data_directories: vec![dir.to_string_lossy().into_owned()],
+            extension: "md".into(),
+            ..Config::default()
+        };
+        let mut handler = Handler::new(&config);
+
+        let plain = dir.join("plain.md");
+        std::fs::write(&plain, "just text\n").unwrap();
+        assert_eq!(edit_note(&mut handler, &plain).unwrap(), plain);
+        assert_eq!(
+            std::fs::read_to_string(&plain).unwrap(),
+            "just text\nmore\n"
+        );
+
+        let yaml = dir.join("yaml.md");
+        std::fs::write(&yaml, "---\n# kept\ntitle: yaml\ntags: [a]\n---\nbody\n").unwrap();
+        assert_eq!(edit_note(&mut handler, &yaml).unwrap(), yaml);
+        let content = std::fs::read_to_string(&yaml).unwrap();
+        assert!(content.starts_with("---\nupdated: "));
+        assert!(content.ends_with("\n# kept\ntitle: yaml\ntags: [a]\n---\nbody\nmore\n"));
+        assert!(Knowledge::parse(&content).updated.is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
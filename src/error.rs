@@ -0,0 +1,114 @@
+use crate::query::QueryError;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong in the knowledge base. The TUI shows these in an error
+/// dialog, the command line prints them and exits.
+#[derive(Debug)]
+pub enum Error {
+    /// I/O not tied to a file
+    Io(std::io::Error),
+    /// setting up or drawing the terminal failed
+    Terminal(crossterm::ErrorKind),
+    /// reading, writing or removing a file or directory failed
+    File(PathBuf, std::io::Error),
+    /// the config file is missing a key or has the wrong type
+    Config(String),
+    Query(QueryError),
+    /// no note is titled like this
+    NotFound(String),
+    /// several notes are titled like this
+    Ambiguous(String, Vec<PathBuf>),
+    /// a note titled like this already exists
+    Exists(String),
+    /// a note can't be named like this, and why
+    InvalidName(String, &'static str),
+    /// restoring from the trash would overwrite this
+    Occupied(PathBuf),
+    Json(serde_json::Error),
+    /// the front matter of a note could not be written
+    FrontMatter(String),
+    /// the external editor could not be started or failed
+    Editor(String),
+    /// the editor has no field to type in selected
+    NoInput,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// For `map_err`, attach the path the I/O error happened on.
+    pub fn file<P: Into<PathBuf>>(path: P) -> impl FnOnce(std::io::Error) -> Error {
+        let path = path.into();
+        move |e| Error::File(path, e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Terminal(e) => write!(f, "terminal: {}", e),
+            Error::File(path, e) => write!(f, "{}: {}", path.display(), e),
+            Error::Config(message) => write!(f, "invalid config: {}", message),
+            Error::Query(e) => write!(f, "{}", e),
+            Error::NotFound(title) => write!(f, "no note titled {:?}", title),
+            Error::Ambiguous(title, paths) => {
+                let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "{} notes titled {:?}: {}",
+                    paths.len(),
+                    title,
+                    paths.join(", ")
+                )
+            }
+            Error::Exists(title) => write!(f, "a note titled {:?} already exists", title),
+            Error::InvalidName(name, reason) => {
+                write!(f, "can't name a note {:?}: {}", name, reason)
+            }
+            Error::Occupied(path) => {
+                write!(f, "can't restore, {} already exists", path.display())
+            }
+            Error::Json(e) => write!(f, "failed to serialize: {}", e),
+            Error::FrontMatter(message) => write!(f, "can't write the front matter: {}", message),
+            Error::Editor(message) => write!(f, "editor: {}", message),
+            Error::NoInput => write!(f, "no field of the editor is selected"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) | Error::File(_, e) => Some(e),
+            Error::Terminal(e) => Some(e),
+            Error::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<crossterm::ErrorKind> for Error {
+    fn from(e: crossterm::ErrorKind) -> Self {
+        Error::Terminal(e)
+    }
+}
+
+impl From<QueryError> for Error {
+    fn from(e: QueryError) -> Self {
+        Error::Query(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -0,0 +1,141 @@
+// scores of a fuzzy match, higher is better
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 8;
+const BONUS_BOUNDARY: i64 = 10;
+const PENALTY_GAP: i64 = 1;
+
+fn is_boundary(previous: Option<char>, c: char) -> bool {
+    match previous {
+        None => true,
+        Some(p) => {
+            matches!(p, '/' | '\\' | '-' | '_' | ' ' | '.')
+                || (p.is_lowercase() && c.is_uppercase())
+        }
+    }
+}
+
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Match `pattern` as a case insensitive subsequence of `candidate`, like fzf. Returns the
+/// score and the char indices of `candidate` that matched, or `None` if it doesn't match.
+///
+/// The first occurrence of the subsequence is found scanning forward, then the match is
+/// tightened by scanning backward from its end, so `kb` in `knowledge/kb.md` highlights `kb`
+/// and not the `k` of `knowledge`.
+pub fn fuzzy_match(pattern: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return Some((0, vec![]));
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+
+    let mut p = 0;
+    let mut end = None;
+    for (i, c) in chars.iter().enumerate() {
+        if eq_ignore_case(*c, pattern[p]) {
+            p += 1;
+            if p == pattern.len() {
+                end = Some(i);
+                break;
+            }
+        }
+    }
+    let end = end?;
+
+    let mut indices = Vec::with_capacity(pattern.len());
+    let mut p = pattern.len();
+    for i in (0..=end).rev() {
+        if eq_ignore_case(chars[i], pattern[p - 1]) {
+            indices.push(i);
+            p -= 1;
+            if p == 0 {
+                break;
+            }
+        }
+    }
+    indices.reverse();
+
+    let mut score = 0;
+    for (n, i) in indices.iter().enumerate() {
+        score += SCORE_MATCH;
+        let previous = if *i == 0 { None } else { Some(chars[i - 1]) };
+        if is_boundary(previous, chars[*i]) {
+            score += BONUS_BOUNDARY;
+        }
+        if n > 0 {
+            let gap = (i - indices[n - 1] - 1) as i64;
+            if gap == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= PENALTY_GAP * gap.min(10);
+            }
+        }
+    }
+    // prefer shorter candidates on equal matches
+    score -= (chars.len() as i64) / 16;
+    Some((score, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(pattern: &str, candidate: &str) -> i64 {
+        fuzzy_match(pattern, candidate).unwrap().0
+    }
+
+    #[test]
+    fn non_matches() {
+        assert_eq!(fuzzy_match("xyz", "abc"), None);
+        // the characters have to come in order
+        assert_eq!(fuzzy_match("ba", "ab"), None);
+        assert_eq!(fuzzy_match("abc", "ab"), None);
+        assert_eq!(fuzzy_match("a", ""), None);
+    }
+
+    #[test]
+    fn matches_ignore_case_and_spaces() {
+        assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+        assert_eq!(fuzzy_match("KB", "kb"), fuzzy_match("kb", "kb"));
+        assert_eq!(fuzzy_match("k b", "kb"), fuzzy_match("kb", "kb"));
+        assert_eq!(fuzzy_match("é", "CAFÉ").map(|m| m.1), Some(vec![3]));
+    }
+
+    #[test]
+    fn scoring() {
+        // two matches at the start, the second right after the first
+        let expected = 2 * SCORE_MATCH + BONUS_BOUNDARY + BONUS_CONSECUTIVE;
+        assert_eq!(score("ab", "ab"), expected);
+        // a gap costs for every character skipped
+        assert_eq!(
+            score("ab", "axxb"),
+            2 * SCORE_MATCH + BONUS_BOUNDARY - 2 * PENALTY_GAP
+        );
+        assert!(score("ab", "abx") > score("ab", "axb"));
+        // shorter candidates first
+        assert!(score("a", "a") > score("a", &"a".repeat(40)));
+    }
+
+    #[test]
+    fn word_boundaries_score_higher() {
+        assert!(score("fb", "foo_bar") > score("fb", "foobar"));
+        assert!(score("fb", "foo/bar") > score("fb", "foobar"));
+        assert!(score("fb", "foo-bar") > score("fb", "foobar"));
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+        assert!(score("rs", "rust/syntax.md") > score("rs", "cursor.md"));
+    }
+
+    #[test]
+    fn match_is_tightened_to_its_end() {
+        assert_eq!(
+            fuzzy_match("kb", "knowledge/kb.md").map(|m| m.1),
+            Some(vec![10, 11])
+        );
+        assert_eq!(
+            fuzzy_match("ace", "abcde").map(|m| m.1),
+            Some(vec![0, 2, 4])
+        );
+    }
+}
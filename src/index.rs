@@ -0,0 +1,461 @@
+use crate::data::Knowledge;
+use crate::error::{Error, Result};
+use crate::trash;
+use glob::{glob, Pattern};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// File name of the index inside every data directory.
+pub static INDEX_FILE: &str = ".kb-index.json";
+
+/// Bumped whenever the layout of `Index` or the tokenizer changes, forcing a rebuild.
+const INDEX_VERSION: u32 = 1;
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+// a term in the title or tags counts as if it appeared this many times in the text
+const TITLE_WEIGHT: u32 = 3;
+const TAG_WEIGHT: u32 = 2;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Document {
+    pub path: PathBuf,
+    pub title: String,
+    pub tags: Vec<String>,
+    /// modification time in milliseconds since the epoch
+    pub mtime: u64,
+    pub size: u64,
+    /// FNV-1a hash of the file content, to skip files that were touched but not changed
+    pub hash: u64,
+    /// weighted number of terms, the document length for BM25
+    pub len: u32,
+}
+
+/// Inverted index of the notes in one data directory.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct Index {
+    version: u32,
+    #[serde(skip)]
+    root: PathBuf,
+    next_id: u32,
+    docs: HashMap<u32, Document>,
+    /// term -> (document id, weighted term frequency)
+    terms: BTreeMap<String, Vec<(u32, u32)>>,
+    total_len: u64,
+    #[serde(skip)]
+    ids: HashMap<PathBuf, u32>,
+}
+
+/// Split text into lowercase terms. Runs of letters and digits form one term, except for
+/// CJK characters which are indexed one by one since they are not separated by spaces.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = vec![];
+    let mut current = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !current.is_empty() {
+                terms.push(std::mem::take(&mut current));
+            }
+            terms.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            current.extend(c.to_lowercase());
+        } else if !current.is_empty() {
+            terms.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// (modification time in milliseconds, size) of a file
+fn stat(path: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    Ok((modified, metadata.len()))
+}
+
+impl Index {
+    /// Load the index stored in `root`, or start an empty one if it is missing, unreadable
+    /// or from another version.
+    pub fn load<T: Into<PathBuf>>(root: T) -> Self {
+        let root = root.into();
+        let mut index = std::fs::read_to_string(root.join(INDEX_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str::<Index>(&s).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default();
+        index.version = INDEX_VERSION;
+        index.ids = index
+            .docs
+            .iter()
+            .map(|(id, doc)| (doc.path.clone(), *id))
+            .collect();
+        index.root = root;
+        index
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        // write next to the index and rename, so an interrupted write never leaves half a file
+        let tmp = self.root.join(String::from(INDEX_FILE) + ".tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(&tmp, self.root.join(INDEX_FILE))
+    }
+
+    /// Bring the index up to date with the files in the data directory. Only files whose
+    /// modification time or size changed are read. Returns whether anything changed.
+    pub fn update(&mut self, extension: &str) -> Result<bool> {
+        let pattern = format!(
+            "{}/**/*.{}",
+            Pattern::escape(&self.root.to_string_lossy()),
+            Pattern::escape(extension)
+        );
+        let paths = glob(&pattern).map_err(|e| Error::Config(format!("{}: {}", pattern, e)))?;
+        let mut seen = HashSet::new();
+        let mut stale = HashSet::new();
+        let mut changed = false;
+        let root = self.root.clone();
+        for path in paths
+            .filter_map(std::result::Result::ok)
+            .filter(|path| !trash::is_trashed(&root, path))
+        {
+            seen.insert(path.clone());
+            let (modified, size) = match stat(&path) {
+                Ok(stat) => stat,
+                // removed since the glob, the next update drops it from the index
+                Err(_) => continue,
+            };
+            let id = self.ids.get(&path).copied();
+            if let Some(doc) = id.and_then(|id| self.docs.get(&id)) {
+                if doc.mtime == modified && doc.size == size {
+                    continue;
+                }
+            }
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                // not valid UTF-8 or unreadable, leave it out of the index
+                Err(_) => continue,
+            };
+            let hash = fnv1a(content.as_bytes());
+            changed = true;
+            if let Some(id) = id {
+                let doc = self.docs.get_mut(&id).unwrap();
+                if doc.hash == hash {
+                    doc.mtime = modified;
+                    doc.size = size;
+                    continue;
+                }
+                self.remove_document(id);
+                stale.insert(id);
+            }
+            self.add_document(
+                Knowledge::from_content(&content, &path),
+                modified,
+                size,
+                hash,
+            );
+        }
+        let removed: Vec<u32> = self
+            .ids
+            .iter()
+            .filter(|(path, _)| !seen.contains(*path))
+            .map(|(_, id)| *id)
+            .collect();
+        for id in removed {
+            self.remove_document(id);
+            stale.insert(id);
+        }
+        if !stale.is_empty() {
+            changed = true;
+            // one pass over the postings for every removed document
+            for postings in self.terms.values_mut() {
+                postings.retain(|(id, _)| !stale.contains(id));
+            }
+            self.terms.retain(|_, postings| !postings.is_empty());
+        }
+        Ok(changed)
+    }
+
+    fn add_document(&mut self, knowledge: Knowledge, mtime: u64, size: u64, hash: u64) {
+        let mut frequencies: HashMap<String, u32> = HashMap::new();
+        for term in tokenize(&knowledge.text) {
+            *frequencies.entry(term).or_default() += 1;
+        }
+        for term in tokenize(&knowledge.title) {
+            *frequencies.entry(term).or_default() += TITLE_WEIGHT;
+        }
+        for term in knowledge.tag.iter().flat_map(|t| tokenize(t)) {
+            *frequencies.entry(term).or_default() += TAG_WEIGHT;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let len = frequencies.values().sum();
+        for (term, frequency) in frequencies {
+            self.terms.entry(term).or_default().push((id, frequency));
+        }
+        self.total_len += len as u64;
+        self.ids.insert(knowledge.path.clone(), id);
+        self.docs.insert(
+            id,
+            Document {
+                path: knowledge.path,
+                title: knowledge.title,
+                tags: knowledge.tag,
+                mtime,
+                size,
+                hash,
+                len,
+            },
+        );
+    }
+
+    /// Forget a document. Its postings are left for the caller to sweep in one pass.
+    fn remove_document(&mut self, id: u32) {
+        if let Some(doc) = self.docs.remove(&id) {
+            self.total_len -= doc.len as u64;
+            self.ids.remove(&doc.path);
+        }
+    }
+
+    pub fn documents(&self) -> impl Iterator<Item = &Document> {
+        self.docs.values()
+    }
+}
+
+/// Rank the documents of `indexes` against `query` with BM25. Every query term has to match.
+/// The last term is also matched as a prefix unless the query ends with a space, so results
+/// show up while the word is being typed.
+pub fn search<'a>(indexes: &'a [Index], query: &str) -> Vec<(&'a Document, f64)> {
+    let terms = tokenize(query);
+    let doc_count: usize = indexes.iter().map(|e| e.docs.len()).sum();
+    if terms.is_empty() || doc_count == 0 {
+        return vec![];
+    }
+    let total_len: u64 = indexes.iter().map(|e| e.total_len).sum();
+    let average_len = (total_len as f64 / doc_count as f64).max(1.0);
+    let prefix = !query.ends_with(char::is_whitespace);
+
+    let mut scores: HashMap<(usize, u32), (f64, usize)> = HashMap::new();
+    for (i, term) in terms.iter().enumerate() {
+        let expansions: Vec<&String> = if prefix && i + 1 == terms.len() {
+            let mut expansions: Vec<&String> = indexes
+                .iter()
+                .flat_map(|index| {
+                    index
+                        .terms
+                        .range::<String, _>(term..)
+                        .take_while(move |(t, _)| t.starts_with(term.as_str()))
+                        .map(|(t, _)| t)
+                })
+                .collect();
+            expansions.sort();
+            expansions.dedup();
+            expansions
+        } else {
+            vec![term]
+        };
+        // a document matching several expansions of a prefix only counts its best one
+        let mut term_scores: HashMap<(usize, u32), f64> = HashMap::new();
+        for expansion in expansions {
+            let postings: Vec<(usize, &Vec<(u32, u32)>)> = indexes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, index)| index.terms.get(expansion).map(|p| (i, p)))
+                .collect();
+            let df: usize = postings.iter().map(|(_, p)| p.len()).sum();
+            let idf = (1.0 + (doc_count as f64 - df as f64 + 0.5) / (df as f64 + 0.5)).ln();
+            for (index_id, postings) in postings {
+                for (doc_id, frequency) in postings {
+                    let len = indexes[index_id].docs[doc_id].len as f64;
+                    let tf = *frequency as f64;
+                    let score =
+                        idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / average_len));
+                    let best = term_scores.entry((index_id, *doc_id)).or_default();
+                    *best = best.max(score);
+                }
+            }
+        }
+        for (key, score) in term_scores {
+            let entry = scores.entry(key).or_default();
+            entry.0 += score;
+            entry.1 += 1;
+        }
+    }
+
+    let mut results: Vec<(&Document, f64)> = scores
+        .into_iter()
+        .filter(|(_, (_, matched))| *matched == terms.len())
+        .map(|((index_id, doc_id), (score, _))| (&indexes[index_id].docs[&doc_id], score))
+        .collect();
+    results.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.title.cmp(&b.0.title))
+    });
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    /// An empty data directory for the test `name`.
+    fn data_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kb-index-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn set_mtime(path: &Path, millis: u64) {
+        let file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_millis(millis))
+            .unwrap();
+    }
+
+    /// The titles `query` finds in `index`, best first.
+    fn titles(index: &Index, query: &str) -> Vec<String> {
+        search(std::slice::from_ref(index), query)
+            .into_iter()
+            .map(|(doc, _)| doc.title.clone())
+            .collect()
+    }
+
+    fn score(index: &Index, query: &str, title: &str) -> f64 {
+        search(std::slice::from_ref(index), query)
+            .into_iter()
+            .find(|(doc, _)| doc.title == title)
+            .map(|(_, score)| score)
+            .unwrap()
+    }
+
+    #[test]
+    fn update_reads_only_changed_files() {
+        let dir = data_dir("update");
+        let (a, b) = (dir.join("a.md"), dir.join("b.md"));
+        std::fs::write(&a, "alpha").unwrap();
+        std::fs::write(&b, "beta").unwrap();
+        let mut index = Index::load(&dir);
+        assert!(index.update("md").unwrap());
+        assert_eq!(index.documents().count(), 2);
+        assert!(!index.update("md").unwrap());
+
+        // touched but not changed: the new time is kept without reading it again
+        set_mtime(&a, 1_000);
+        assert!(index.update("md").unwrap());
+        assert_eq!(index.next_id, 2);
+        assert_eq!(index.docs[&index.ids[&a]].mtime, 1_000);
+        assert!(!index.update("md").unwrap());
+
+        // same size, another time and content
+        std::fs::write(&a, "gamma").unwrap();
+        set_mtime(&a, 2_000);
+        assert!(index.update("md").unwrap());
+        assert_eq!(index.next_id, 3);
+        assert_eq!(titles(&index, "alpha "), Vec::<String>::new());
+        assert_eq!(titles(&index, "gamma "), vec!["a"]);
+
+        // same time, another size
+        std::fs::write(&a, "delta epsilon").unwrap();
+        set_mtime(&a, 2_000);
+        assert!(index.update("md").unwrap());
+        assert_eq!(titles(&index, "gamma "), Vec::<String>::new());
+        assert_eq!(titles(&index, "epsilon "), vec!["a"]);
+
+        std::fs::remove_file(&b).unwrap();
+        assert!(index.update("md").unwrap());
+        assert_eq!(index.documents().count(), 1);
+        assert!(!index.terms.contains_key("beta"));
+        // two words of text and the title
+        assert_eq!(index.total_len, 2 + TITLE_WEIGHT as u64);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn update_skips_the_trash_and_saves() {
+        let dir = data_dir("trash");
+        std::fs::create_dir_all(dir.join(".trash")).unwrap();
+        std::fs::write(dir.join("kept.md"), "note").unwrap();
+        std::fs::write(dir.join(".trash").join("gone.md"), "note").unwrap();
+        let mut index = Index::load(&dir);
+        index.update("md").unwrap();
+        assert_eq!(titles(&index, "note "), vec!["kept"]);
+        index.save().unwrap();
+        let loaded = Index::load(&dir);
+        assert_eq!(titles(&loaded, "note "), vec!["kept"]);
+        assert_eq!(loaded.ids.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bm25_ranking() {
+        let dir = data_dir("bm25");
+        std::fs::write(dir.join("once.md"), "rust and some other words here").unwrap();
+        std::fs::write(dir.join("twice.md"), "rust rust and some other words").unwrap();
+        std::fs::write(dir.join("short.md"), "rust go").unwrap();
+        std::fs::write(dir.join("rust.md"), "a title match").unwrap();
+        std::fs::write(dir.join("none.md"), "nothing to see").unwrap();
+        let mut index = Index::load(&dir);
+        index.update("md").unwrap();
+        let found = titles(&index, "rust ");
+        assert_eq!(found.len(), 4);
+        // the title weighs three times the text
+        assert_eq!(found[0], "rust");
+        // more occurrences and shorter notes rank higher
+        assert!(score(&index, "rust ", "twice") > score(&index, "rust ", "once"));
+        assert!(score(&index, "rust ", "short") > score(&index, "rust ", "once"));
+        // every term has to match
+        assert_eq!(titles(&index, "rust go "), vec!["short"]);
+        assert_eq!(titles(&index, "rust python "), Vec::<String>::new());
+        // rarer terms weigh more
+        assert!(score(&index, "go ", "short") > score(&index, "rust ", "short"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn last_term_is_a_prefix() {
+        let dir = data_dir("prefix");
+        std::fs::write(dir.join("rust.md"), "about rust").unwrap();
+        std::fs::write(dir.join("ruby.md"), "about ruby").unwrap();
+        std::fs::write(dir.join("both.md"), "rust ruby").unwrap();
+        std::fs::write(dir.join("run.md"), "about running").unwrap();
+        let mut index = Index::load(&dir);
+        index.update("md").unwrap();
+        let mut found = titles(&index, "ru");
+        found.sort();
+        assert_eq!(found, vec!["both", "ruby", "run", "rust"]);
+        assert_eq!(titles(&index, "rub"), vec!["ruby", "both"]);
+        // a space ends the word
+        assert_eq!(titles(&index, "ru "), Vec::<String>::new());
+        // only the last term is a prefix
+        assert_eq!(titles(&index, "ab ru"), Vec::<String>::new());
+        assert_eq!(titles(&index, "about ru").len(), 3);
+        // a note matching several expansions counts its best one
+        let best = score(&index, "rust ", "both").max(score(&index, "ruby ", "both"));
+        assert_eq!(score(&index, "ru", "both"), best);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
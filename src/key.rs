@@ -1,5 +1,7 @@
 use crossterm::event;
+use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
 
 /// Represents an key.
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
@@ -66,9 +68,29 @@ pub enum Key {
     Char(char),
     Ctrl(CtrlKey),
     Alt(char),
+    /// movement key with Shift, extending the selection
+    Shift(ShiftKey),
+    /// movement key with Ctrl and Shift, extending the selection by a word
+    CtrlShift(ShiftKey),
     Unknown,
 }
 
+#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+pub enum ShiftKey {
+    /// Left arrow
+    Left,
+    /// Right arrow
+    Right,
+    /// Up arrow
+    Up,
+    /// Down arrow
+    Down,
+    /// Home key
+    Home,
+    /// End key
+    End,
+}
+
 #[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
 pub enum CtrlKey {
     Enter,
@@ -110,11 +132,7 @@ pub enum CtrlKey {
 impl Key {
     /// Returns the function key corresponding to the given number
     ///
-    /// 1 -> F1, etc...
-    ///
-    /// # Panics
-    ///
-    /// If `n == 0 || n > 12`
+    /// 1 -> F1, etc... Keys above F12 are `Key::Unknown`.
     pub fn from_f(n: u8) -> Key {
         match n {
             0 => Key::F0,
@@ -130,31 +148,179 @@ impl Key {
             10 => Key::F10,
             11 => Key::F11,
             12 => Key::F12,
-            _ => panic!("unknown function key: F{}", n),
+            _ => Key::Unknown,
         }
     }
 }
 
 impl fmt::Display for Key {
+    /// Keys are written the way the `[keys]` section of the config takes them: `j`,
+    /// `<Space>`, `<Ctrl+g>`, `<Shift+Left>`.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Key::Alt(' ') => write!(f, "<Alt+Space>"),
             Key::Char(' ') => write!(f, "<Space>"),
+            // a sequence like <lt>a> is not taken for the key <a>
+            Key::Char('<') => write!(f, "<lt>"),
             Key::Alt(c) => write!(f, "<Alt+{}>", c),
+            Key::Ctrl(CtrlKey::Char(' ')) => write!(f, "<Ctrl+Space>"),
             Key::Ctrl(CtrlKey::Char(c)) => write!(f, "<Ctrl+{}>", c),
+            Key::Ctrl(key) => write!(f, "<Ctrl+{:?}>", key),
+            Key::Shift(key) => write!(f, "<Shift+{:?}>", key),
+            Key::CtrlShift(key) => write!(f, "<Ctrl+Shift+{:?}>", key),
             Key::Char(c) => write!(f, "{}", c),
-            Key::Left | Key::Right | Key::Up | Key::Down => write!(f, "<{:?} Arrow Key>", self),
-            Key::Enter
-            | Key::Tab
-            | Key::Backspace
-            | Key::Esc
-            | Key::Ins
-            | Key::Delete
-            | Key::Home
-            | Key::End
-            | Key::PageUp
-            | Key::PageDown => write!(f, "<{:?}>", self),
-            _ => write!(f, "{:?}", self),
+            _ => write!(f, "<{:?}>", self),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    /// Parse a key written like `Display` writes it. Names and modifiers are case
+    /// insensitive, `<Ctrl+G>` is the same as `<Ctrl+g>`.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let unknown = || format!("unknown key {:?}", s);
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key::Char(c));
+        }
+        let inner = s
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or_else(unknown)?;
+        // the key itself may be a plus: <Ctrl++>
+        let (modifiers, name) = match inner.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => match inner.rfind('+') {
+                Some(i) => (&inner[..i], &inner[i + 1..]),
+                None => ("", inner),
+            },
+        };
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        for modifier in modifiers.split('+').filter(|m| !m.is_empty()) {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" | "c" => ctrl = true,
+                "alt" | "meta" | "a" | "m" => alt = true,
+                "shift" | "s" => shift = true,
+                _ => return Err(unknown()),
+            }
+        }
+        let mut chars = name.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c), None) => Key::Char(c),
+            _ => match name.to_lowercase().as_str() {
+                "space" => Key::Char(' '),
+                "lt" => Key::Char('<'),
+                "gt" => Key::Char('>'),
+                "enter" | "return" | "cr" => Key::Enter,
+                "tab" => Key::Tab,
+                "backtab" => Key::BackTab,
+                "backspace" | "bs" => Key::Backspace,
+                "esc" | "escape" => Key::Esc,
+                "left" => Key::Left,
+                "right" => Key::Right,
+                "up" => Key::Up,
+                "down" => Key::Down,
+                "ins" | "insert" => Key::Ins,
+                "delete" | "del" => Key::Delete,
+                "home" => Key::Home,
+                "end" => Key::End,
+                "pageup" | "pgup" => Key::PageUp,
+                "pagedown" | "pgdn" => Key::PageDown,
+                f => match f.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) if n <= 12 => Key::from_f(n),
+                    _ => return Err(unknown()),
+                },
+            },
+        };
+        let key = match (ctrl, alt, shift, key) {
+            (false, false, false, key) => key,
+            (false, false, true, Key::Char(c)) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+            (false, false, true, Key::Tab) => Key::BackTab,
+            (false, false, true, key) => {
+                Key::Shift(ShiftKey::try_from(key).map_err(|_| unknown())?)
+            }
+            (true, false, true, key) => {
+                Key::CtrlShift(ShiftKey::try_from(key).map_err(|_| unknown())?)
+            }
+            (false, true, false, Key::Char(c)) => Key::Alt(c),
+            (true, false, false, Key::Char(c)) => Key::Ctrl(CtrlKey::Char(c.to_ascii_lowercase())),
+            (true, false, false, key) => Key::Ctrl(CtrlKey::try_from(key).map_err(|_| unknown())?),
+            _ => return Err(unknown()),
+        };
+        Ok(key)
+    }
+}
+
+/// Write a key sequence the way `parse_keys` takes it, like `gg` or `<Space>ft`.
+pub fn display_keys(keys: &[Key]) -> String {
+    keys.iter().map(Key::to_string).collect()
+}
+
+/// Parse keys typed one after the other, written like `gg`, `<Space>ft` or
+/// `<Ctrl+x><Ctrl+s>`. `<leader>` stands for the `leader` key.
+pub fn parse_keys(s: &str, leader: Option<Key>) -> Result<Vec<Key>, String> {
+    let mut keys = vec![];
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        // a < without a > after it is the key itself
+        let len = match (c, rest[1..].find('>')) {
+            ('<', Some(end)) => end + 2,
+            _ => c.len_utf8(),
+        };
+        let (name, next) = rest.split_at(len);
+        if name.eq_ignore_ascii_case("<leader>") {
+            keys.push(leader.ok_or_else(|| format!("no leader key for {:?}", s))?);
+        } else {
+            keys.push(name.parse()?);
+        }
+        rest = next;
+    }
+    if keys.is_empty() {
+        return Err(String::from("empty key"));
+    }
+    Ok(keys)
+}
+
+impl TryFrom<Key> for ShiftKey {
+    type Error = Key;
+
+    fn try_from(key: Key) -> std::result::Result<Self, Self::Error> {
+        match key {
+            Key::Left => Ok(ShiftKey::Left),
+            Key::Right => Ok(ShiftKey::Right),
+            Key::Up => Ok(ShiftKey::Up),
+            Key::Down => Ok(ShiftKey::Down),
+            Key::Home => Ok(ShiftKey::Home),
+            Key::End => Ok(ShiftKey::End),
+            key => Err(key),
+        }
+    }
+}
+
+impl TryFrom<Key> for CtrlKey {
+    type Error = Key;
+
+    fn try_from(key: Key) -> std::result::Result<Self, Self::Error> {
+        match key {
+            Key::Enter => Ok(CtrlKey::Enter),
+            Key::Tab => Ok(CtrlKey::Tab),
+            Key::BackTab => Ok(CtrlKey::BackTab),
+            Key::Backspace => Ok(CtrlKey::Backspace),
+            Key::Esc => Ok(CtrlKey::Esc),
+            Key::Left => Ok(CtrlKey::Left),
+            Key::Right => Ok(CtrlKey::Right),
+            Key::Up => Ok(CtrlKey::Up),
+            Key::Down => Ok(CtrlKey::Down),
+            Key::Ins => Ok(CtrlKey::Ins),
+            Key::Delete => Ok(CtrlKey::Delete),
+            Key::Home => Ok(CtrlKey::Home),
+            Key::End => Ok(CtrlKey::End),
+            Key::PageUp => Ok(CtrlKey::PageUp),
+            Key::PageDown => Ok(CtrlKey::PageDown),
+            Key::Char(c) => Ok(CtrlKey::Char(c)),
+            key => Err(key),
         }
     }
 }
@@ -202,6 +368,54 @@ impl From<event::KeyEvent> for Key {
                 code: event::KeyCode::Down,
                 modifiers: event::KeyModifiers::CONTROL,
             } => Key::Ctrl(CtrlKey::Down),
+            event::KeyEvent {
+                code: event::KeyCode::Left,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::Left),
+            event::KeyEvent {
+                code: event::KeyCode::Right,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::Right),
+            event::KeyEvent {
+                code: event::KeyCode::Up,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::Up),
+            event::KeyEvent {
+                code: event::KeyCode::Down,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::Down),
+            event::KeyEvent {
+                code: event::KeyCode::Home,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::Home),
+            event::KeyEvent {
+                code: event::KeyCode::End,
+                modifiers: event::KeyModifiers::SHIFT,
+            } => Key::Shift(ShiftKey::End),
+            event::KeyEvent {
+                code: event::KeyCode::Left,
+                modifiers,
+            } if modifiers == event::KeyModifiers::CONTROL | event::KeyModifiers::SHIFT => {
+                Key::CtrlShift(ShiftKey::Left)
+            }
+            event::KeyEvent {
+                code: event::KeyCode::Right,
+                modifiers,
+            } if modifiers == event::KeyModifiers::CONTROL | event::KeyModifiers::SHIFT => {
+                Key::CtrlShift(ShiftKey::Right)
+            }
+            event::KeyEvent {
+                code: event::KeyCode::Home,
+                modifiers,
+            } if modifiers == event::KeyModifiers::CONTROL | event::KeyModifiers::SHIFT => {
+                Key::CtrlShift(ShiftKey::Home)
+            }
+            event::KeyEvent {
+                code: event::KeyCode::End,
+                modifiers,
+            } if modifiers == event::KeyModifiers::CONTROL | event::KeyModifiers::SHIFT => {
+                Key::CtrlShift(ShiftKey::End)
+            }
             event::KeyEvent {
                 code: event::KeyCode::Esc,
                 ..
@@ -3,26 +3,48 @@ mod util;
 
 use std::path::PathBuf;
 
+mod cli;
 mod config;
 mod data;
+mod editor;
+mod error;
+mod fuzzy;
+mod index;
 mod key;
+mod query;
+mod trash;
 mod views;
 
 pub use crate::views::*;
 use std::process::exit;
+use structopt::StructOpt;
 
-static CONFIG_FILE: &str = "kb.conf";
-
-fn init<T: Into<PathBuf>>(config_file: T) -> std::io::Result<config::Config> {
-    let config;
+fn init<T: Into<PathBuf>>(config_file: T) -> error::Result<config::Config> {
     let config_file_ = config_file.into();
-    config = config::Config::new(&config_file_).expect("Error in reading config file");
-    Ok(config)
+    config::Config::new(&config_file_)
 }
 
-fn main() {
-    let config = init(CONFIG_FILE).unwrap();
+fn run(opt: cli::Opt) -> error::Result<()> {
+    let mut config = init(&opt.config)?;
+    if let Some(dir) = opt.dir {
+        config.data_directories = vec![dir];
+    }
     let mut d = data::Handler::new(&config);
-    d.read_all_files();
-    ui::ui(d);
+    // notes past the retention stay in the trash until the next run, purging is best effort
+    let _ = d.purge_trash();
+    match opt.command {
+        Some(command) => cli::run(command, opt.format, &mut d),
+        None => {
+            // the TUI still works with a stale index, saving it is best effort
+            let _ = d.update_index();
+            ui::ui(d)
+        }
+    }
+}
+
+fn main() {
+    if let Err(e) = run(cli::Opt::from_args()) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
 }
@@ -0,0 +1,653 @@
+//! A small query language over the fields of `Knowledge`:
+//!
+//! ```text
+//! tag:rust title:"error handling" -tag:draft body:/regex/ updated:>2026-01-01
+//! (tag:rust OR tag:go) AND NOT body:todo
+//! ```
+//!
+//! Terms next to each other are AND-ed. `NOT` (or a leading `-`) binds tighter than `AND`,
+//! which binds tighter than `OR`.
+use crate::data::{find_ignore_case, parse_date, Knowledge};
+use chrono::{DateTime, Local};
+use core::fmt;
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// title, tags, body, description and aliases
+    Any,
+    Title,
+    Tag,
+    Body,
+    Description,
+    Alias,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateField {
+    Created,
+    Updated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+#[derive(Debug)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    /// case insensitive substring match, except for `tag:` which has to match a whole tag
+    Text(Field, String),
+    Regex(Field, Regex),
+    Date {
+        field: DateField,
+        comparison: Comparison,
+        date: DateTime<Local>,
+        /// compare the day only, the value was given without a time
+        day_only: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct QueryError {
+    /// column of the error in the query, starting from 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Term { field: Option<String>, value: Value },
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Word(String),
+    Quoted(String),
+    Regex(String),
+}
+
+struct Token {
+    kind: TokenKind,
+    /// byte offset in the query
+    pos: usize,
+}
+
+struct Lexer<'a> {
+    query: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn error<T>(&self, pos: usize, message: String) -> Result<T, QueryError> {
+        Err(QueryError {
+            column: self.query[..pos].chars().count() + 1,
+            message,
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.query[self.pos..].chars().next()
+    }
+
+    fn tokens(mut self) -> Result<Vec<Token>, QueryError> {
+        let mut tokens = vec![];
+        while let Some(c) = self.peek() {
+            let pos = self.pos;
+            let kind = match c {
+                c if c.is_whitespace() => {
+                    self.pos += c.len_utf8();
+                    continue;
+                }
+                '(' => {
+                    self.pos += 1;
+                    TokenKind::LParen
+                }
+                ')' => {
+                    self.pos += 1;
+                    TokenKind::RParen
+                }
+                '-' if self.query[pos + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| !c.is_whitespace() && c != ')') =>
+                {
+                    self.pos += 1;
+                    TokenKind::Not
+                }
+                _ => self.term()?,
+            };
+            tokens.push(Token { kind, pos });
+        }
+        Ok(tokens)
+    }
+
+    fn term(&mut self) -> Result<TokenKind, QueryError> {
+        let start = self.pos;
+        let field_len = self.query[start..]
+            .find(|c: char| !(c.is_ascii_alphabetic() || c == '_'))
+            .unwrap_or(self.query.len() - start);
+        if field_len > 0 && self.query[start + field_len..].starts_with(':') {
+            let field = self.query[start..start + field_len].to_string();
+            self.pos = start + field_len + 1;
+            let value = self.value()?;
+            if let Value::Word(word) = &value {
+                if word.is_empty() {
+                    return self.error(start, format!("missing value after '{}:'", field));
+                }
+            }
+            return Ok(TokenKind::Term {
+                field: Some(field),
+                value,
+            });
+        }
+        let value = self.value()?;
+        Ok(match value {
+            Value::Word(word) if word == "AND" => TokenKind::And,
+            Value::Word(word) if word == "OR" => TokenKind::Or,
+            Value::Word(word) if word == "NOT" => TokenKind::Not,
+            value => TokenKind::Term { field: None, value },
+        })
+    }
+
+    fn value(&mut self) -> Result<Value, QueryError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => {
+                let content = self.delimited('"', start)?;
+                Ok(Value::Quoted(content))
+            }
+            Some('/') => {
+                let content = self.delimited('/', start)?;
+                Ok(Value::Regex(content))
+            }
+            _ => {
+                let len = self.query[start..]
+                    .find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                    .unwrap_or(self.query.len() - start);
+                self.pos = start + len;
+                Ok(Value::Word(self.query[start..self.pos].to_string()))
+            }
+        }
+    }
+
+    /// Read up to the closing `delimiter`, which can be escaped with a backslash.
+    fn delimited(&mut self, delimiter: char, start: usize) -> Result<String, QueryError> {
+        let mut content = String::new();
+        let mut chars = self.query[start + 1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some((_, next)) if next == delimiter => content.push(next),
+                    Some((_, next)) => {
+                        content.push(c);
+                        content.push(next);
+                    }
+                    None => content.push(c),
+                }
+            } else if c == delimiter {
+                self.pos = start + 1 + i + 1;
+                return Ok(content);
+            } else {
+                content.push(c);
+            }
+        }
+        let name = if delimiter == '"' { "quote" } else { "regex" };
+        self.error(
+            start,
+            format!("unterminated {}, missing closing {}", name, delimiter),
+        )
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, pos: usize, message: String) -> Result<T, QueryError> {
+        Err(QueryError {
+            column: self.query[..pos].chars().count() + 1,
+            message,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// byte offset of the next token, or the end of the query
+    fn next_pos(&self) -> usize {
+        self.peek().map_or(self.query.len(), |t| t.pos)
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.and()?];
+        while let Some(Token {
+            kind: TokenKind::Or,
+            ..
+        }) = self.peek()
+        {
+            self.pos += 1;
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Query::Or(terms)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut terms = vec![self.unary()?];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => {
+                    self.pos += 1;
+                }
+                // no operator between two terms means AND
+                Some(TokenKind::Not) | Some(TokenKind::LParen) | Some(TokenKind::Term { .. }) => {}
+                _ => break,
+            }
+            terms.push(self.unary()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Query::And(terms)
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        let pos = self.next_pos();
+        match self.tokens.get(self.pos).map(|t| &t.kind) {
+            Some(TokenKind::Not) => {
+                self.pos += 1;
+                Ok(Query::Not(Box::new(self.unary()?)))
+            }
+            Some(TokenKind::LParen) => {
+                self.pos += 1;
+                let query = self.or()?;
+                match self.peek() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => {
+                        self.pos += 1;
+                        Ok(query)
+                    }
+                    _ => self.error(pos, String::from("unmatched '(', missing ')'")),
+                }
+            }
+            Some(TokenKind::Term { .. }) => {
+                let token = self.tokens.remove(self.pos);
+                match token.kind {
+                    TokenKind::Term { field, value } => self.term(token.pos, field, value),
+                    _ => unreachable!(),
+                }
+            }
+            Some(TokenKind::RParen) => self.error(pos, String::from("unexpected ')'")),
+            Some(TokenKind::And) | Some(TokenKind::Or) => {
+                self.error(pos, String::from("expected a term before the operator"))
+            }
+            None => self.error(pos, String::from("expected a term at the end of the query")),
+        }
+    }
+
+    fn term(
+        &mut self,
+        pos: usize,
+        field: Option<String>,
+        value: Value,
+    ) -> Result<Query, QueryError> {
+        let field_name = field.as_deref().unwrap_or("");
+        let date_field = match field_name {
+            "created" => Some(DateField::Created),
+            "updated" => Some(DateField::Updated),
+            _ => None,
+        };
+        if let Some(date_field) = date_field {
+            return match value {
+                Value::Word(word) | Value::Quoted(word) => self.date(pos, date_field, &word),
+                Value::Regex(_) => {
+                    self.error(pos, format!("'{}' takes a date, not a regex", field_name))
+                }
+            };
+        }
+        let field = match field_name {
+            "" => Field::Any,
+            "title" => Field::Title,
+            "tag" | "tags" => Field::Tag,
+            "body" | "text" => Field::Body,
+            "description" | "desc" => Field::Description,
+            "alias" | "aliases" => Field::Alias,
+            "path" => Field::Path,
+            _ => {
+                return self.error(
+                    pos,
+                    format!(
+                        "unknown field '{}', expected one of title, tag, body, description, \
+                         alias, path, created, updated",
+                        field_name
+                    ),
+                )
+            }
+        };
+        match value {
+            Value::Word(text) | Value::Quoted(text) => Ok(Query::Text(field, text)),
+            Value::Regex(pattern) => match Regex::new(&pattern) {
+                Ok(regex) => Ok(Query::Regex(field, regex)),
+                Err(e) => {
+                    // regex errors span several lines pointing at the pattern
+                    let e = e
+                        .to_string()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    self.error(pos, format!("invalid regex: {}", e))
+                }
+            },
+        }
+    }
+
+    fn date(&self, pos: usize, field: DateField, value: &str) -> Result<Query, QueryError> {
+        let (comparison, date) = if let Some(d) = value.strip_prefix(">=") {
+            (Comparison::GreaterEqual, d)
+        } else if let Some(d) = value.strip_prefix("<=") {
+            (Comparison::LessEqual, d)
+        } else if let Some(d) = value.strip_prefix('>') {
+            (Comparison::Greater, d)
+        } else if let Some(d) = value.strip_prefix('<') {
+            (Comparison::Less, d)
+        } else if let Some(d) = value.strip_prefix('=') {
+            (Comparison::Equal, d)
+        } else {
+            (Comparison::Equal, value)
+        };
+        match parse_date(date) {
+            Some(parsed) => Ok(Query::Date {
+                field,
+                comparison,
+                date: parsed,
+                day_only: date.trim().len() == "YYYY-MM-DD".len(),
+            }),
+            None => self.error(
+                pos,
+                format!("invalid date '{}', expected YYYY-MM-DD or RFC 3339", date),
+            ),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        let tokens = Lexer { query, pos: 0 }.tokens()?;
+        let mut parser = Parser {
+            query,
+            tokens,
+            pos: 0,
+        };
+        let result = parser.or()?;
+        match parser.peek() {
+            None => Ok(result),
+            Some(token) => {
+                let message = match token.kind {
+                    TokenKind::RParen => "unexpected ')'",
+                    _ => "expected a term after the operator",
+                };
+                parser.error(token.pos, String::from(message))
+            }
+        }
+    }
+
+    /// Plain words without fields or operators, which the search index can answer.
+    pub fn is_plain(&self) -> bool {
+        match self {
+            Query::Text(Field::Any, text) => !text.contains(char::is_whitespace),
+            Query::And(terms) => terms.iter().all(|t| t.is_plain()),
+            _ => false,
+        }
+    }
+
+    pub fn matches(&self, k: &Knowledge) -> bool {
+        match self {
+            Query::And(terms) => terms.iter().all(|t| t.matches(k)),
+            Query::Or(terms) => terms.iter().any(|t| t.matches(k)),
+            Query::Not(query) => !query.matches(k),
+            Query::Text(Field::Tag, text) => {
+                let text = text.to_lowercase();
+                k.tag.iter().any(|t| t.to_lowercase() == text)
+            }
+            Query::Text(field, text) => {
+                field_values(*field, k).any(|(_, value)| find_ignore_case(value, text).is_some())
+            }
+            Query::Regex(field, regex) => field_values(*field, k).any(|(_, v)| regex.is_match(v)),
+            Query::Date {
+                field,
+                comparison,
+                date,
+                day_only,
+            } => {
+                let value = match field {
+                    DateField::Created => k.created,
+                    DateField::Updated => k.updated,
+                };
+                let ordering = match value {
+                    Some(value) if *day_only => {
+                        value.naive_local().date().cmp(&date.naive_local().date())
+                    }
+                    Some(value) => value.cmp(date),
+                    None => return false,
+                };
+                match comparison {
+                    Comparison::Equal => ordering.is_eq(),
+                    Comparison::Greater => ordering.is_gt(),
+                    Comparison::GreaterEqual => ordering.is_ge(),
+                    Comparison::Less => ordering.is_lt(),
+                    Comparison::LessEqual => ordering.is_le(),
+                }
+            }
+        }
+    }
+
+    /// Byte range of the first positive text or regex term matching in `line`, used to
+    /// highlight search results.
+    pub fn find_in(&self, field: Field, line: &str) -> Option<(usize, usize)> {
+        match self {
+            Query::And(terms) | Query::Or(terms) => {
+                terms.iter().find_map(|t| t.find_in(field, line))
+            }
+            Query::Text(f, text) if *f == field || *f == Field::Any => find_ignore_case(line, text),
+            Query::Regex(f, regex) if *f == field || *f == Field::Any => {
+                regex.find(line).map(|m| (m.start(), m.end()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The values of `field` in `k`, each with the field it came from.
+fn field_values<'a>(
+    field: Field,
+    k: &'a Knowledge,
+) -> Box<dyn Iterator<Item = (Field, &'a str)> + 'a> {
+    let tags = k.tag.iter().map(|t| (Field::Tag, t.as_str()));
+    let aliases = k.aliases.iter().map(|t| (Field::Alias, t.as_str()));
+    match field {
+        Field::Any => Box::new(
+            vec![
+                (Field::Title, k.title.as_str()),
+                (Field::Body, k.text.as_str()),
+                (Field::Description, k.descriptions.as_str()),
+            ]
+            .into_iter()
+            .chain(tags)
+            .chain(aliases),
+        ),
+        Field::Title => Box::new(std::iter::once((Field::Title, k.title.as_str()))),
+        Field::Tag => Box::new(tags),
+        Field::Body => Box::new(std::iter::once((Field::Body, k.text.as_str()))),
+        Field::Description => Box::new(std::iter::once((
+            Field::Description,
+            k.descriptions.as_str(),
+        ))),
+        Field::Alias => Box::new(aliases),
+        Field::Path => Box::new(k.path.to_str().map(|p| (Field::Path, p)).into_iter()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The structure of a parsed query as an s-expression, fields as `field:value`.
+    fn show(query: &Query) -> String {
+        let list = |name: &str, terms: &[Query]| {
+            let terms: Vec<String> = terms.iter().map(show).collect();
+            format!("({} {})", name, terms.join(" "))
+        };
+        match query {
+            Query::And(terms) => list("and", terms),
+            Query::Or(terms) => list("or", terms),
+            Query::Not(query) => format!("(not {})", show(query)),
+            Query::Text(Field::Any, text) => text.clone(),
+            Query::Text(field, text) => format!("{:?}:{}", field, text),
+            Query::Regex(field, regex) => format!("{:?}:/{}/", field, regex.as_str()),
+            Query::Date {
+                field,
+                comparison,
+                day_only,
+                ..
+            } => format!("{:?} {:?} day_only={}", field, comparison, day_only),
+        }
+    }
+
+    fn parse(query: &str) -> String {
+        show(&Query::parse(query).unwrap())
+    }
+
+    fn column(query: &str) -> usize {
+        Query::parse(query).unwrap_err().column
+    }
+
+    fn note_updated(updated: &str) -> Knowledge {
+        Knowledge {
+            updated: parse_date(updated),
+            ..Knowledge::default()
+        }
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_tighter_than_or() {
+        assert_eq!(parse("a OR b AND NOT c"), "(or a (and b (not c)))");
+        assert_eq!(parse("NOT a AND b OR c"), "(or (and (not a) b) c)");
+        assert_eq!(parse("(a OR b) AND c"), "(and (or a b) c)");
+        assert_eq!(parse("NOT (a OR b)"), "(not (or a b))");
+    }
+
+    #[test]
+    fn terms_next_to_each_other_are_anded() {
+        assert_eq!(parse("a b OR c"), "(or (and a b) c)");
+        assert_eq!(parse("a OR b c"), "(or a (and b c))");
+        assert_eq!(parse("a (b OR c) NOT d"), "(and a (or b c) (not d))");
+    }
+
+    #[test]
+    fn leading_dash_negates() {
+        assert_eq!(parse("-tag:draft rust"), "(and (not Tag:draft) rust)");
+        assert_eq!(parse("rust -(a OR b)"), "(and rust (not (or a b)))");
+        // a dash on its own or inside a word is text
+        assert_eq!(parse("a - b"), "(and a - b)");
+        assert_eq!(parse("well-known"), "well-known");
+    }
+
+    #[test]
+    fn quoted_values_and_escapes() {
+        assert_eq!(parse(r#"title:"error handling""#), "Title:error handling");
+        assert_eq!(parse(r#""say \"hi\"""#), r#"say "hi""#);
+        // other escapes are kept as written
+        assert_eq!(parse(r#""a\b""#), r"a\b");
+        assert_eq!(parse(r#""a OR b""#), "a OR b");
+    }
+
+    #[test]
+    fn regex_values_and_escapes() {
+        assert_eq!(parse(r"body:/a\/b/"), "Body:/a/b/");
+        assert_eq!(parse(r"/\d+ (x|y)/"), r"Any:/\d+ (x|y)/");
+    }
+
+    #[test]
+    fn date_comparisons() {
+        assert_eq!(
+            parse("updated:>2026-01-01"),
+            "Updated Greater day_only=true"
+        );
+        assert_eq!(
+            parse("created:<=2026-01-01"),
+            "Created LessEqual day_only=true"
+        );
+        assert_eq!(
+            parse(r#"updated:">=2026-01-01 09:00:00""#),
+            "Updated GreaterEqual day_only=false"
+        );
+        assert_eq!(
+            parse("updated:2026-01-01T09:00:00+00:00"),
+            "Updated Equal day_only=false"
+        );
+
+        let note = note_updated("2026-01-01 10:00:00");
+        let matches = |query: &str| Query::parse(query).unwrap().matches(&note);
+        // a day compares with the whole day, whatever the time of the note
+        assert!(matches("updated:2026-01-01"));
+        assert!(!matches("updated:>2026-01-01"));
+        assert!(matches("updated:>=2026-01-01"));
+        assert!(matches("updated:<2026-01-02"));
+        assert!(!matches("updated:<2026-01-01"));
+        // with a time the exact moment is compared
+        assert!(matches(r#"updated:">2026-01-01 09:00:00""#));
+        assert!(!matches(r#"updated:">2026-01-01 11:00:00""#));
+        assert!(!matches(r#"updated:"2026-01-01 09:00:00""#));
+        // notes without the date never match
+        assert!(!Query::parse("created:<2100-01-01").unwrap().matches(&note));
+    }
+
+    #[test]
+    fn error_columns() {
+        assert_eq!(column("tag:rust (a"), 10);
+        assert_eq!(column("a AND"), 6);
+        assert_eq!(column("a )"), 3);
+        assert_eq!(column("OR a"), 1);
+        assert_eq!(column(r#"a "unterminated"#), 3);
+        assert_eq!(column("a /unterminated"), 3);
+        assert_eq!(column("a tag:"), 3);
+        assert_eq!(column("a body:/(/"), 3);
+        assert_eq!(column("a updated:yesterday"), 3);
+        // columns count characters, not bytes
+        assert_eq!(column("über größe )"), 12);
+        assert_eq!(column(r#""größe" tag:"#), 9);
+        let error = Query::parse("größe nope:x").unwrap_err();
+        assert_eq!(error.column, 7);
+        assert!(error.message.starts_with("unknown field 'nope'"));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::error::{Error, Result};
+use chrono::{DateTime, Duration, Local};
+use std::path::{Path, PathBuf};
+
+/// Directory inside every data directory deleted notes and directories are moved to.
+pub static TRASH_DIR: &str = ".trash";
+
+/// Directory of the trash holding the deleted entries, each under its id.
+const FILES_DIR: &str = "files";
+
+/// Directory of the trash holding `<id>.json` with the `Info` of every entry.
+const INFO_DIR: &str = "info";
+
+/// Where an entry of the trash came from and when it was deleted.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Info {
+    /// relative to the data directory
+    path: PathBuf,
+    deleted: DateTime<Local>,
+}
+
+/// A note or directory in the trash of a data directory.
+pub struct TrashEntry {
+    /// name in the trash, the file name it had, made unique
+    pub id: String,
+    /// the data directory it was deleted from
+    pub root: PathBuf,
+    /// where it was, relative to `root`
+    pub path: PathBuf,
+    pub deleted: DateTime<Local>,
+}
+
+/// Whether `path` is in the trash of the data directory `root`.
+pub fn is_trashed(root: &Path, path: &Path) -> bool {
+    path.starts_with(root.join(TRASH_DIR))
+}
+
+/// Move `path` in the data directory `root` to its trash, remembering where it was.
+pub fn move_to_trash(root: &Path, path: &Path) -> Result<TrashEntry> {
+    let trash = root.join(TRASH_DIR);
+    let files = trash.join(FILES_DIR);
+    let info = trash.join(INFO_DIR);
+    for dir in [&files, &info].iter() {
+        std::fs::create_dir_all(dir).map_err(Error::file(*dir))?;
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // a note deleted again after it was recreated gets a number
+    let mut id = name.clone();
+    let mut n = 1;
+    while files.join(&id).exists() || info_path(root, &id).exists() {
+        n += 1;
+        id = format!("{}.{}", name, n);
+    }
+    let entry = TrashEntry {
+        id,
+        root: root.to_path_buf(),
+        path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+        deleted: Local::now(),
+    };
+    // the info is written first, an entry without it could not be restored
+    let info = Info {
+        path: entry.path.clone(),
+        deleted: entry.deleted,
+    };
+    let info_file = entry.info_path();
+    std::fs::write(&info_file, serde_json::to_string_pretty(&info)?)
+        .map_err(Error::file(&info_file))?;
+    if let Err(e) = std::fs::rename(path, entry.location()) {
+        let _ = std::fs::remove_file(&info_file);
+        return Err(Error::File(path.to_path_buf(), e));
+    }
+    Ok(entry)
+}
+
+fn info_path(root: &Path, id: &str) -> PathBuf {
+    root.join(TRASH_DIR)
+        .join(INFO_DIR)
+        .join(String::from(id) + ".json")
+}
+
+/// The entries in the trash of the data directory `root`, the most recently deleted first.
+/// Entries whose info is missing or unreadable are left out.
+pub fn entries(root: &Path) -> Vec<TrashEntry> {
+    let dir = match std::fs::read_dir(root.join(TRASH_DIR).join(FILES_DIR)) {
+        Ok(dir) => dir,
+        Err(_) => return vec![],
+    };
+    let mut entries: Vec<TrashEntry> = dir
+        .filter_map(|e| {
+            let id = e.ok()?.file_name().into_string().ok()?;
+            let info = std::fs::read_to_string(info_path(root, &id)).ok()?;
+            let info: Info = serde_json::from_str(&info).ok()?;
+            Some(TrashEntry {
+                id,
+                root: root.to_path_buf(),
+                path: info.path,
+                deleted: info.deleted,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.deleted));
+    entries
+}
+
+/// Purge the entries of the trash of `root` deleted more than `days` days ago. Returns how
+/// many were purged.
+pub fn purge_expired(root: &Path, days: u64) -> Result<usize> {
+    let oldest = Local::now() - Duration::days(days as i64);
+    let mut purged = 0;
+    for entry in entries(root) {
+        if entry.deleted < oldest {
+            entry.purge()?;
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+impl TrashEntry {
+    /// Where the entry was before it was deleted.
+    pub fn original(&self) -> PathBuf {
+        self.root.join(&self.path)
+    }
+
+    /// Where the entry is in the trash.
+    pub fn location(&self) -> PathBuf {
+        self.root.join(TRASH_DIR).join(FILES_DIR).join(&self.id)
+    }
+
+    fn info_path(&self) -> PathBuf {
+        info_path(&self.root, &self.id)
+    }
+
+    /// Move the entry back where it was, creating the directories it was in. Nothing there
+    /// is ever overwritten. Returns the path it is restored to.
+    pub fn restore(&self) -> Result<PathBuf> {
+        let original = self.original();
+        if original.exists() {
+            return Err(Error::Occupied(original));
+        }
+        if let Some(dir) = original.parent() {
+            std::fs::create_dir_all(dir).map_err(Error::file(dir))?;
+        }
+        let location = self.location();
+        std::fs::rename(&location, &original).map_err(Error::file(&location))?;
+        let info = self.info_path();
+        std::fs::remove_file(&info).map_err(Error::file(&info))?;
+        Ok(original)
+    }
+
+    /// Delete the entry for good.
+    pub fn purge(&self) -> Result<()> {
+        let location = self.location();
+        if location.is_dir() {
+            std::fs::remove_dir_all(&location).map_err(Error::file(&location))?;
+        } else {
+            std::fs::remove_file(&location).map_err(Error::file(&location))?;
+        }
+        let info = self.info_path();
+        std::fs::remove_file(&info).map_err(Error::file(&info))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deleted_notes_go_to_the_trash_and_come_back() {
+        let root = std::env::temp_dir().join(format!("kb-trash-{}", std::process::id()));
+        let dir = root.join("work");
+        std::fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        std::fs::write(&note, "text").unwrap();
+
+        let entry = move_to_trash(&root, &note).unwrap();
+        assert!(!note.exists());
+        assert_eq!(entry.id, "note.md");
+        assert_eq!(entry.path, Path::new("work/note.md"));
+        assert!(is_trashed(&root, &entry.location()));
+        assert_eq!(std::fs::read_to_string(entry.location()).unwrap(), "text");
+        let info = std::fs::read_to_string(entry.info_path()).unwrap();
+        let info: Info = serde_json::from_str(&info).unwrap();
+        assert_eq!(info.path, entry.path);
+        assert_eq!(info.deleted, entry.deleted);
+
+        // the note is recreated and deleted again, the same directory is deleted too
+        std::fs::write(&note, "again").unwrap();
+        let again = move_to_trash(&root, &note).unwrap();
+        assert_eq!(again.id, "note.md.2");
+        let work = move_to_trash(&root, &dir).unwrap();
+        let listed = entries(&root);
+        let ids: Vec<&str> = listed.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&"note.md") && ids.contains(&"note.md.2"));
+        assert_eq!(
+            listed.iter().find(|e| e.id == "note.md.2").unwrap().path,
+            entry.path
+        );
+
+        // a restored entry never overwrites what took its place
+        assert_eq!(work.restore().unwrap(), dir);
+        assert_eq!(entry.restore().unwrap(), note);
+        assert!(matches!(again.restore(), Err(Error::Occupied(path)) if path == note));
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "text");
+        assert!(again.location().exists());
+        let ids: Vec<String> = entries(&root).into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["note.md.2"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn purging_deletes_entries_for_good() {
+        let root = std::env::temp_dir().join(format!("kb-purge-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("dir")).unwrap();
+        std::fs::write(root.join("dir/note.md"), "").unwrap();
+        for name in &["old.md", "new.md"] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+        let dir = move_to_trash(&root, &root.join("dir")).unwrap();
+        let old = move_to_trash(&root, &root.join("old.md")).unwrap();
+        move_to_trash(&root, &root.join("new.md")).unwrap();
+
+        let info = Info {
+            path: old.path.clone(),
+            deleted: Local::now() - Duration::days(31),
+        };
+        std::fs::write(old.info_path(), serde_json::to_string(&info).unwrap()).unwrap();
+        assert_eq!(purge_expired(&root, 30).unwrap(), 1);
+        assert!(!old.location().exists() && !old.info_path().exists());
+
+        dir.purge().unwrap();
+        assert!(!dir.location().exists() && !dir.info_path().exists());
+        let ids: Vec<String> = entries(&root).into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["new.md"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
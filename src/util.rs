@@ -1,3 +1,33 @@
+use std::io::Write;
+
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Copy `text` to the clipboard of the terminal with the OSC 52 escape sequence. Terminals
+/// without support ignore it.
+pub fn osc52_copy(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    stdout.flush()
+}
+
 #[derive(Default)]
 pub struct BiCycle {
     pub total_len: usize,
@@ -13,6 +43,9 @@ impl BiCycle {
     }
 
     pub fn next(&mut self) -> Option<usize> {
+        if self.total_len == 0 {
+            return None;
+        }
         self.current_item = if self.current_item >= self.total_len - 1 {
             0
         } else {
@@ -22,6 +55,9 @@ impl BiCycle {
     }
 
     pub fn prev(&mut self) -> Option<usize> {
+        if self.total_len == 0 {
+            return None;
+        }
         self.current_item = if self.current_item == 0 {
             self.total_len - 1
         } else {
@@ -1,141 +1,514 @@
-use crate::data::Knowledge;
-use crate::key::{CtrlKey, Key};
-use crate::views::app::{App, FileStatus, ViewState};
-use std::fs::remove_file;
+use crate::data::{split_tags, Knowledge};
+use crate::error::Result;
+use crate::key::Key;
+use crate::views::app::{App, FileStatus, Input, ViewState};
+use crate::views::keymap::{Action, ActionInfo};
+use crate::vim;
+use chrono::Local;
 
-pub fn handler(app: &mut App, event: &Key) {
-    match event {
-        Key::Char(c) => {
-            app.get_current_input().insert(c.clone());
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EditorAction {
+    NextField,
+    PreviousField,
+    NewLine,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    DeleteWordForward,
+    Left,
+    Right,
+    Up,
+    Down,
+    WordLeft,
+    WordRight,
+    LineStart,
+    LineEnd,
+    TextStart,
+    TextEnd,
+    PageUp,
+    PageDown,
+    SelectLeft,
+    SelectRight,
+    SelectUp,
+    SelectDown,
+    SelectWordLeft,
+    SelectWordRight,
+    SelectLineStart,
+    SelectLineEnd,
+    SelectTextStart,
+    SelectTextEnd,
+    Copy,
+    Cut,
+    Paste,
+    PastePrevious,
+    Undo,
+    Redo,
+    ToggleWrap,
+    Save,
+    Close,
+    Help,
+}
+
+impl Action for EditorAction {
+    const VIEW: &'static str = "editor";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            EditorAction::NextField,
+            "next-field",
+            &["<Tab>"],
+            "go to the next field",
+        ),
+        (
+            EditorAction::PreviousField,
+            "previous-field",
+            &["<BackTab>"],
+            "go to the previous field",
+        ),
+        (
+            EditorAction::NewLine,
+            "new-line",
+            &["<Enter>"],
+            "start a new line",
+        ),
+        (
+            EditorAction::DeleteBackward,
+            "delete-backward",
+            &["<Backspace>"],
+            "delete the character before the cursor",
+        ),
+        (
+            EditorAction::DeleteForward,
+            "delete-forward",
+            &["<Delete>"],
+            "delete the character under the cursor",
+        ),
+        (
+            EditorAction::DeleteWordBackward,
+            "delete-word-backward",
+            &["<Ctrl+w>"],
+            "cut the word before the cursor",
+        ),
+        (
+            EditorAction::DeleteWordForward,
+            "delete-word-forward",
+            &["<Ctrl+Delete>"],
+            "cut the word after the cursor",
+        ),
+        (EditorAction::Left, "left", &["<Left>"], "move left"),
+        (EditorAction::Right, "right", &["<Right>"], "move right"),
+        (EditorAction::Up, "up", &["<Up>"], "move up"),
+        (EditorAction::Down, "down", &["<Down>"], "move down"),
+        (
+            EditorAction::WordLeft,
+            "word-left",
+            &["<Ctrl+Left>"],
+            "move to the previous word",
+        ),
+        (
+            EditorAction::WordRight,
+            "word-right",
+            &["<Ctrl+Right>"],
+            "move to the next word",
+        ),
+        (
+            EditorAction::LineStart,
+            "line-start",
+            &["<Home>"],
+            "move to the start of the line",
+        ),
+        (
+            EditorAction::LineEnd,
+            "line-end",
+            &["<End>"],
+            "move to the end of the line",
+        ),
+        (
+            EditorAction::TextStart,
+            "text-start",
+            &["<Ctrl+Home>"],
+            "move to the start of the text",
+        ),
+        (
+            EditorAction::TextEnd,
+            "text-end",
+            &["<Ctrl+End>"],
+            "move to the end of the text",
+        ),
+        (
+            EditorAction::PageUp,
+            "page-up",
+            &["<PageUp>"],
+            "move up a screen",
+        ),
+        (
+            EditorAction::PageDown,
+            "page-down",
+            &["<PageDown>"],
+            "move down a screen",
+        ),
+        (
+            EditorAction::SelectLeft,
+            "select-left",
+            &["<Shift+Left>"],
+            "select left",
+        ),
+        (
+            EditorAction::SelectRight,
+            "select-right",
+            &["<Shift+Right>"],
+            "select right",
+        ),
+        (
+            EditorAction::SelectUp,
+            "select-up",
+            &["<Shift+Up>"],
+            "select up",
+        ),
+        (
+            EditorAction::SelectDown,
+            "select-down",
+            &["<Shift+Down>"],
+            "select down",
+        ),
+        (
+            EditorAction::SelectWordLeft,
+            "select-word-left",
+            &["<Ctrl+Shift+Left>"],
+            "select to the previous word",
+        ),
+        (
+            EditorAction::SelectWordRight,
+            "select-word-right",
+            &["<Ctrl+Shift+Right>"],
+            "select to the next word",
+        ),
+        (
+            EditorAction::SelectLineStart,
+            "select-line-start",
+            &["<Shift+Home>"],
+            "select to the start of the line",
+        ),
+        (
+            EditorAction::SelectLineEnd,
+            "select-line-end",
+            &["<Shift+End>"],
+            "select to the end of the line",
+        ),
+        (
+            EditorAction::SelectTextStart,
+            "select-text-start",
+            &["<Ctrl+Shift+Home>"],
+            "select to the start of the text",
+        ),
+        (
+            EditorAction::SelectTextEnd,
+            "select-text-end",
+            &["<Ctrl+Shift+End>"],
+            "select to the end of the text",
+        ),
+        (
+            EditorAction::Copy,
+            "copy",
+            &["<Ctrl+c>"],
+            "copy the selection",
+        ),
+        (EditorAction::Cut, "cut", &["<Ctrl+x>"], "cut the selection"),
+        (
+            EditorAction::Paste,
+            "paste",
+            &["<Ctrl+v>"],
+            "paste the last cut or copied text",
+        ),
+        (
+            EditorAction::PastePrevious,
+            "paste-previous",
+            &["<Alt+y>"],
+            "right after pasting, paste the text cut before instead",
+        ),
+        (EditorAction::Undo, "undo", &["<Ctrl+z>"], "undo"),
+        (EditorAction::Redo, "redo", &["<Ctrl+y>"], "redo"),
+        (
+            EditorAction::ToggleWrap,
+            "toggle-wrap",
+            &["<Alt+z>"],
+            "wrap long lines or scroll sideways",
+        ),
+        (EditorAction::Save, "save", &["<Ctrl+g>"], "save the note"),
+        (
+            EditorAction::Close,
+            "close",
+            &["<Esc>"],
+            "leave the editor without saving",
+        ),
+        (EditorAction::Help, "help", &["<F1>"], "list the keys"),
+    ];
+
+    fn category(self) -> &'static str {
+        use EditorAction::*;
+        match self {
+            NextField | PreviousField | Save | Close | Help => "note",
+            NewLine | DeleteBackward | DeleteForward | DeleteWordBackward | DeleteWordForward
+            | Undo | Redo => "edit",
+            Left | Right | Up | Down | WordLeft | WordRight | LineStart | LineEnd | TextStart
+            | TextEnd | PageUp | PageDown => "move",
+            SelectLeft | SelectRight | SelectUp | SelectDown | SelectWordLeft | SelectWordRight
+            | SelectLineStart | SelectLineEnd | SelectTextStart | SelectTextEnd => "select",
+            Copy | Cut | Paste | PastePrevious => "clipboard",
+            ToggleWrap => "view",
+        }
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    if let Err(e) = edit(app, keys) {
+        app.show_error(e);
+    }
+}
+
+fn edit(app: &mut App, keys: &[Key]) -> Result<()> {
+    let action = app.keymaps.editor.get(keys);
+    if action != Some(EditorAction::PastePrevious) {
+        // pasting the previous text only works right after a paste
+        app.yank_index = None;
+    }
+    if action == Some(EditorAction::Help) {
+        app.open_help();
+        return Ok(());
+    }
+    // vim mode takes keys one by one, sequences of the keymap are handled below
+    if let [key] = keys {
+        if vim::handler(app, key) {
+            return Ok(());
+        }
+    }
+    let action = match (action, keys) {
+        (Some(action), _) => action,
+        (None, [Key::Char(c)]) => {
+            app.get_current_input()?.insert(*c);
+            return Ok(());
         }
-        Key::Tab => {
+        (None, _) => return Ok(()),
+    };
+    match action {
+        EditorAction::NextField => {
             app.input_current_tab.next();
         }
-        Key::BackTab => {
+        EditorAction::PreviousField => {
             app.input_current_tab.prev();
         }
-        Key::Backspace => {
-            app.get_current_input().backspace();
+        EditorAction::NewLine => {
+            app.get_current_input()?.new_line();
+        }
+        EditorAction::DeleteBackward => {
+            app.get_current_input()?.backspace();
+        }
+        EditorAction::DeleteForward => {
+            app.get_current_input()?.delete();
+        }
+        EditorAction::Left => {
+            app.get_current_input()?
+                .move_cursor(Input::move_left, false);
+        }
+        EditorAction::Right => {
+            app.get_current_input()?
+                .move_cursor(Input::move_right, false);
+        }
+        EditorAction::Up => {
+            app.get_current_input()?.move_cursor(Input::move_up, false);
+        }
+        EditorAction::Down => {
+            app.get_current_input()?
+                .move_cursor(Input::move_down, false);
+        }
+        EditorAction::WordLeft => {
+            app.get_current_input()?
+                .move_cursor(Input::move_left_word, false);
+        }
+        EditorAction::WordRight => {
+            app.get_current_input()?
+                .move_cursor(Input::move_right_word, false);
+        }
+        EditorAction::LineStart => {
+            app.get_current_input()?
+                .move_cursor(Input::beginning_of_line, false);
+        }
+        EditorAction::LineEnd => {
+            app.get_current_input()?
+                .move_cursor(Input::end_of_line, false);
+        }
+        EditorAction::TextStart => {
+            app.get_current_input()?
+                .move_cursor(Input::top_beginning_of_line, false);
+        }
+        EditorAction::TextEnd => {
+            app.get_current_input()?
+                .move_cursor(Input::bottom_end_of_line, false);
         }
-        Key::Left => {
-            app.get_current_input().move_left();
+        EditorAction::PageUp => {
+            app.get_current_input()?.move_cursor(Input::page_up, false);
         }
-        Key::Right => {
-            app.get_current_input().move_right();
+        EditorAction::PageDown => {
+            app.get_current_input()?
+                .move_cursor(Input::page_down, false);
         }
-        Key::Up => {
-            app.get_current_input().move_up();
+        EditorAction::SelectLeft => {
+            app.get_current_input()?.move_cursor(Input::move_left, true);
         }
-        Key::Down => {
-            app.get_current_input().move_down();
+        EditorAction::SelectRight => {
+            app.get_current_input()?
+                .move_cursor(Input::move_right, true);
         }
-        Key::Enter => {
-            app.get_current_input().new_line();
+        EditorAction::SelectUp => {
+            app.get_current_input()?.move_cursor(Input::move_up, true);
         }
-        Key::Home => {
-            app.get_current_input().beginning_of_line();
+        EditorAction::SelectDown => {
+            app.get_current_input()?.move_cursor(Input::move_down, true);
         }
-        Key::End => {
-            app.get_current_input().end_of_line();
+        EditorAction::SelectWordLeft => {
+            app.get_current_input()?
+                .move_cursor(Input::move_left_word, true);
         }
-        Key::Delete => {
-            app.get_current_input().delete();
+        EditorAction::SelectWordRight => {
+            app.get_current_input()?
+                .move_cursor(Input::move_right_word, true);
         }
-        Key::Ctrl(CtrlKey::Home) => {
-            app.get_current_input().top_beginning_of_line();
+        EditorAction::SelectLineStart => {
+            app.get_current_input()?
+                .move_cursor(Input::beginning_of_line, true);
         }
-        Key::Ctrl(CtrlKey::End) => {
-            app.get_current_input().bottom_end_of_line();
+        EditorAction::SelectLineEnd => {
+            app.get_current_input()?
+                .move_cursor(Input::end_of_line, true);
         }
-        Key::Ctrl(CtrlKey::Char('w')) => {
-            app.get_current_input().backspace_word();
+        EditorAction::SelectTextStart => {
+            app.get_current_input()?
+                .move_cursor(Input::top_beginning_of_line, true);
         }
-        Key::Ctrl(CtrlKey::Left) => {
-            app.get_current_input().move_left_word();
+        EditorAction::SelectTextEnd => {
+            app.get_current_input()?
+                .move_cursor(Input::bottom_end_of_line, true);
         }
-        Key::Ctrl(CtrlKey::Right) => {
-            app.get_current_input().move_right_word();
+        EditorAction::DeleteWordBackward => {
+            let killed = app.get_current_input()?.backspace_word();
+            app.kill(killed);
         }
-        Key::Ctrl(CtrlKey::Delete) => {
-            app.get_current_input().delete_word();
+        EditorAction::DeleteWordForward => {
+            let killed = app.get_current_input()?.delete_word();
+            app.kill(killed);
         }
-        Key::Ctrl(CtrlKey::Char('g')) => {
-            fn action(app: &mut App) {
+        EditorAction::Copy => {
+            if let Some(text) = app.get_current_input()?.selected_text() {
+                app.kill(text);
+            }
+        }
+        EditorAction::Cut => {
+            if let Some(text) = app.get_current_input()?.cut() {
+                app.kill(text);
+            }
+        }
+        EditorAction::Paste => {
+            app.yank()?;
+        }
+        EditorAction::PastePrevious => {
+            app.yank_pop()?;
+        }
+        EditorAction::Undo => {
+            app.get_current_input()?.undo();
+        }
+        EditorAction::Redo => {
+            app.get_current_input()?.redo();
+        }
+        EditorAction::ToggleWrap => {
+            app.soft_wrap = !app.soft_wrap;
+        }
+        EditorAction::Save => {
+            save(app);
+        }
+        EditorAction::Close => {
+            quit(app);
+        }
+        // opened before vim mode sees the key
+        EditorAction::Help => {}
+    }
+    Ok(())
+}
+
+/// Ask to write the note being edited, leaving the editor once it is written.
+pub fn save(app: &mut App) {
+    fn action(app: &mut App) -> Result<()> {
+        let title = app.input_title.get_string();
+        let extension = app.handler.config.extension.clone();
+        match &app.file_status {
+            FileStatus::Edit(file) => {
+                let file = file.clone();
+                // start from the original file so metadata not shown in the editor is kept
+                let mut knowledge = Knowledge::from_file(&file)?;
+                let old_title = std::mem::replace(&mut knowledge.title, title);
+                knowledge.text = app.input_text.get_string();
+                knowledge.tag = split_tags(&app.input_tags.get_string());
+                knowledge.updated = Some(Local::now());
+                // a new title renames the note and rewrites the links to it
+                let path = app.handler.save_note(knowledge, &old_title)?;
+                if app.base_path == file {
+                    // the note was opened in FileMode::File, follow the rename
+                    app.base_path = path;
+                }
+            }
+            FileStatus::Create => {
                 let knowledge = Knowledge::new(
-                    app.input_title.get_string(),
+                    title,
                     app.input_text.get_string(),
                     String::new(),
                     app.input_tags.get_string(),
                 );
-                match &app.file_status {
-                    FileStatus::Edit(file) => {
-                        // remove the original referenced file for edit
-                        let stem = file
-                            .file_stem()
-                            .and_then(|e| e.to_str())
-                            .unwrap_or("Invalid_file");
-                        if stem != app.input_title.get_string() {
-                            remove_file(file).expect("Error in removing file!");
-                        }
-                        if app.base_path.is_file() {
-                            let mut path = app.base_path.clone();
-                            path.pop();
-                            path.push(app.input_title.get_string());
-                            std::fs::write(&path, &format!("{}", knowledge));
-                        } else {
-                            knowledge.write_to_file(app.base_path.clone(), "md").expect("Error in writing file")
-                        }
-                    }
-                    FileStatus::Create => {
-                        // base_path must be directory when we are in creat mode
-                        match knowledge.write_to_file(app.base_path.clone(), "md") {
-                            std::io::Result::Ok(()) => {}
-                            std::io::Result::Err(_e) => {
-                                panic!("Error in writing file!: {:?}, base_path: {:?}", _e, app.base_path.clone());
-                            }
-                        }
-                    }
-                }
-                app.pop_state();
+                // base_path must be directory when we are in creat mode
+                knowledge.write_to_file(app.base_path.clone(), &extension)?;
             }
-            app.push_state(ViewState::DialogView);
-            app.confirm_action = Some(action);
+        }
+        app.pop_state();
+        Ok(())
+    }
+    app.push_state(ViewState::DialogView);
+    app.confirm_action = Some(action);
 
-            app.confirm_text = match &app.file_status {
-                FileStatus::Create => {
-                    let input_title = app.input_title.get_string();
-                    if app.file_exist(&input_title) {
-                        format!("Confirm overwrite existing file: {}?", input_title)
-                    } else {
-                        format!("Confirm writing new file: {}?", input_title)
-                    }
-                }
-                FileStatus::Edit(s) => {
-                    let stem = s
-                        .file_stem()
-                        .and_then(|e| e.to_str())
-                        .unwrap_or("Invalid_file");
-                    let title = app.input_title.get_string();
-                    if stem == title.as_str() {
-                        format!("Save file: {}?", stem)
-                    } else {
-                        format!("Confirm overwrite file: {} with: {}?", stem, title)
-                    }
-                }
-            };
-            // default it to True so we don't need to use arrow key
-            app.confirm = true;
-        }
-        Key::Esc => {
-            fn action(app: &mut App) {
-                app.set_add_view_ref();
-                app.pop_state();
+    app.confirm_text = match &app.file_status {
+        FileStatus::Create => {
+            let input_title = app.input_title.get_string();
+            if app.file_exist(&input_title) {
+                format!("Confirm overwrite existing file: {}?", input_title)
+            } else {
+                format!("Confirm writing new file: {}?", input_title)
+            }
+        }
+        FileStatus::Edit(s) => {
+            let stem = s
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .unwrap_or("Invalid_file");
+            let title = app.input_title.get_string();
+            if stem == title.as_str() {
+                format!("Save file: {}?", stem)
+            } else {
+                format!(
+                    "Save and rename {} to {}, rewriting the links to it?",
+                    stem, title
+                )
             }
-            app.push_state(ViewState::DialogView);
-            app.confirm_action = Some(action);
-            app.confirm_text = format!("Confirm Quiting?");
-            app.confirm = false;
         }
-        _ => {}
+    };
+    // default it to True so we don't need to use arrow key
+    app.confirm = true;
+}
+
+/// Ask to leave the editor without saving.
+pub fn quit(app: &mut App) {
+    fn action(app: &mut App) -> Result<()> {
+        app.set_add_view_ref();
+        app.pop_state();
+        Ok(())
     }
+    app.push_state(ViewState::DialogView);
+    app.confirm_action = Some(action);
+    app.confirm_text = String::from("Confirm Quitting?");
+    app.confirm = false;
 }
@@ -1,16 +1,39 @@
 use crate::add_view;
-use crate::config::Config;
+use crate::data::{Backlink, Handler, Knowledge, Rename, SearchResult, WikiLink};
 use crate::dialog_view;
+use crate::error::{Error, Result};
 use crate::file_view;
+use crate::finder_view;
+use crate::fuzzy::fuzzy_match;
+use crate::help_view;
 use crate::key::Key;
+use crate::keymap::{binds, Bindings, GlobalAction, HelpEntry, Keymaps};
+use crate::markdown;
+use crate::rename_view;
+use crate::search_view;
+use crate::sequence::{Context, Sequence};
+use crate::tag_view;
+use crate::trash::{TrashEntry, TRASH_DIR};
+use crate::trash_view;
+use crate::util::osc52_copy;
 use crate::util::BiCycle;
-use std::fs::{remove_dir_all, remove_file};
+use crate::vim::{Mode, Vim};
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
+#[derive(Clone, Copy)]
 pub enum ViewState {
     FileView,
     AddView,
     TagView,
+    SearchView,
+    FinderView,
+    HelpView,
+    RenameView,
+    TrashView,
     DialogView,
 }
 
@@ -20,90 +43,527 @@ pub enum Tab {
     Text,
 }
 
+/// Position in an `Input` as (vertical_idx, horizontal_idx).
+pub type Position = (usize, usize);
+
+/// Only this many edits can be undone.
+const HISTORY_LIMIT: usize = 500;
+
+/// One step of the undo history: `removed` was replaced by `inserted` at `start`.
+struct Edit {
+    start: Position,
+    removed: String,
+    inserted: String,
+    cursor_before: Position,
+    cursor_after: Position,
+}
+
+/// Where `text` ends when it is inserted at `start`.
+fn end_position(start: Position, text: &str) -> Position {
+    let mut lines = text.split('\n');
+    let first = lines.next().unwrap_or("").len();
+    match lines.enumerate().last() {
+        Some((i, last)) => (start.0 + i + 1, last.len()),
+        None => (start.0, start.1 + first),
+    }
+}
+
+/// Kind of a character for word motions: blank, word character or punctuation.
+fn char_class(c: char) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Byte index of the grapheme cluster before `index` in `row`.
+fn previous_boundary(row: &str, index: usize) -> usize {
+    row[..index]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Byte index of the grapheme cluster after the one at `index` in `row`.
+fn next_boundary(row: &str, index: usize) -> usize {
+    row[index..]
+        .graphemes(true)
+        .next()
+        .map(|g| index + g.len())
+        .unwrap_or_else(|| row.len())
+}
+
+/// Number of terminal columns `text` takes, wide characters taking two.
+pub fn text_width(text: &str) -> usize {
+    text.graphemes(true).map(|g| g.width()).sum()
+}
+
+/// Byte index in `row` of the cluster at screen `column`, or the end of the row if it is
+/// shorter. A wide character covering `column` counts as being at it.
+fn column_to_index(row: &str, column: usize) -> usize {
+    let mut width = 0;
+    for (i, g) in row.grapheme_indices(true) {
+        width += g.width();
+        if width > column {
+            return i;
+        }
+    }
+    row.len()
+}
+
+/// Byte range of the clusters of `text` shown in the `width` columns after `left`, and
+/// the number of columns to pad before it for a wide character cut off by the left edge.
+pub fn visible_range(text: &str, left: usize, width: usize) -> (std::ops::Range<usize>, usize) {
+    let mut column = 0;
+    let mut start = None;
+    let mut padding = 0;
+    for (i, g) in text.grapheme_indices(true) {
+        let next = column + g.width();
+        match start {
+            None if column >= left => start = Some(i),
+            None if next > left => padding = next - left,
+            _ => {}
+        }
+        if let Some(start) = start {
+            if next > left + width {
+                return (start..i, padding);
+            }
+        }
+        column = next;
+    }
+    (start.unwrap_or(text.len())..text.len(), padding)
+}
+
+/// Byte ranges of the occurrences of `query` in `text`, not overlapping.
+fn find_all(text: &str, query: &str, ignore_case: bool) -> Vec<std::ops::Range<usize>> {
+    let same = |a: char, b: char| {
+        if ignore_case {
+            a.to_lowercase().eq(b.to_lowercase())
+        } else {
+            a == b
+        }
+    };
+    let mut found = vec![];
+    let mut start = 0;
+    'search: while start < text.len() {
+        let mut chars = text[start..].char_indices();
+        let mut end = start;
+        for q in query.chars() {
+            match chars.next() {
+                Some((i, c)) if same(c, q) => end = start + i + c.len_utf8(),
+                _ => {
+                    start += text[start..].chars().next().map_or(1, |c| c.len_utf8());
+                    continue 'search;
+                }
+            }
+        }
+        found.push(start..end);
+        start = end.max(start + 1);
+    }
+    found
+}
+
+/// One line on screen of an `Input`: bytes `start..end` of row `row`.
+pub struct ScreenLine {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The part of an `Input` shown on screen, moved along with the cursor when drawn.
+#[derive(Default, Clone, Copy)]
+pub struct Viewport {
+    /// first screen line shown
+    pub top: usize,
+    /// first column shown, lines are cut off instead of wrapped
+    pub left: usize,
+    /// number of screen lines shown the last time it was drawn
+    pub height: usize,
+}
+
+/// Editable text. The cursor is a row and a byte index into it, always at the start of a
+/// grapheme cluster so that a character with its combining marks moves as one.
 pub struct Input {
-    pub input: Vec<Vec<char>>,
+    pub input: Vec<String>,
     pub horizontal_idx: usize,
     pub vertical_idx: usize,
+    pub viewport: Viewport,
+    /// the other end of the selection, the cursor being one end
+    anchor: Option<Position>,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
 }
 
 impl Default for Input {
     fn default() -> Self {
         Self {
-            input: vec![vec![]],
+            input: vec![String::new()],
             horizontal_idx: 0,
             vertical_idx: 0,
+            viewport: Viewport::default(),
+            anchor: None,
+            undo_stack: vec![],
+            redo_stack: vec![],
         }
     }
 }
 
 impl Input {
     pub fn insert(&mut self, c: char) {
-        if let Some(row) = self.input.get_mut(self.vertical_idx) {
-            row.insert(self.horizontal_idx, c);
-            self.horizontal_idx += 1;
+        let (start, end) = self.selection().unwrap_or((self.cursor(), self.cursor()));
+        self.edit(start, end, &c.to_string(), true);
+    }
+
+    /// Insert `text` at the cursor, replacing the selection, as one undo step.
+    pub fn insert_text(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let (start, end) = self.selection().unwrap_or((self.cursor(), self.cursor()));
+        self.edit(start, end, &text, false);
+    }
+
+    /// Replace the text inserted by the last edit with `text`, keeping it one undo step.
+    pub fn amend_last_edit(&mut self, text: &str) {
+        if let Some(mut edit) = self.undo_stack.pop() {
+            let end = end_position(edit.start, &edit.inserted);
+            edit.cursor_after = self.splice(edit.start, end, text);
+            edit.inserted = text.to_owned();
+            self.set_cursor(edit.cursor_after);
+            self.anchor = None;
+            self.undo_stack.push(edit);
         }
     }
 
+    /// Start and end of the selected text, None if nothing is selected.
+    pub fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.anchor?;
+        let cursor = self.cursor();
+        if anchor < cursor {
+            Some((anchor, cursor))
+        } else if cursor < anchor {
+            Some((cursor, anchor))
+        } else {
+            None
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection()
+            .map(|(start, end)| self.text_range(start, end))
+    }
+
+    /// Remove the selected text and return it.
+    pub fn cut(&mut self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        let text = self.text_range(start, end);
+        self.edit(start, end, "", false);
+        Some(text)
+    }
+
+    /// Move the cursor with one of the `move_*` methods. With `select` the selection is
+    /// extended to the new position, otherwise it is dropped.
+    pub fn move_cursor(&mut self, movement: fn(&mut Input), select: bool) {
+        if !select {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor());
+        }
+        movement(self);
+    }
+
+    /// Delete the selected text, returning false if nothing is selected.
+    fn delete_selection(&mut self) -> bool {
+        self.cut().is_some()
+    }
+
+    /// Replace the content with `s`, e.g. when a note is opened. This starts a new history.
     pub fn insert_string(&mut self, s: &str) {
-        let new_vec: Vec<_> = s
-            .split('\n')
-            .map(|e| e.chars().collect::<Vec<_>>())
-            .collect();
-        self.input = new_vec;
+        self.input = s.split('\n').map(String::from).collect();
         if let Some(last_input) = self.input.last() {
             self.horizontal_idx = last_input.len();
             self.vertical_idx = self.input.len() - 1;
         }
+        self.anchor = None;
+        self.viewport = Viewport::default();
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    pub fn cursor(&self) -> Position {
+        (self.vertical_idx, self.horizontal_idx)
+    }
+
+    /// Move the cursor to `position`, dropping the selection.
+    pub fn jump_to(&mut self, position: Position) {
+        self.anchor = None;
+        self.set_cursor(position);
+    }
+
+    /// Select from `start` to `end`, the cursor being at `end`.
+    pub fn select(&mut self, start: Position, end: Position) {
+        self.anchor = Some(start);
+        self.set_cursor(end);
+    }
+
+    pub fn deselect(&mut self) {
+        self.anchor = None;
+    }
+
+    /// Start and end of rows `first` to `last`. With `newline` the line break separating
+    /// them from the other rows is included, so that removing the range removes the rows.
+    pub fn rows_range(&self, first: usize, last: usize, newline: bool) -> (Position, Position) {
+        let end = (last, self.input[last].len());
+        if newline {
+            if last + 1 < self.input.len() {
+                return ((first, 0), (last + 1, 0));
+            } else if first > 0 {
+                return ((first - 1, self.input[first - 1].len()), end);
+            }
+        }
+        ((first, 0), end)
+    }
+
+    /// The position after `position`, the start of the next row at the end of a row.
+    pub fn next_position(&self, (row, index): Position) -> Option<Position> {
+        if index < self.input[row].len() {
+            Some((row, next_boundary(&self.input[row], index)))
+        } else if row + 1 < self.input.len() {
+            Some((row + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    fn previous_position(&self, (row, index): Position) -> Option<Position> {
+        if index > 0 {
+            Some((row, previous_boundary(&self.input[row], index)))
+        } else if row > 0 {
+            Some((row - 1, self.input[row - 1].len()))
+        } else {
+            None
+        }
+    }
+
+    /// Word motion class of the character at `position`, a row end counts as blank.
+    fn class_at(&self, (row, index): Position) -> u8 {
+        char_class(self.input[row][index..].chars().next().unwrap_or('\n'))
+    }
+
+    /// Move to the start of the next word, like `w` in vim.
+    pub fn next_word_start(&mut self) {
+        let mut position = self.cursor();
+        let class = self.class_at(position);
+        // skip the rest of this word, then the blanks after it
+        while let Some(next) = self.next_position(position) {
+            position = next;
+            if self.class_at(position) != class {
+                break;
+            }
+        }
+        while self.class_at(position) == 0 {
+            match self.next_position(position) {
+                Some(next) => position = next,
+                None => break,
+            }
+        }
+        self.set_cursor(position);
+    }
+
+    /// Move to the start of this or the previous word, like `b` in vim.
+    pub fn previous_word_start(&mut self) {
+        let mut position = self.cursor();
+        while let Some(previous) = self.previous_position(position) {
+            position = previous;
+            if self.class_at(position) != 0 {
+                break;
+            }
+        }
+        let class = self.class_at(position);
+        while let Some(previous) = self.previous_position(position) {
+            if self.class_at(previous) != class {
+                break;
+            }
+            position = previous;
+        }
+        self.set_cursor(position);
+    }
+
+    /// Move to the last character of this or the next word, like `e` in vim.
+    pub fn word_end(&mut self) {
+        let mut position = self.cursor();
+        while let Some(next) = self.next_position(position) {
+            position = next;
+            if self.class_at(position) != 0 {
+                break;
+            }
+        }
+        let class = self.class_at(position);
+        while let Some(next) = self.next_position(position) {
+            if self.class_at(next) != class {
+                break;
+            }
+            position = next;
+        }
+        self.set_cursor(position);
+    }
+
+    fn set_cursor(&mut self, (vertical_idx, horizontal_idx): Position) {
+        self.vertical_idx = vertical_idx;
+        self.horizontal_idx = horizontal_idx;
+    }
+
+    /// Screen column of the cursor in its row.
+    pub fn cursor_column(&self) -> usize {
+        text_width(&self.input[self.vertical_idx][..self.horizontal_idx])
+    }
+
+    /// `position`, moved back to the start of the cluster it is in. Removing text can join
+    /// a combining mark to the character before it.
+    fn cluster_start(&self, (row, index): Position) -> Position {
+        let text = &self.input[row];
+        if index >= text.len() {
+            return (row, text.len());
+        }
+        let start = text
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .take_while(|&i| i <= index)
+            .last()
+            .unwrap_or(0);
+        (row, start)
+    }
+
+    /// Text between `start` and `end`, rows joined with newlines.
+    fn text_range(&self, start: Position, end: Position) -> String {
+        if start.0 == end.0 {
+            return self.input[start.0][start.1..end.1].to_owned();
+        }
+        let mut text = self.input[start.0][start.1..].to_owned();
+        for row in self.input[start.0 + 1..end.0].iter() {
+            text.push('\n');
+            text.push_str(row);
+        }
+        text.push('\n');
+        text.push_str(&self.input[end.0][..end.1]);
+        text
+    }
+
+    /// Replace the text between `start` and `end` with `text`. Returns where `text` ends.
+    fn splice(&mut self, start: Position, end: Position, text: &str) -> Position {
+        let tail = self.input[end.0][end.1..].to_owned();
+        let mut rows: Vec<String> = text.split('\n').map(String::from).collect();
+        rows[0].insert_str(0, &self.input[start.0][..start.1]);
+        if let Some(last) = rows.last_mut() {
+            last.push_str(&tail);
+        }
+        self.input.splice(start.0..=end.0, rows);
+        end_position(start, text)
+    }
+
+    /// Replace the text between `start` and `end` with `text` and record it for undo. With
+    /// `typing`, characters typed one after another are undone together, a word at a time.
+    fn edit(&mut self, start: Position, end: Position, text: &str, typing: bool) {
+        if start == end && text.is_empty() {
+            return;
+        }
+        self.anchor = None;
+        let cursor_before = self.cursor();
+        let removed = self.text_range(start, end);
+        let end_of_text = self.splice(start, end, text);
+        // inserted text leaves the cursor after it, deleted text at its start
+        let cursor_after = if text.is_empty() { start } else { end_of_text };
+        let cursor_after = self.cluster_start(cursor_after);
+        self.set_cursor(cursor_after);
+        self.redo_stack.clear();
+
+        let is_typing = typing && removed.is_empty() && text != "\n";
+        if let Some(last) = self.undo_stack.last_mut() {
+            let continues = last.removed.is_empty()
+                && !last.inserted.contains('\n')
+                && last.cursor_after == cursor_before
+                && end_position(last.start, &last.inserted) == start
+                // a space ends the word, the next character starts a new step
+                && !(last.inserted.ends_with(' ') && text != " ");
+            if is_typing && continues {
+                last.inserted.push_str(text);
+                last.cursor_after = cursor_after;
+                return;
+            }
+        }
+        self.undo_stack.push(Edit {
+            start,
+            removed,
+            inserted: text.to_owned(),
+            cursor_before,
+            cursor_after,
+        });
+        if self.undo_stack.len() > HISTORY_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    pub fn undo(&mut self) {
+        self.anchor = None;
+        if let Some(edit) = self.undo_stack.pop() {
+            let end = end_position(edit.start, &edit.inserted);
+            self.splice(edit.start, end, &edit.removed);
+            self.set_cursor(edit.cursor_before);
+            self.redo_stack.push(edit);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.anchor = None;
+        if let Some(edit) = self.redo_stack.pop() {
+            let end = end_position(edit.start, &edit.removed);
+            self.splice(edit.start, end, &edit.inserted);
+            self.set_cursor(edit.cursor_after);
+            self.undo_stack.push(edit);
+        }
     }
 
     pub fn get_string(&self) -> String {
-        self.input
-            .iter()
-            .map(|e| e.iter().collect::<String>())
-            .collect::<Vec<_>>()
-            .join("\n")
+        self.input.join("\n")
     }
 
     pub fn backspace(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        let cursor = self.cursor();
         if self.horizontal_idx > 0 {
-            self.horizontal_idx -= 1;
-            self.input
-                .get_mut(self.vertical_idx)
-                .unwrap()
-                .remove(self.horizontal_idx);
-        } else {
-            if self.vertical_idx > 0 {
-                let previous_row = self
-                    .input
-                    .get(self.vertical_idx)
-                    .expect("Error in vertical index!")
-                    .clone();
-                self.input.remove(self.vertical_idx);
-                self.vertical_idx -= 1;
-                if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-                    self.horizontal_idx = current_row.len();
-                    current_row.extend(previous_row);
-                }
-            }
+            let start = previous_boundary(&self.input[self.vertical_idx], self.horizontal_idx);
+            self.edit((self.vertical_idx, start), cursor, "", false);
+        } else if self.vertical_idx > 0 {
+            // join with the previous row
+            let previous_len = self.input[self.vertical_idx - 1].len();
+            self.edit((self.vertical_idx - 1, previous_len), cursor, "", false);
         }
     }
 
     pub fn delete(&mut self) {
-        if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-            if current_row.len() >= self.horizontal_idx + 1 {
-                current_row.remove(self.horizontal_idx);
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(current_row) = self.input.get(self.vertical_idx) {
+            if current_row.len() > self.horizontal_idx {
+                let end = next_boundary(current_row, self.horizontal_idx);
+                let cursor = self.cursor();
+                self.edit(cursor, (self.vertical_idx, end), "", false);
             }
         }
     }
 
     pub fn move_left(&mut self) {
         if self.horizontal_idx > 0 {
-            self.horizontal_idx -= 1;
-        } else {
-            if self.vertical_idx > 0 {
-                self.vertical_idx -= 1;
-                self.horizontal_idx = self.input.get(self.vertical_idx).unwrap().len();
-            }
+            self.horizontal_idx =
+                previous_boundary(&self.input[self.vertical_idx], self.horizontal_idx);
+        } else if self.vertical_idx > 0 {
+            self.vertical_idx -= 1;
+            self.horizontal_idx = self.input[self.vertical_idx].len();
         }
     }
 
@@ -115,46 +575,33 @@ impl Input {
                     self.horizontal_idx = 0;
                 }
             } else {
-                self.horizontal_idx += 1;
+                self.horizontal_idx = next_boundary(current_row, self.horizontal_idx);
             }
         }
     }
 
     pub fn move_up(&mut self) {
         if self.vertical_idx > 0 {
+            let column = self.cursor_column();
             self.vertical_idx -= 1;
-            self.readjust_horizontal_line();
+            self.horizontal_idx = column_to_index(&self.input[self.vertical_idx], column);
         }
     }
 
     pub fn move_down(&mut self) {
         if self.vertical_idx + 1 < self.input.len() {
+            let column = self.cursor_column();
             self.vertical_idx += 1;
-            self.readjust_horizontal_line();
-        }
-    }
-
-    fn readjust_horizontal_line(&mut self) {
-        if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-            if self.horizontal_idx + 1 > current_row.len() {
-                self.horizontal_idx = current_row.len();
-            }
+            self.horizontal_idx = column_to_index(&self.input[self.vertical_idx], column);
         }
     }
 
     pub fn new_line(&mut self) {
-        let remaining: Vec<_> = if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-            current_row.drain(self.horizontal_idx..).collect()
-        } else {
-            vec![]
-        };
-        self.input.insert(self.vertical_idx + 1, remaining);
-        self.vertical_idx += 1;
-        self.horizontal_idx = 0;
+        self.insert_text("\n");
     }
 
     pub fn end_of_line(&mut self) {
-        if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
+        if let Some(current_row) = self.input.get(self.vertical_idx) {
             self.horizontal_idx = current_row.len();
         }
     }
@@ -175,42 +622,46 @@ impl Input {
         }
     }
 
-    pub fn backspace_word(&mut self) {
-        if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-            if let Some(index) = current_row[..self.horizontal_idx]
-                .iter()
-                .rposition(|&e| e == ' ')
-            {
-                current_row.drain(index..self.horizontal_idx);
-                self.horizontal_idx = index;
-            } else {
-                // no space found. Delete until beginning
-                current_row.drain(..self.horizontal_idx);
-                self.horizontal_idx = 0;
-            }
+    /// Delete the word before the cursor, or the selection. Returns the deleted text.
+    pub fn backspace_word(&mut self) -> String {
+        if let Some(text) = self.cut() {
+            return text;
+        }
+        if let Some(current_row) = self.input.get(self.vertical_idx) {
+            // no space found. Delete until beginning
+            let index = current_row[..self.horizontal_idx].rfind(' ').unwrap_or(0);
+            let start = (self.vertical_idx, index);
+            let cursor = self.cursor();
+            let text = self.text_range(start, cursor);
+            self.edit(start, cursor, "", false);
+            return text;
         }
+        String::new()
     }
 
-    pub fn delete_word(&mut self) {
-        if let Some(current_row) = self.input.get_mut(self.vertical_idx) {
-            if let Some(index) = current_row[self.horizontal_idx..]
-                .iter()
-                .position(|&e| e == ' ')
-            {
-                current_row.drain(self.horizontal_idx..index + self.horizontal_idx + 1);
-            } else {
+    /// Delete the word after the cursor, or the selection. Returns the deleted text.
+    pub fn delete_word(&mut self) -> String {
+        if let Some(text) = self.cut() {
+            return text;
+        }
+        if let Some(current_row) = self.input.get(self.vertical_idx) {
+            let end = match current_row[self.horizontal_idx..].find(' ') {
+                Some(index) => index + self.horizontal_idx + 1,
                 // no space found. Delete until end
-                current_row.drain(self.horizontal_idx..);
-            }
+                None => current_row.len(),
+            };
+            let end = (self.vertical_idx, end);
+            let cursor = self.cursor();
+            let text = self.text_range(cursor, end);
+            self.edit(cursor, end, "", false);
+            return text;
         }
+        String::new()
     }
 
     pub fn move_left_word(&mut self) {
         if let Some(current_row) = self.input.get(self.vertical_idx) {
-            if let Some(index) = current_row[..self.horizontal_idx]
-                .iter()
-                .rposition(|&e| e == ' ')
-            {
+            if let Some(index) = current_row[..self.horizontal_idx].rfind(' ') {
                 self.horizontal_idx = index;
             } else {
                 self.horizontal_idx = 0;
@@ -220,16 +671,101 @@ impl Input {
 
     pub fn move_right_word(&mut self) {
         if let Some(current_row) = self.input.get(self.vertical_idx) {
-            if let Some(index) = current_row[self.horizontal_idx..]
-                .iter()
-                .position(|&e| e == ' ')
-            {
+            if let Some(index) = current_row[self.horizontal_idx..].find(' ') {
                 self.horizontal_idx = index + self.horizontal_idx + 1;
             } else {
                 self.horizontal_idx = current_row.len();
             }
         }
     }
+
+    /// Move the cursor up by the number of lines shown on screen.
+    pub fn page_up(&mut self) {
+        for _ in 0..self.viewport.height.max(1) {
+            self.move_up();
+        }
+    }
+
+    /// Move the cursor down by the number of lines shown on screen.
+    pub fn page_down(&mut self) {
+        for _ in 0..self.viewport.height.max(1) {
+            self.move_down();
+        }
+    }
+
+    /// Split the rows into screen lines at most `width` columns wide, breaking after a space
+    /// if possible. Without `wrap` every row is one line. A row filling the last line
+    /// is followed by an empty line, for the cursor at its end.
+    pub fn screen_lines(&self, width: usize, wrap: bool) -> Vec<ScreenLine> {
+        let width = width.max(1);
+        let mut lines = vec![];
+        for (row, text) in self.input.iter().enumerate() {
+            let mut start = 0;
+            let mut used = 0;
+            // where the line can be broken, after the last space
+            let mut after_space = None;
+            for (i, g) in text.grapheme_indices(true) {
+                while wrap && i > start && used + g.width() > width {
+                    let end = after_space.take().unwrap_or(i);
+                    lines.push(ScreenLine { row, start, end });
+                    start = end;
+                    used = text_width(&text[start..i]);
+                }
+                used += g.width();
+                if g == " " {
+                    after_space = Some(i + 1);
+                }
+            }
+            lines.push(ScreenLine {
+                row,
+                start,
+                end: text.len(),
+            });
+            if wrap && used >= width {
+                lines.push(ScreenLine {
+                    row,
+                    start: text.len(),
+                    end: text.len(),
+                });
+            }
+        }
+        lines
+    }
+
+    /// Index in `lines` of the line with the cursor, and the screen column of the cursor in it.
+    pub fn screen_cursor(&self, lines: &[ScreenLine]) -> (usize, usize) {
+        lines
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, line)| line.row == self.vertical_idx && line.start <= self.horizontal_idx)
+            .map(|(i, line)| {
+                let text = &self.input[line.row][line.start..self.horizontal_idx];
+                (i, text_width(text))
+            })
+            .unwrap_or((0, 0))
+    }
+
+    /// Scroll the viewport of `width` x `height` over `total` screen lines just enough to
+    /// show the cursor at screen line and column `cursor`.
+    pub fn scroll_to(&mut self, cursor: (usize, usize), total: usize, width: usize, height: usize) {
+        let viewport = &mut self.viewport;
+        let (line, column) = cursor;
+        viewport.height = height;
+        // don't leave empty lines at the bottom after text was removed
+        viewport.top = viewport.top.min(total.saturating_sub(height));
+        if line < viewport.top {
+            viewport.top = line;
+        } else if line >= viewport.top + height {
+            viewport.top = line + 1 - height;
+        }
+        if column < viewport.left {
+            // jump back by half a screen, so short lines don't look empty
+            viewport.left = column.saturating_sub(width / 2);
+        } else if column >= viewport.left + width {
+            viewport.left = column + 1 - width;
+        }
+    }
 }
 
 pub enum FileMode {
@@ -242,8 +778,55 @@ pub enum FileStatus {
     Edit(PathBuf),
 }
 
+pub enum TagFocus {
+    Tags,
+    Notes,
+}
+
+pub enum SearchFocus {
+    Query,
+    Results,
+}
+
+pub struct FinderMatch {
+    pub title: String,
+    /// path of the note in its data directory
+    pub path: String,
+    pub file: PathBuf,
+    /// whether the query matched the title better than the path
+    pub in_title: bool,
+    /// char indices of the title or path matching the query
+    pub indices: Vec<usize>,
+}
+
+/// Number of cut or copied texts kept for pasting.
+const KILL_RING_SIZE: usize = 16;
+
+/// The fuzzy finder only lists this many matches.
+const FINDER_LIMIT: usize = 200;
+
+/// The confirm dialog of a rename lists this many of the notes whose links are rewritten.
+const RENAME_PREVIEW_LIMIT: usize = 10;
+
+/// A match of the search in the reader: row of the rendered note and byte range in it.
+pub type ReaderMatch = (usize, std::ops::Range<usize>);
+
+/// A `[[wiki link]]` in the reader: row of the rendered note, byte range of its label and
+/// where it leads.
+pub type ReaderLink = (usize, std::ops::Range<usize>, WikiLink);
+
+pub struct TagEntry {
+    pub tag: String,
+    /// (title, path) of every note carrying this tag
+    pub notes: Vec<(String, PathBuf)>,
+}
+
 pub struct App {
     navigation_stack: Vec<ViewState>,
+    pub handler: Handler,
+    pub keymaps: Keymaps,
+    /// keys typed of a sequence that is not complete yet, and the count typed before it
+    pub sequence: Sequence,
 
     pub file_status: FileStatus,
     pub input_title: Input,
@@ -251,6 +834,12 @@ pub struct App {
     pub input_tags: Input,
     pub input_tabs: Vec<Tab>,
     pub input_current_tab: BiCycle,
+    /// wrap long lines of the text instead of scrolling sideways, toggled with Alt+Z
+    pub soft_wrap: bool,
+    /// where the cursor of the focused input was drawn
+    pub input_cursor: (u16, u16),
+    /// modal editing state, None unless enabled in the config
+    pub vim: Option<Vim>,
 
     pub file_hierarchy: String,
     pub files: Vec<String>,
@@ -258,10 +847,94 @@ pub struct App {
     pub file_mode: FileMode,
     pub file_cycle_stack: Vec<BiCycle>,
 
+    pub tags: Vec<TagEntry>,
+    pub tag_cycle: BiCycle,
+    pub tag_note_cycle: BiCycle,
+    pub tag_focus: TagFocus,
+
+    /// deleted notes and directories of every data directory, latest first
+    pub trash: Vec<TrashEntry>,
+    pub trash_cycle: BiCycle,
+
+    pub search_query: Input,
+    pub search_results: Vec<SearchResult>,
+    pub search_cycle: BiCycle,
+    pub search_focus: SearchFocus,
+    /// error of the last query, shown instead of updating the results
+    pub search_error: Option<String>,
+
+    /// cut and copied text, latest first
+    pub kill_ring: VecDeque<String>,
+    /// index in `kill_ring` of the text just pasted, for cycling with Alt+Y
+    pub yank_index: Option<usize>,
+
+    pub finder_query: Input,
+    /// (title, path in its data directory, file) of every note in the data directories
+    pub finder_paths: Vec<(String, String, PathBuf)>,
+    pub finder_matches: Vec<FinderMatch>,
+    pub finder_cycle: BiCycle,
+
+    /// filter of the help listing the keys
+    pub help_query: Input,
+    /// first line of the help shown, clamped when drawing
+    pub help_scroll: usize,
+    /// number of lines of the help shown, set when drawing
+    pub help_height: usize,
+
+    /// note shown in FileMode::File
+    pub reader_path: PathBuf,
+    pub reader_title: String,
+    pub reader_text: String,
+    /// plain text of every rendered line of the note, for searching
+    pub reader_lines: Vec<String>,
+    /// first line shown
+    pub reader_scroll: usize,
+    /// number of lines shown, set when drawing
+    pub reader_height: usize,
+    /// scroll offset of the notes shown before, restored when they are opened again
+    pub reader_offsets: HashMap<PathBuf, usize>,
+    pub reader_query: Input,
+    /// whether keys go to the query
+    pub reader_searching: bool,
+    pub reader_matches: Vec<ReaderMatch>,
+    /// index in `reader_matches` of the current match, moved with n and N
+    pub reader_match: usize,
+    pub reader_links: Vec<ReaderLink>,
+    /// index in `reader_links` of the link followed with Enter
+    pub reader_link: Option<usize>,
+    /// (note, scroll offset) of the notes left by following links, the latest last
+    pub link_history: Vec<(PathBuf, usize)>,
+    /// notes gone back from in `link_history`, the latest last
+    pub link_future: Vec<(PathBuf, usize)>,
+
+    /// whether the panel listing the notes linking to the note is shown in the file view
+    pub backlinks_shown: bool,
+    /// whether keys go to the backlinks panel
+    pub backlinks_focus: bool,
+    /// note the backlinks are listed for, the one in the reader or the selected file
+    pub backlinks_note: PathBuf,
+    pub backlinks: Vec<Backlink>,
+    /// index in `backlinks` of the selected one
+    pub backlink: usize,
+
+    /// new path of the note being renamed in its data directory, without the extension
+    pub rename_input: Input,
+    pub rename_from: PathBuf,
+    /// error of the last preview, shown in the rename popup
+    pub rename_error: Option<String>,
+    /// rename waiting for confirmation in the dialog
+    pub rename: Option<Rename>,
+
     pub confirm: bool,
     pub confirm_text: String,
-    pub confirm_action: Option<fn(&mut App)>,
+    pub confirm_action: Option<fn(&mut App) -> Result<()>>,
+    /// lines listed below `confirm_text`, cleared when the dialog closes
+    pub confirm_details: Vec<String>,
     pub previous_view: ViewState,
+    /// message of the error dialog, the dialog asks for confirmation when this is None
+    pub error: Option<String>,
+    /// note to open in the external editor, the UI suspends itself for it
+    pub external_edit: Option<PathBuf>,
 }
 
 impl Default for App {
@@ -270,45 +943,98 @@ impl Default for App {
             file_status: FileStatus::Create,
             file_mode: FileMode::Dir,
             navigation_stack: vec![ViewState::FileView],
+            handler: Handler::default(),
+            keymaps: Keymaps::default(),
+            sequence: Sequence::default(),
             input_title: Input::default(),
             input_text: Input::default(),
             input_tags: Input::default(),
             input_tabs: vec![],
             input_current_tab: BiCycle::default(),
+            soft_wrap: true,
+            input_cursor: (0, 0),
+            vim: None,
             file_hierarchy: String::default(),
             files: vec![],
             base_path: PathBuf::default(),
             file_cycle_stack: vec![],
+            tags: vec![],
+            tag_cycle: BiCycle::default(),
+            tag_note_cycle: BiCycle::default(),
+            tag_focus: TagFocus::Tags,
+            trash: vec![],
+            trash_cycle: BiCycle::default(),
+            search_query: Input::default(),
+            search_results: vec![],
+            search_cycle: BiCycle::default(),
+            search_focus: SearchFocus::Query,
+            search_error: None,
+            kill_ring: VecDeque::new(),
+            yank_index: None,
+            finder_query: Input::default(),
+            finder_paths: vec![],
+            finder_matches: vec![],
+            finder_cycle: BiCycle::default(),
+            help_query: Input::default(),
+            help_scroll: 0,
+            help_height: 0,
+            reader_path: PathBuf::default(),
+            reader_title: String::default(),
+            reader_text: String::default(),
+            reader_lines: vec![],
+            reader_scroll: 0,
+            reader_height: 0,
+            reader_offsets: HashMap::new(),
+            reader_query: Input::default(),
+            reader_searching: false,
+            reader_matches: vec![],
+            reader_match: 0,
+            reader_links: vec![],
+            reader_link: None,
+            link_history: vec![],
+            link_future: vec![],
+            backlinks_shown: false,
+            backlinks_focus: false,
+            backlinks_note: PathBuf::default(),
+            backlinks: vec![],
+            backlink: 0,
+            rename_input: Input::default(),
+            rename_from: PathBuf::default(),
+            rename_error: None,
+            rename: None,
             confirm: false,
             confirm_text: String::default(),
             confirm_action: None,
+            confirm_details: vec![],
             previous_view: ViewState::AddView,
+            error: None,
+            external_edit: None,
         }
     }
 }
 
 impl App {
-    pub fn set_file_view(mut self, config: &Config) -> Self {
-        let file_directory = config.data_directories.last().unwrap();
-        match App::get_file_list(file_directory) {
-            Ok(item) => {
-                let item_len = item.len();
-                self.files = item;
-                self.file_cycle_stack = vec![BiCycle::new(item_len)];
-                self.base_path = PathBuf::from(file_directory.clone());
-                self.file_mode = FileMode::Dir
-            }
-            Err(_) => {
-                panic!(
-                    "{}",
-                    format!(
-                        "Data directories specified in config: {} is not a directory!",
-                        file_directory
-                    )
-                );
-            }
+    pub fn set_file_view(mut self, handler: Handler) -> Result<Self> {
+        self.handler = handler;
+        let file_directory = self
+            .handler
+            .config
+            .data_directories
+            .last()
+            .cloned()
+            .ok_or_else(|| Error::Config(String::from("no data directory to show")))?;
+        let item = App::get_file_list(&file_directory).map_err(Error::file(&file_directory))?;
+        let item_len = item.len();
+        self.files = item;
+        self.file_cycle_stack = vec![BiCycle::new(item_len)];
+        self.base_path = PathBuf::from(file_directory);
+        self.file_mode = FileMode::Dir;
+        self.soft_wrap = self.handler.config.soft_wrap;
+        self.keymaps = Keymaps::new(&self.handler.config.keys, &self.handler.config.leader)?;
+        if self.handler.config.vim {
+            self.vim = Some(Vim::default());
         }
-        self
+        Ok(self)
     }
 
     pub fn set_add_view(mut self) -> Self {
@@ -325,25 +1051,760 @@ impl App {
         self.input_tags = Input::default();
         self.input_tabs = s;
         self.input_current_tab = BiCycle::new(len);
+        if let Some(vim) = &mut self.vim {
+            // every note starts in normal mode
+            *vim = Vim::default();
+        }
+        self
+    }
+
+    pub fn set_edit_view_ref(&mut self, file: PathBuf) -> Result<&mut Self> {
+        let knowledge = Knowledge::from_file(&file)?;
+        self.set_add_view_ref();
+        self.input_title.insert_string(&knowledge.title);
+        self.input_tags.insert_string(&knowledge.tag.join(", "));
+        self.input_text.insert_string(&knowledge.text);
+        self.file_status = FileStatus::Edit(file);
+        Ok(self)
+    }
+
+    pub fn set_tag_view_ref(&mut self) -> Result<&mut Self> {
+        self.handler.read_all_files()?;
+        let mut tags: Vec<TagEntry> = {
+            self.handler
+                .get_mapping()
+                .into_iter()
+                .map(|(tag, notes)| TagEntry {
+                    tag,
+                    notes: notes
+                        .iter()
+                        .map(|k| (k.title.clone(), k.path.clone()))
+                        .collect(),
+                })
+                .collect()
+        };
+        tags.sort_by(|a, b| a.tag.cmp(&b.tag));
+        for entry in tags.iter_mut() {
+            entry.notes.sort();
+        }
+        // keep the selection when coming back from an edit or delete
+        let selected_tag = self.get_selected_tag().map(|e| e.tag.clone());
+        self.tags = tags;
+        self.tag_cycle = BiCycle::new(self.tags.len());
+        if let Some(index) = selected_tag.and_then(|t| self.tags.iter().position(|e| e.tag == t)) {
+            self.tag_cycle.current_item = index;
+        }
+        self.refresh_tag_notes();
+        Ok(self)
+    }
+
+    /// Reset the note selection after the selected tag changed.
+    pub fn refresh_tag_notes(&mut self) {
+        let current_item = self.tag_note_cycle.current_item;
+        let len = self.get_selected_tag().map(|e| e.notes.len()).unwrap_or(0);
+        self.tag_note_cycle = BiCycle::new(len);
+        if current_item < len {
+            self.tag_note_cycle.current_item = current_item;
+        }
+    }
+
+    pub fn get_selected_tag(&self) -> Option<&TagEntry> {
+        self.tags.get(self.tag_cycle.current_item)
+    }
+
+    pub fn get_selected_tag_note(&self) -> Option<&(String, PathBuf)> {
+        self.get_selected_tag()
+            .and_then(|e| e.notes.get(self.tag_note_cycle.current_item))
+    }
+
+    /// Read the trash again, keeping the selection at the same place.
+    pub fn set_trash_view_ref(&mut self) -> &mut Self {
+        self.trash = self.handler.trash_entries();
+        let current_item = self.trash_cycle.current_item;
+        self.trash_cycle = BiCycle::new(self.trash.len());
+        self.trash_cycle.current_item = current_item.min(self.trash.len().saturating_sub(1));
+        self
+    }
+
+    pub fn get_selected_trash(&self) -> Option<&TrashEntry> {
+        self.trash.get(self.trash_cycle.current_item)
+    }
+
+    /// Move the selected entry of the trash back where it was and select it in the file view.
+    pub fn restore_trash(&mut self) -> Result<()> {
+        let path = match self.get_selected_trash() {
+            Some(entry) => entry.restore()?,
+            None => return Ok(()),
+        };
+        let _ = self.handler.refresh();
+        self.jump_to(&path);
+        self.set_trash_view_ref();
+        Ok(())
+    }
+
+    /// Delete the selected entry of the trash for good.
+    pub fn purge_trash(&mut self) -> Result<()> {
+        match self.get_selected_trash() {
+            Some(entry) => entry.purge(),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete every entry of the trash for good.
+    pub fn empty_trash(&mut self) -> Result<()> {
+        for entry in self.trash.iter() {
+            entry.purge()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_search_view_ref(&mut self) -> &mut Self {
+        let _ = self.handler.refresh();
+        self.search_query = Input::default();
+        self.search_focus = SearchFocus::Query;
+        self.search_cycle = BiCycle::default();
+        self.refresh_search();
+        self
+    }
+
+    /// Run the query again, keeping the selection if it is still in range. An invalid query
+    /// keeps the previous results.
+    pub fn refresh_search(&mut self) {
+        match self.handler.search(&self.search_query.get_string()) {
+            Ok(results) => {
+                self.search_results = results;
+                self.search_error = None;
+            }
+            Err(e) => {
+                self.search_error = Some(e.to_string());
+                return;
+            }
+        }
+        let current_item = self.search_cycle.current_item;
+        self.search_cycle = BiCycle::new(self.search_results.len());
+        if current_item < self.search_results.len() {
+            self.search_cycle.current_item = current_item;
+        }
+    }
+
+    pub fn get_selected_search_result(&self) -> Option<&SearchResult> {
+        self.search_results.get(self.search_cycle.current_item)
+    }
+
+    pub fn set_finder_view_ref(&mut self) -> &mut Self {
+        let _ = self.handler.update_index();
+        let handler = &self.handler;
+        let mut paths: Vec<(String, String, PathBuf)> = handler
+            .index
+            .iter()
+            .flat_map(|index| index.documents())
+            .map(|doc| {
+                let root = handler.data_directory(&doc.path);
+                let path = doc.path.strip_prefix(&root).unwrap_or(&doc.path);
+                let path = path.to_string_lossy().into_owned();
+                (doc.title.clone(), path, doc.path.clone())
+            })
+            .collect();
+        paths.sort_by(|a, b| a.1.cmp(&b.1));
+        self.finder_paths = paths;
+        self.finder_query = Input::default();
+        self.refresh_finder();
         self
     }
 
+    /// Match the title and path of every note against the query, best match first.
+    pub fn refresh_finder(&mut self) {
+        let query = self.finder_query.get_string();
+        let mut matches: Vec<(i64, FinderMatch)> = self
+            .finder_paths
+            .iter()
+            .filter_map(|(title, path, file)| {
+                let by_path = fuzzy_match(&query, path);
+                let by_title = fuzzy_match(&query, title);
+                let (score, indices, in_title) = match (by_path, by_title) {
+                    (Some(p), Some(t)) if t.0 > p.0 => (t.0, t.1, true),
+                    (Some(p), _) => (p.0, p.1, false),
+                    (None, Some(t)) => (t.0, t.1, true),
+                    (None, None) => return None,
+                };
+                Some((
+                    score,
+                    FinderMatch {
+                        title: title.clone(),
+                        path: path.clone(),
+                        file: file.clone(),
+                        in_title,
+                        indices,
+                    },
+                ))
+            })
+            .collect();
+        // stable sort, equal scores keep the alphabetical order of finder_paths
+        matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        matches.truncate(FINDER_LIMIT);
+        self.finder_matches = matches.into_iter().map(|(_, m)| m).collect();
+        self.finder_cycle = BiCycle::new(self.finder_matches.len());
+    }
+
+    pub fn get_selected_finder_match(&self) -> Option<&FinderMatch> {
+        self.finder_matches.get(self.finder_cycle.current_item)
+    }
+
+    /// Show the note at `base_path` in the reader. Reading the same note again keeps the scroll
+    /// offset and the search.
+    fn open_reader(&mut self) {
+        if self.reader_path != self.base_path {
+            if !self.reader_path.as_os_str().is_empty() {
+                self.reader_offsets
+                    .insert(self.reader_path.clone(), self.reader_scroll);
+            }
+            self.reader_path = self.base_path.clone();
+            self.reader_scroll = self
+                .reader_offsets
+                .get(&self.base_path)
+                .copied()
+                .unwrap_or(0);
+            self.reader_query = Input::default();
+            self.reader_searching = false;
+            self.reader_link = None;
+        }
+        let knowledge = match Knowledge::from_file(&self.base_path) {
+            Ok(knowledge) => knowledge,
+            Err(e) => Knowledge {
+                text: e.to_string(),
+                ..Knowledge::default()
+            },
+        };
+        // the width only matters for horizontal rules, which are never matched
+        let (text, links) = markdown::render(&knowledge.text, 0);
+        self.reader_lines = text
+            .lines
+            .iter()
+            .map(|spans| spans.0.iter().map(|span| span.content.as_ref()).collect())
+            .collect();
+        self.reader_links = links;
+        if matches!(self.reader_link, Some(i) if i >= self.reader_links.len()) {
+            self.reader_link = None;
+        }
+        self.reader_title = knowledge.title;
+        self.reader_text = knowledge.text;
+        self.refresh_reader_search();
+    }
+
+    /// Scroll offset showing the end of the note.
+    pub fn reader_max_scroll(&self) -> usize {
+        self.reader_lines
+            .len()
+            .saturating_sub(self.reader_height.max(1))
+    }
+
+    /// Scroll the reader by `lines`, up if negative.
+    pub fn scroll_reader(&mut self, lines: isize) {
+        let scroll = (self.reader_scroll as isize + lines).max(0) as usize;
+        self.reader_scroll = scroll.min(self.reader_max_scroll());
+    }
+
+    /// Find the query in the note, ignoring case unless the query has capitals. The current
+    /// match becomes the first one from the top of the screen on.
+    pub fn refresh_reader_search(&mut self) {
+        let query = self.reader_query.get_string();
+        self.reader_matches = if query.is_empty() {
+            vec![]
+        } else {
+            let ignore_case = !query.chars().any(char::is_uppercase);
+            self.reader_lines
+                .iter()
+                .enumerate()
+                .flat_map(|(row, line)| {
+                    find_all(line, &query, ignore_case)
+                        .into_iter()
+                        .map(move |range| (row, range))
+                })
+                .collect()
+        };
+        let scroll = self.reader_scroll;
+        self.reader_match = self
+            .reader_matches
+            .iter()
+            .position(|(row, _)| *row >= scroll)
+            .unwrap_or(0);
+    }
+
+    /// Move to the next match, or the previous one, wrapping around the note.
+    pub fn next_reader_match(&mut self, forward: bool) {
+        let len = self.reader_matches.len();
+        if len == 0 {
+            return;
+        }
+        self.reader_match = if forward {
+            (self.reader_match + 1) % len
+        } else {
+            (self.reader_match + len - 1) % len
+        };
+        self.show_reader_match();
+    }
+
+    /// Scroll the current match into view if it is off screen.
+    pub fn show_reader_match(&mut self) {
+        if let Some(&(row, _)) = self.reader_matches.get(self.reader_match) {
+            let height = self.reader_height.max(1);
+            if row < self.reader_scroll || row >= self.reader_scroll + height {
+                // leave some lines above the match to read it in context
+                self.reader_scroll = row.saturating_sub(height / 3).min(self.reader_max_scroll());
+            }
+        }
+    }
+
+    /// Select the next link of the note, or the previous one, wrapping around the note. The
+    /// first one selected is the first one on screen, or the last one going backwards.
+    pub fn next_reader_link(&mut self, forward: bool) {
+        let len = self.reader_links.len();
+        if len == 0 {
+            return;
+        }
+        let link = match self.reader_link {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None if forward => {
+                let top = self.reader_scroll;
+                let on_screen = self.reader_links.iter().position(|(row, _, _)| *row >= top);
+                on_screen.unwrap_or(0)
+            }
+            None => {
+                let bottom = self.reader_scroll + self.reader_height.max(1);
+                let on_screen = self
+                    .reader_links
+                    .iter()
+                    .rposition(|(row, _, _)| *row < bottom);
+                on_screen.unwrap_or(len - 1)
+            }
+        };
+        self.reader_link = Some(link);
+        self.show_reader_link();
+    }
+
+    /// Scroll the selected link into view if it is off screen.
+    fn show_reader_link(&mut self) {
+        if let Some(&(row, _, _)) = self.reader_link.and_then(|i| self.reader_links.get(i)) {
+            let height = self.reader_height.max(1);
+            if row < self.reader_scroll || row >= self.reader_scroll + height {
+                self.reader_scroll = row.saturating_sub(height / 3).min(self.reader_max_scroll());
+            }
+        }
+    }
+
+    /// Open the note the selected link leads to, at its heading if it has one. The note left
+    /// is put on `link_history`.
+    pub fn follow_reader_link(&mut self) -> Result<()> {
+        let link = match self.reader_link.and_then(|i| self.reader_links.get(i)) {
+            Some((_, _, link)) => link.clone(),
+            None => return Ok(()),
+        };
+        let path = if link.target.is_empty() {
+            self.reader_path.clone()
+        } else {
+            self.find_note(&link.target)?
+        };
+        self.link_history
+            .push((self.reader_path.clone(), self.reader_scroll));
+        self.link_future.clear();
+        self.open_note(&path);
+        if let Some(heading) = &link.heading {
+            let heading = heading.to_lowercase();
+            let row = self.reader_lines.iter().position(|line| {
+                line.starts_with('#')
+                    && line.trim_start_matches('#').trim().to_lowercase() == heading
+            });
+            if let Some(row) = row {
+                self.reader_scroll = row.min(self.reader_max_scroll());
+            }
+        }
+        Ok(())
+    }
+
+    /// Go back to the note left by following a link, or forward again, where it was
+    /// scrolled to.
+    pub fn jump_link_history(&mut self, back: bool) {
+        let (from, to) = if back {
+            (&mut self.link_history, &mut self.link_future)
+        } else {
+            (&mut self.link_future, &mut self.link_history)
+        };
+        let (path, scroll) = match from.pop() {
+            Some(entry) => entry,
+            None => return,
+        };
+        to.push((self.reader_path.clone(), self.reader_scroll));
+        self.open_note(&path);
+        self.reader_scroll = scroll.min(self.reader_max_scroll());
+    }
+
+    /// List the backlinks of the note in the reader, or of the selected file, again. The
+    /// selection is kept while it is the same note.
+    pub fn refresh_backlinks(&mut self) {
+        let note = match self.file_mode {
+            FileMode::File => Some(self.reader_path.clone()),
+            FileMode::Dir => self
+                .get_current_selected_entry()
+                .filter(|path| path.is_file()),
+        };
+        self.backlinks = match &note {
+            Some(note) => match self.handler.backlinks_to(note) {
+                Ok(backlinks) => backlinks.to_vec(),
+                Err(e) => {
+                    self.show_error(e);
+                    vec![]
+                }
+            },
+            None => vec![],
+        };
+        let note = note.unwrap_or_default();
+        if note != self.backlinks_note {
+            self.backlinks_note = note;
+            self.backlink = 0;
+        }
+        self.backlink = self.backlink.min(self.backlinks.len().saturating_sub(1));
+    }
+
+    /// Open the note the selected backlink is in, with its link to the note left selected.
+    /// From the reader the note left is put on `link_history`, like following a link.
+    pub fn open_backlink(&mut self) {
+        let path = match self.backlinks.get(self.backlink) {
+            Some(backlink) => backlink.path.clone(),
+            None => return,
+        };
+        if let FileMode::File = self.file_mode {
+            self.link_history
+                .push((self.reader_path.clone(), self.reader_scroll));
+            self.link_future.clear();
+        }
+        let from = self.backlinks_note.clone();
+        self.open_note(&path);
+        let handler = &mut self.handler;
+        self.reader_link = self.reader_links.iter().position(|(_, _, link)| {
+            !link.target.is_empty()
+                && handler
+                    .find_by_title(&link.target)
+                    .is_ok_and(|notes| notes.iter().any(|note| note.path == from))
+        });
+        self.show_reader_link();
+    }
+
+    /// The path of the note titled `title`, reading the notes again if there is none, as it
+    /// may have been written since they were read.
+    fn find_note(&mut self, title: &str) -> Result<PathBuf> {
+        if self.handler.find_by_title(title)?.is_empty() {
+            self.handler.read_all_files()?;
+        }
+        let notes = self.handler.find_by_title(title)?;
+        match notes.as_slice() {
+            [] => Err(Error::NotFound(title.to_owned())),
+            [note] => Ok(note.path.clone()),
+            notes => Err(Error::Ambiguous(
+                title.to_owned(),
+                notes.iter().map(|note| note.path.clone()).collect(),
+            )),
+        }
+    }
+
+    /// Show the note at `path` in the reader of the file view.
+    fn open_note(&mut self, path: &Path) {
+        self.jump_to(path);
+        self.enter_directory();
+    }
+
+    /// Open the fuzzy finder over the current view.
+    pub fn open_finder(&mut self) {
+        self.set_finder_view_ref();
+        self.push_state(ViewState::FinderView);
+    }
+
+    /// Open the popup renaming the note in the reader or the selected note, starting from its
+    /// path in the data directory.
+    pub fn open_rename(&mut self) {
+        let note = match self.get_current_selected_entry() {
+            Some(entry) if entry.is_file() => entry,
+            _ => return,
+        };
+        let root = self.handler.data_directory(&note);
+        let name = note.strip_prefix(&root).unwrap_or(&note).with_extension("");
+        self.rename_input = Input::default();
+        self.rename_input.insert_string(&name.to_string_lossy());
+        self.rename_from = note;
+        self.rename_error = None;
+        self.push_state(ViewState::RenameView);
+    }
+
+    /// Plan the rename typed in the popup and ask to confirm it, listing the notes whose links
+    /// are rewritten. An invalid name is shown in the popup.
+    pub fn preview_rename(&mut self) {
+        let from = self.rename_from.clone();
+        let rename = match self
+            .handler
+            .plan_rename(&from, &self.rename_input.get_string())
+        {
+            Ok(rename) => rename,
+            Err(e) => {
+                self.rename_error = Some(e.to_string());
+                return;
+            }
+        };
+        self.pop_state();
+        if rename.to == rename.from {
+            return;
+        }
+        let root = self.handler.data_directory(&from);
+        let show = |path: &Path| {
+            path.strip_prefix(&root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        let links = |count: usize| match count {
+            1 => String::from("1 link"),
+            count => format!("{} links", count),
+        };
+        self.confirm_text = format!("Rename {} to {}?", show(&rename.from), show(&rename.to));
+        let mut details = vec![];
+        if rename.own_links > 0 {
+            details.push(format!(
+                "{}  {} in the note",
+                show(&rename.to),
+                links(rename.own_links)
+            ));
+        }
+        for (path, _, count) in rename.rewrites.iter().take(RENAME_PREVIEW_LIMIT) {
+            details.push(format!("{}  {}", show(path), links(*count)));
+        }
+        if rename.rewrites.len() > RENAME_PREVIEW_LIMIT {
+            let more = rename.rewrites.len() - RENAME_PREVIEW_LIMIT;
+            details.push(format!("and {} more notes", more));
+        }
+        if details.is_empty() {
+            details.push(String::from("no links to rewrite"));
+        }
+        self.confirm_details = details;
+        self.rename = Some(rename);
+
+        fn action(app: &mut App) -> Result<()> {
+            app.apply_rename()
+        }
+        self.push_state(ViewState::DialogView);
+        self.confirm_action = Some(action);
+        self.confirm = true;
+        self.previous_view = ViewState::FileView;
+    }
+
+    /// Carry out the rename confirmed in the dialog and show the note under its new name.
+    fn apply_rename(&mut self) -> Result<()> {
+        let rename = match self.rename.take() {
+            Some(rename) => rename,
+            None => return Ok(()),
+        };
+        rename.apply()?;
+        // the notes to go back to and their scroll offsets follow the note
+        for (path, _) in self
+            .link_history
+            .iter_mut()
+            .chain(self.link_future.iter_mut())
+        {
+            if *path == rename.from {
+                *path = rename.to.clone();
+            }
+        }
+        if let Some(offset) = self.reader_offsets.remove(&rename.from) {
+            self.reader_offsets.insert(rename.to.clone(), offset);
+        }
+        let was_open = matches!(self.file_mode, FileMode::File);
+        if self.reader_path == rename.from {
+            self.reader_path = rename.to.clone();
+        }
+        self.jump_to(&rename.to);
+        if was_open {
+            self.enter_directory();
+        }
+        Ok(())
+    }
+
+    /// Open the help over the current view.
+    pub fn open_help(&mut self) {
+        self.help_query = Input::default();
+        self.help_scroll = 0;
+        self.push_state(ViewState::HelpView);
+    }
+
+    /// Keys of the view below the help and the global ones, keeping those whose keys, name,
+    /// description or category contain the filter, ignoring case.
+    pub fn help_entries(&self) -> Vec<HelpEntry> {
+        let keymaps = &self.keymaps;
+        let mut entries = match self.get_previous_state() {
+            Some(ViewState::FileView) => {
+                // the backlinks panel and the reader get the keys first, the file view the rest
+                let backlinks = self.backlinks_focus;
+                let reader = matches!(self.file_mode, FileMode::File);
+                let in_backlinks =
+                    |keys: &[Key]| backlinks && keymaps.backlinks.get(keys).is_some();
+                let in_reader = |keys: &[Key]| reader && keymaps.reader.get(keys).is_some();
+                let mut entries = vec![];
+                if backlinks {
+                    entries.extend(keymaps.backlinks.help(|_| false));
+                }
+                if reader {
+                    entries.extend(keymaps.reader.help(in_backlinks));
+                }
+                entries.extend(
+                    keymaps
+                        .file
+                        .help(|keys| in_backlinks(keys) || in_reader(keys)),
+                );
+                entries
+            }
+            Some(ViewState::AddView) => keymaps.editor.help(|_| false),
+            Some(ViewState::TagView) => keymaps.tag.help(|_| false),
+            Some(ViewState::TrashView) => keymaps.trash.help(|_| false),
+            Some(ViewState::SearchView) => keymaps.search.help(|_| false),
+            Some(ViewState::FinderView) => keymaps.finder.help(|_| false),
+            Some(ViewState::DialogView) => keymaps.dialog.help(|_| false),
+            Some(ViewState::HelpView) | Some(ViewState::RenameView) | None => vec![],
+        };
+        let view = self.view_bindings(self.get_previous_state());
+        entries.extend(keymaps.global.help(|keys| binds(&view, keys)));
+        let query = self.help_query.get_string().to_lowercase();
+        entries.retain(|entry| {
+            [
+                entry.keys.as_str(),
+                entry.name,
+                entry.description,
+                entry.category,
+            ]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&query))
+        });
+        entries
+    }
+
+    /// The keymaps of the view `state`, in the order it looks keys up.
+    fn view_bindings(&self, state: Option<&ViewState>) -> Vec<&dyn Bindings> {
+        let keymaps = &self.keymaps;
+        match state {
+            Some(ViewState::FileView) => {
+                // the backlinks panel and the reader get the keys first, the file view the rest
+                let mut bindings: Vec<&dyn Bindings> = vec![];
+                if self.backlinks_focus {
+                    bindings.push(&keymaps.backlinks);
+                }
+                if let FileMode::File = self.file_mode {
+                    bindings.push(&keymaps.reader);
+                }
+                bindings.push(&keymaps.file);
+                bindings
+            }
+            Some(ViewState::AddView) => vec![&keymaps.editor],
+            Some(ViewState::TagView) => vec![&keymaps.tag],
+            Some(ViewState::TrashView) => vec![&keymaps.trash],
+            Some(ViewState::SearchView) => vec![&keymaps.search],
+            Some(ViewState::FinderView) => vec![&keymaps.finder],
+            Some(ViewState::DialogView) => vec![&keymaps.dialog],
+            Some(ViewState::HelpView) | Some(ViewState::RenameView) | None => vec![],
+        }
+    }
+
+    /// The global action of `key`, unless the current view binds it for itself.
+    pub fn global_action(&self, key: Key) -> Option<GlobalAction> {
+        if binds(&self.view_bindings(self.get_latest_state()), &[key]) {
+            return None;
+        }
+        self.keymaps.global.get(&[key])
+    }
+
+    /// The keymaps the keys typed in the current view are matched against.
+    pub fn key_context(&self) -> Context<'_> {
+        let state = self.get_latest_state();
+        let bindings = self.view_bindings(state);
+        let (bindings, counts, typing) = match state {
+            Some(ViewState::FileView) => match self.file_mode {
+                FileMode::File if self.reader_searching => (vec![], false, true),
+                _ => (bindings, true, false),
+            },
+            Some(ViewState::AddView) => match &self.vim {
+                // vim mode reads its own commands, like gg and dd, key by key
+                Some(vim) if vim.mode != Mode::Insert => (vec![], false, false),
+                _ => (bindings, false, false),
+            },
+            Some(ViewState::TagView) | Some(ViewState::TrashView) => (bindings, true, false),
+            Some(ViewState::SearchView) => match self.search_focus {
+                SearchFocus::Query => (bindings, false, true),
+                SearchFocus::Results => (bindings, true, false),
+            },
+            Some(ViewState::FinderView) => (bindings, false, true),
+            Some(ViewState::HelpView) | Some(ViewState::RenameView) => (bindings, false, true),
+            Some(ViewState::DialogView) | None => (bindings, false, false),
+        };
+        Context {
+            bindings,
+            counts,
+            typing,
+        }
+    }
+
+    /// The count typed before the keys being handled, 1 without one.
+    pub fn count(&self) -> usize {
+        self.sequence.count.unwrap_or(1)
+    }
+
     pub fn update_state(&mut self, event: &Key) {
-        // return err if it reaches last state in the stack
-        if let Some(state) = self.get_latest_mut_state() {
-            match state {
-                ViewState::FileView => {
-                    file_view::handler(self, event);
+        let mut sequence = std::mem::take(&mut self.sequence);
+        let ready = sequence.push(*event, &self.key_context());
+        self.sequence = sequence;
+        self.handle_keys(ready);
+    }
+
+    /// Stop waiting for the next key of a sequence once the timeout of the config is over.
+    pub fn tick(&mut self) {
+        let timeout = Duration::from_millis(self.handler.config.key_timeout);
+        if self.sequence.expired(timeout) {
+            let mut sequence = std::mem::take(&mut self.sequence);
+            let ready = sequence.flush(&self.key_context());
+            self.sequence = sequence;
+            self.handle_keys(ready);
+        }
+    }
+
+    /// Hand every key sequence in `ready` to the current view.
+    fn handle_keys(&mut self, ready: Vec<Vec<Key>>) {
+        for keys in ready {
+            match self.get_latest_state() {
+                Some(ViewState::FileView) => {
+                    file_view::handler(self, &keys);
+                }
+                Some(ViewState::AddView) => {
+                    add_view::handler(self, &keys);
+                }
+                Some(ViewState::DialogView) => {
+                    dialog_view::handler(self, &keys);
+                }
+                Some(ViewState::TagView) => {
+                    tag_view::handler(self, &keys);
                 }
-                ViewState::AddView => {
-                    add_view::handler(self, event);
+                Some(ViewState::TrashView) => {
+                    trash_view::handler(self, &keys);
                 }
-                ViewState::DialogView => {
-                    dialog_view::handler(self, event);
+                Some(ViewState::SearchView) => {
+                    search_view::handler(self, &keys);
                 }
-                ViewState::TagView => {}
+                Some(ViewState::FinderView) => {
+                    finder_view::handler(self, &keys);
+                }
+                Some(ViewState::HelpView) => {
+                    help_view::handler(self, &keys);
+                }
+                Some(ViewState::RenameView) => {
+                    rename_view::handler(self, &keys);
+                }
+                None => {}
             }
-        } else {
+            // the count is used up by the first keys
+            self.sequence.count = None;
         }
     }
 
@@ -355,6 +1816,103 @@ impl App {
         self.navigation_stack.pop();
     }
 
+    /// Put cut or copied text on the kill ring, and on the terminal clipboard if enabled.
+    pub fn kill(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        if self.handler.config.osc52 {
+            // the clipboard is a convenience, the kill ring still has the text
+            let _ = osc52_copy(&text);
+        }
+        self.kill_ring.push_front(text);
+        self.kill_ring.truncate(KILL_RING_SIZE);
+    }
+
+    /// Paste the latest text of the kill ring into the current input.
+    pub fn yank(&mut self) -> Result<()> {
+        if let Some(text) = self.kill_ring.front().cloned() {
+            self.get_current_input()?.insert_text(&text);
+            self.yank_index = Some(0);
+        }
+        Ok(())
+    }
+
+    /// Right after a paste, replace the pasted text with the next older one of the kill ring.
+    pub fn yank_pop(&mut self) -> Result<()> {
+        if let Some(index) = self.yank_index {
+            let index = (index + 1) % self.kill_ring.len();
+            let text = self.kill_ring[index].clone();
+            self.get_current_input()?.amend_last_edit(&text);
+            self.yank_index = Some(index);
+        }
+        Ok(())
+    }
+
+    /// Text pasted in the terminal, inserted into the input being typed in. Single line inputs
+    /// get its lines joined by spaces. Anywhere else, vim modes other than insert included,
+    /// it is ignored rather than taken for commands.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        let line = text.lines().collect::<Vec<_>>().join(" ");
+        match self.get_latest_state() {
+            Some(ViewState::AddView) => {
+                if matches!(&self.vim, Some(vim) if vim.mode != Mode::Insert) {
+                    return;
+                }
+                let multiline = matches!(
+                    self.input_tabs.get(self.input_current_tab.current_item),
+                    Some(Tab::Text)
+                );
+                match self.get_current_input() {
+                    Ok(input) => input.insert_text(if multiline { &text } else { &line }),
+                    Err(e) => self.show_error(e),
+                }
+            }
+            Some(ViewState::FileView)
+                if self.reader_searching && matches!(self.file_mode, FileMode::File) =>
+            {
+                self.reader_query.insert_text(&line);
+                self.refresh_reader_search();
+                self.show_reader_match();
+            }
+            Some(ViewState::SearchView) if matches!(self.search_focus, SearchFocus::Query) => {
+                self.search_query.insert_text(&line);
+                self.search_cycle.current_item = 0;
+                self.refresh_search();
+            }
+            Some(ViewState::FinderView) => {
+                self.finder_query.insert_text(&line);
+                self.refresh_finder();
+            }
+            Some(ViewState::HelpView) => {
+                self.help_query.insert_text(&line);
+                self.help_scroll = 0;
+            }
+            Some(ViewState::RenameView) => {
+                self.rename_input.insert_text(&line);
+                self.rename_error = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Show `error` in a dialog over the current view until it is dismissed.
+    pub fn show_error(&mut self, error: Error) {
+        match self.get_latest_state() {
+            // the confirm dialog is still open, keep the view below it
+            Some(ViewState::DialogView) | None => {}
+            Some(state) => self.previous_view = *state,
+        }
+        self.error = Some(error.to_string());
+        self.push_state(ViewState::DialogView);
+    }
+
+    /// Go back to the file view at the bottom of the stack.
+    pub fn pop_to_root(&mut self) {
+        self.navigation_stack.truncate(1);
+    }
+
     pub fn get_latest_mut_state(&mut self) -> Option<&mut ViewState> {
         self.navigation_stack.last_mut()
     }
@@ -363,18 +1921,34 @@ impl App {
         self.navigation_stack.last()
     }
 
+    /// Every state, the latest last.
+    pub fn get_states(&self) -> &[ViewState] {
+        &self.navigation_stack
+    }
+
+    /// The state below the latest one, which overlays are drawn on top of.
+    pub fn get_previous_state(&self) -> Option<&ViewState> {
+        let len = self.navigation_stack.len();
+        if len >= 2 {
+            self.navigation_stack.get(len - 2)
+        } else {
+            None
+        }
+    }
+
     fn get_file_list<T: AsRef<Path>>(path: T) -> std::io::Result<Vec<String>> {
         let item: Vec<_> = std::fs::read_dir(path)?
             .filter_map(|e| {
-                let file_type = e.as_ref().unwrap().file_type().ok()?;
+                let e = e.ok()?;
+                let file_type = e.file_type().ok()?;
                 if file_type.is_file() {
-                    if e.as_ref().unwrap().path().extension()?.clone() == "md" {
-                        Some(e.unwrap().file_name().into_string().unwrap())
+                    if e.path().extension()? == "md" {
+                        e.file_name().into_string().ok()
                     } else {
                         None
                     }
-                } else if file_type.is_dir() {
-                    Some(e.unwrap().file_name().into_string().unwrap())
+                } else if file_type.is_dir() && e.file_name() != TRASH_DIR {
+                    e.file_name().into_string().ok()
                 } else {
                     None
                 }
@@ -383,6 +1957,27 @@ impl App {
         Ok(item)
     }
 
+    /// Show the note at `old` after it was edited in the external editor, which may have
+    /// renamed it to `new`.
+    pub fn finish_external_edit(&mut self, old: &Path, new: Result<PathBuf>) {
+        let _ = self.handler.refresh();
+        match new {
+            Ok(new) if new != old => {
+                let was_open = self.base_path == old;
+                self.jump_to(&new);
+                if was_open {
+                    // the note was shown in FileMode::File, show it under its new name
+                    self.enter_directory();
+                }
+            }
+            Ok(_) => self.refresh_directory(),
+            Err(e) => {
+                self.refresh_directory();
+                self.show_error(e);
+            }
+        }
+    }
+
     pub fn refresh_directory(&mut self) {
         match App::get_file_list(&self.base_path) {
             Ok(files) => {
@@ -394,6 +1989,7 @@ impl App {
             Err(_e) => {
                 self.files.clear();
                 self.file_mode = FileMode::File;
+                self.open_reader();
             }
         }
     }
@@ -401,12 +1997,40 @@ impl App {
     pub fn enter_directory(&mut self) {
         // enter directory specify by `self.cycle.current_item`
         let cycle = self.file_cycle_stack.last().unwrap();
-        let selected_file = self.files.get(cycle.current_item).unwrap();
+        let selected_file = match self.files.get(cycle.current_item) {
+            Some(file) => file,
+            // empty directory
+            None => return,
+        };
         self.base_path.push(selected_file);
         self.file_cycle_stack.push(BiCycle::new(self.files.len()));
         self.refresh_directory();
     }
 
+    /// Point the file view at `path`: `base_path` becomes its directory inside the data
+    /// directory it belongs to, with every level on `file_cycle_stack`, and `path` selected.
+    pub fn jump_to<T: AsRef<Path>>(&mut self, path: T) {
+        let path = path.as_ref();
+        let root = self.handler.data_directory(path);
+        let relative: Vec<_> = path
+            .strip_prefix(&root)
+            .map(|p| p.components().collect())
+            .unwrap_or_default();
+        self.base_path = root;
+        self.file_cycle_stack = vec![BiCycle::new(0)];
+        self.refresh_directory();
+        for (i, component) in relative.iter().enumerate() {
+            let name = component.as_os_str().to_string_lossy();
+            match self.files.iter().position(|e| *e == name) {
+                Some(index) => self.file_cycle_stack.last_mut().unwrap().current_item = index,
+                None => break,
+            }
+            if i + 1 < relative.len() {
+                self.enter_directory();
+            }
+        }
+    }
+
     pub fn leave_directory(&mut self) {
         if self.file_cycle_stack.len() > 1 {
             self.base_path.pop();
@@ -415,55 +2039,48 @@ impl App {
         }
     }
 
-    pub fn get_current_selected_entry(&self) -> PathBuf {
+    /// The selected file or directory, None in an empty directory or if `base_path` was
+    /// removed behind our back.
+    pub fn get_current_selected_entry(&self) -> Option<PathBuf> {
         if self.base_path.is_dir() {
-            let cycle = self.file_cycle_stack.last().unwrap();
-            let selected_file = self.files.get(cycle.current_item).unwrap();
+            let cycle = self.file_cycle_stack.last()?;
+            let selected_file = self.files.get(cycle.current_item)?;
             let mut dir_to_remove = self.base_path.clone();
             dir_to_remove.push(selected_file);
-            dir_to_remove
+            Some(dir_to_remove)
         } else if self.base_path.is_file() {
-            self.base_path.clone()
+            Some(self.base_path.clone())
         } else {
-            panic!("selected path is not a file and directory")
+            None
         }
     }
 
-    pub fn remove_directory(&mut self) {
-        let entry = self.get_current_selected_entry();
-        if entry.is_dir() {
-            remove_dir_all(entry).expect("Error in removing this directory!");
-        } else if entry.is_file() {
-            remove_file(entry).expect("Error in removing this file");
+    pub fn remove_directory(&mut self) -> Result<()> {
+        let entry = match self.get_current_selected_entry() {
+            Some(entry) => entry,
+            None => return Ok(()),
+        };
+        self.handler.trash(&entry)?;
+        if let Some(cycle) = self.file_cycle_stack.last_mut() {
+            cycle.total_len = cycle.total_len.saturating_sub(1);
         }
-        self.file_cycle_stack.last_mut().unwrap().total_len -= 1;
+        Ok(())
     }
 
-    pub fn get_current_input(&mut self) -> &mut Input {
-        if let Some(s) = self.input_tabs.get(self.input_current_tab.current_item) {
-            match s {
-                Tab::Title => &mut self.input_title,
-                Tab::Text => &mut self.input_text,
-                Tab::Tags => &mut self.input_tags,
-            }
-        } else {
-            panic!("invalid tab selected!");
+    pub fn get_current_input(&mut self) -> Result<&mut Input> {
+        match self.input_tabs.get(self.input_current_tab.current_item) {
+            Some(Tab::Title) => Ok(&mut self.input_title),
+            Some(Tab::Text) => Ok(&mut self.input_text),
+            Some(Tab::Tags) => Ok(&mut self.input_tags),
+            None => Err(Error::NoInput),
         }
     }
 
     pub fn get_cursor_position(&self) -> (u16, u16) {
-        if let Some(s) = self.input_tabs.get(self.input_current_tab.current_item) {
-            match s {
-                Tab::Title => (3 + self.input_title.horizontal_idx as u16, 3),
-                Tab::Tags => (3 + self.input_tags.horizontal_idx as u16, 7),
-                Tab::Text => (
-                    3 + self.input_text.horizontal_idx as u16,
-                    12 + self.input_text.vertical_idx as u16,
-                ),
-            }
-        } else {
-            panic!("invalid tab selected!");
+        if let Some(ViewState::SearchView) = self.get_latest_state() {
+            return (3 + self.search_query.cursor_column() as u16, 3);
         }
+        self.input_cursor
     }
 
     pub fn file_exist(&self, title: &str) -> bool {
@@ -473,3 +2090,140 @@ impl App {
         path.exists()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An `Input` holding `text`, the cursor at the end.
+    fn input(text: &str) -> Input {
+        let mut input = Input::default();
+        input.insert_text(text);
+        input
+    }
+
+    fn ranges(lines: &[ScreenLine]) -> Vec<(usize, std::ops::Range<usize>)> {
+        lines.iter().map(|l| (l.row, l.start..l.end)).collect()
+    }
+
+    #[test]
+    fn width_of_wide_and_combining_characters() {
+        assert_eq!(text_width(""), 0);
+        assert_eq!(text_width("abc"), 3);
+        assert_eq!(text_width("日本語"), 6);
+        assert_eq!(text_width("🦀"), 2);
+        assert_eq!(text_width("e\u{301}"), 1);
+        assert_eq!(text_width("a日🦀e\u{301}"), 6);
+    }
+
+    #[test]
+    fn column_inside_a_wide_character_is_at_it() {
+        // a at 0, 日 at 1, 🦀 at 4, e with its accent at 8, x at 11
+        let row = "a日🦀e\u{301}x";
+        let indices: Vec<usize> = (0..8).map(|column| column_to_index(row, column)).collect();
+        assert_eq!(indices, vec![0, 1, 1, 4, 4, 8, 11, 12]);
+    }
+
+    #[test]
+    fn visible_range_pads_a_cut_off_wide_character() {
+        // 本 is shown after one column of padding for the half of 日 cut off
+        assert_eq!(visible_range("日本語", 1, 3), (3..6, 1));
+        assert_eq!(visible_range("日本語", 0, 5), (0..6, 0));
+        assert_eq!(visible_range("e\u{301}x", 1, 5), (3..4, 0));
+        assert_eq!(visible_range("ab", 5, 3), (2..2, 0));
+    }
+
+    #[test]
+    fn cursor_moves_over_whole_clusters() {
+        let mut input = input("e\u{301}🦀");
+        assert_eq!(input.horizontal_idx, 7);
+        input.move_left();
+        assert_eq!(input.horizontal_idx, 3);
+        input.move_left();
+        assert_eq!(input.horizontal_idx, 0);
+        input.move_right();
+        assert_eq!(input.horizontal_idx, 3);
+        assert_eq!(input.cursor_column(), 1);
+    }
+
+    #[test]
+    fn clusters_are_deleted_as_one() {
+        let mut input = input("a🦀e");
+        input.insert('\u{301}');
+        assert_eq!(input.get_string(), "a🦀e\u{301}");
+        input.backspace();
+        assert_eq!(input.get_string(), "a🦀");
+        input.horizontal_idx = 1;
+        input.delete();
+        assert_eq!(input.get_string(), "a");
+        input.undo();
+        assert_eq!(input.get_string(), "a🦀");
+    }
+
+    #[test]
+    fn vertical_moves_keep_the_screen_column() {
+        let mut input = input("日本\nabcd");
+        input.horizontal_idx = 2;
+        input.move_up();
+        assert_eq!((input.vertical_idx, input.horizontal_idx), (0, 3));
+        input.move_down();
+        assert_eq!((input.vertical_idx, input.horizontal_idx), (1, 2));
+        input.horizontal_idx = 1;
+        // column 1 is the right half of 日
+        input.move_up();
+        assert_eq!((input.vertical_idx, input.horizontal_idx), (0, 0));
+    }
+
+    #[test]
+    fn screen_lines_wrap_after_spaces_and_by_width() {
+        let mut input = input("ab 日本語");
+        let lines = input.screen_lines(4, true);
+        assert_eq!(ranges(&lines), vec![(0, 0..3), (0, 3..9), (0, 9..12)]);
+        assert_eq!(input.screen_cursor(&lines), (2, 2));
+        input.horizontal_idx = 9;
+        assert_eq!(input.screen_cursor(&lines), (2, 0));
+        input.horizontal_idx = 3;
+        assert_eq!(input.screen_cursor(&lines), (1, 0));
+    }
+
+    #[test]
+    fn full_row_is_followed_by_a_line_for_the_cursor() {
+        let input = input("日本\ne\u{301}");
+        let lines = input.screen_lines(4, true);
+        assert_eq!(ranges(&lines), vec![(0, 0..6), (0, 6..6), (1, 0..3)]);
+        assert_eq!(input.screen_cursor(&lines), (2, 1));
+        let lines = input.screen_lines(4, false);
+        assert_eq!(ranges(&lines), vec![(0, 0..6), (1, 0..3)]);
+    }
+
+    #[test]
+    fn finder_matches_paths_in_the_data_directory() {
+        let dir = std::env::temp_dir().join(format!("kb-finder-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("lang")).unwrap();
+        std::fs::write(dir.join("lang").join("rust.md"), "text").unwrap();
+        let config = crate::config::Config {
+            data_directories: vec![dir.to_string_lossy().into_owned()],
+            extension: "md".into(),
+            ..Default::default()
+        };
+        let mut app = App {
+            handler: Handler::new(&config),
+            ..App::default()
+        };
+        app.set_finder_view_ref();
+        let paths: Vec<&str> = app.finder_paths.iter().map(|p| p.1.as_str()).collect();
+        assert_eq!(paths, vec!["lang/rust.md"]);
+
+        // the directory the notes are in is not part of the path
+        app.finder_query.insert_text("kbfinder");
+        app.refresh_finder();
+        assert!(app.finder_matches.is_empty());
+        app.finder_query = Input::default();
+        app.finder_query.insert_text("lr");
+        app.refresh_finder();
+        let found = &app.finder_matches[0];
+        assert_eq!((found.in_title, &found.indices), (false, &vec![0, 5]));
+        assert_eq!(found.file, dir.join("lang").join("rust.md"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+use crate::key::Key;
+use crate::views::app::App;
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BacklinkAction {
+    Down,
+    Up,
+    Open,
+    Notes,
+    Close,
+}
+
+impl Action for BacklinkAction {
+    const VIEW: &'static str = "backlinks";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            BacklinkAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "select the next backlink",
+        ),
+        (
+            BacklinkAction::Up,
+            "up",
+            &["k", "<Up>"],
+            "select the previous backlink",
+        ),
+        (
+            BacklinkAction::Open,
+            "open",
+            &["<Enter>", "l"],
+            "open the note the backlink is in",
+        ),
+        (
+            BacklinkAction::Notes,
+            "notes",
+            &["<Tab>"],
+            "go back to the notes, keeping the backlinks shown",
+        ),
+        (
+            BacklinkAction::Close,
+            "close",
+            &["b", "<Esc>"],
+            "hide the backlinks",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        "backlinks"
+    }
+}
+
+/// Keys of the backlinks panel of the file view while it has the focus. Returns false for the
+/// keys left to the reader and the file view.
+pub fn handler(app: &mut App, keys: &[Key]) -> bool {
+    let len = app.backlinks.len();
+    match app.keymaps.backlinks.get(keys) {
+        Some(BacklinkAction::Down) if len > 0 => {
+            app.backlink = (app.backlink + app.count()) % len;
+        }
+        Some(BacklinkAction::Up) if len > 0 => {
+            app.backlink = (app.backlink + len - app.count() % len) % len;
+        }
+        Some(BacklinkAction::Open) => app.open_backlink(),
+        Some(BacklinkAction::Notes) => app.backlinks_focus = false,
+        Some(BacklinkAction::Close) => {
+            app.backlinks_shown = false;
+            app.backlinks_focus = false;
+        }
+        // nothing to select, but the keys are not the file view's either
+        Some(_) => {}
+        None => return false,
+    }
+    true
+}
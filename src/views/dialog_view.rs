@@ -1,23 +1,90 @@
 use crate::key::Key;
-use crate::views::app::App;
+use crate::views::app::{App, ViewState};
+use crate::views::keymap::{Action, ActionInfo};
 
-pub fn handler(app: &mut App, event: &Key) {
-    match event {
-        Key::Left | Key::Right | Key::Char('h') | Key::Char('l') => {
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DialogAction {
+    Toggle,
+    Confirm,
+    Cancel,
+}
+
+impl Action for DialogAction {
+    const VIEW: &'static str = "dialog";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            DialogAction::Toggle,
+            "toggle",
+            &["<Left>", "<Right>", "h", "l"],
+            "switch between Ok and Cancel",
+        ),
+        (
+            DialogAction::Confirm,
+            "confirm",
+            &["<Enter>"],
+            "press the selected button",
+        ),
+        (
+            DialogAction::Cancel,
+            "cancel",
+            &["<Esc>", "q"],
+            "close the dialog",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        "dialog"
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    let action = match app.keymaps.dialog.get(keys) {
+        Some(action) => action,
+        None => return,
+    };
+    if app.error.is_some() {
+        // the error dialog only has an Ok button
+        if let DialogAction::Confirm | DialogAction::Cancel = action {
+            app.error = None;
+            app.pop_state();
+        }
+        return;
+    }
+    match action {
+        DialogAction::Toggle => {
             app.confirm = !app.confirm;
         }
-        Key::Enter => {
+        DialogAction::Confirm => {
+            let mut result = Ok(());
             if app.confirm {
                 if let Some(action) = app.confirm_action {
-                    action(app);
+                    result = action(app);
                 }
             }
             app.pop_state();
+            app.confirm_details.clear();
             app.refresh_directory();
+            // a note may have been written or deleted, the notes and backlinks are read again
+            let _ = app.handler.refresh();
+            match app.get_latest_state() {
+                Some(ViewState::TagView) => {
+                    result = result.and(app.set_tag_view_ref().map(|_| ()));
+                }
+                Some(ViewState::TrashView) => {
+                    app.set_trash_view_ref();
+                }
+                Some(ViewState::SearchView) => {
+                    app.refresh_search();
+                }
+                _ => {}
+            }
+            if let Err(e) = result {
+                app.show_error(e);
+            }
         }
-        Key::Esc => {
+        DialogAction::Cancel => {
             app.pop_state();
+            app.confirm_details.clear();
         }
-        _ => {}
     }
 }
@@ -1,34 +1,189 @@
-use crate::data::Knowledge;
+use crate::error::Result;
 use crate::key::Key;
-use crate::views::app::{App, FileStatus, ViewState};
+use crate::views::app::{App, FileMode, TagFocus, ViewState};
+use crate::views::backlinks_view;
+use crate::views::keymap::{Action, ActionInfo};
+use crate::views::reader_view;
 
-pub fn handler(app: &mut App, event: &Key) {
-    match event {
-        Key::Down | Key::Char('j') => {
-            app.file_cycle_stack.last_mut().unwrap().next();
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileAction {
+    Down,
+    Up,
+    Open,
+    Back,
+    New,
+    Edit,
+    ExternalEdit,
+    Delete,
+    Rename,
+    Tags,
+    Search,
+    Finder,
+    Backlinks,
+    Trash,
+    Help,
+}
+
+impl Action for FileAction {
+    const VIEW: &'static str = "file";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            FileAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "select the next entry",
+        ),
+        (
+            FileAction::Up,
+            "up",
+            &["k", "<Up>"],
+            "select the previous entry",
+        ),
+        (
+            FileAction::Open,
+            "open",
+            &["<Enter>", "l"],
+            "open the selected directory or note",
+        ),
+        (
+            FileAction::Back,
+            "back",
+            &["h"],
+            "go back to the parent directory",
+        ),
+        (FileAction::New, "new", &["a"], "write a new note"),
+        (FileAction::Edit, "edit", &["e"], "edit the selected note"),
+        (
+            FileAction::ExternalEdit,
+            "edit-external",
+            &["E"],
+            "edit the selected note in $VISUAL or $EDITOR",
+        ),
+        (
+            FileAction::Delete,
+            "delete",
+            &["D"],
+            "move the selected note or directory to the trash",
+        ),
+        (
+            FileAction::Rename,
+            "rename",
+            &["r"],
+            "rename or move the selected note, rewriting the links to it",
+        ),
+        (FileAction::Tags, "tags", &["t"], "browse the notes by tag"),
+        (FileAction::Search, "search", &["/"], "search the notes"),
+        (
+            FileAction::Finder,
+            "finder",
+            &["<Ctrl+p>"],
+            "find a note by name",
+        ),
+        (
+            FileAction::Backlinks,
+            "backlinks",
+            &["b"],
+            "show the notes linking to the note, or go to them",
+        ),
+        (
+            FileAction::Trash,
+            "trash",
+            &["T"],
+            "browse the deleted notes to restore or purge them",
+        ),
+        (FileAction::Help, "help", &["?", "<F1>"], "list the keys"),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            FileAction::Down | FileAction::Up | FileAction::Open | FileAction::Back => "navigate",
+            FileAction::New
+            | FileAction::Edit
+            | FileAction::ExternalEdit
+            | FileAction::Delete
+            | FileAction::Rename => "notes",
+            FileAction::Tags
+            | FileAction::Search
+            | FileAction::Finder
+            | FileAction::Backlinks
+            | FileAction::Trash
+            | FileAction::Help => "views",
+        }
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    let searching = matches!(app.file_mode, FileMode::File) && app.reader_searching;
+    if app.backlinks_focus && !searching && backlinks_view::handler(app, keys) {
+        return;
+    }
+    if let FileMode::File = app.file_mode {
+        if reader_view::handler(app, keys) {
+            return;
         }
-        Key::Up | Key::Char('k') => {
-            app.file_cycle_stack.last_mut().unwrap().prev();
+    }
+    let action = match app.keymaps.file.get(keys) {
+        Some(action) => action,
+        None => return,
+    };
+    match action {
+        FileAction::Down => {
+            for _ in 0..app.count() {
+                app.file_cycle_stack.last_mut().unwrap().next();
+            }
+        }
+        FileAction::Up => {
+            for _ in 0..app.count() {
+                app.file_cycle_stack.last_mut().unwrap().prev();
+            }
         }
-        Key::Enter | Key::Char('l') => {
+        FileAction::Open => {
             app.enter_directory();
         }
-        Key::Char('h') => {
+        FileAction::Back => {
             app.leave_directory();
         }
-        Key::Char('a') => {
+        FileAction::New => {
             app.set_add_view_ref();
             app.push_state(ViewState::AddView);
         }
-        Key::Char('D') => {
-            fn action(app: &mut App) {
-                app.remove_directory();
+        FileAction::Tags => match app.set_tag_view_ref() {
+            Ok(_) => {
+                app.tag_focus = TagFocus::Tags;
+                app.push_state(ViewState::TagView);
+            }
+            Err(e) => app.show_error(e),
+        },
+        FileAction::Search => {
+            app.set_search_view_ref();
+            app.push_state(ViewState::SearchView);
+        }
+        FileAction::Finder => {
+            app.open_finder();
+        }
+        FileAction::Backlinks => {
+            app.backlinks_shown = true;
+            app.backlinks_focus = true;
+        }
+        FileAction::Trash => {
+            app.set_trash_view_ref();
+            app.push_state(ViewState::TrashView);
+        }
+        FileAction::Help => {
+            app.open_help();
+        }
+        FileAction::Delete => {
+            fn action(app: &mut App) -> Result<()> {
+                app.remove_directory()
             }
-            let entry = app.get_current_selected_entry();
+            let entry = match app.get_current_selected_entry() {
+                Some(entry) => entry,
+                None => return,
+            };
             app.push_state(ViewState::DialogView);
             app.confirm_action = Some(action);
             app.confirm_text = format!(
-                "confirm deleting: {}?",
+                "move to the trash: {}?",
                 entry
                     .file_name()
                     .and_then(|name| name.to_str())
@@ -38,52 +193,31 @@ pub fn handler(app: &mut App, event: &Key) {
             app.confirm = false;
             app.previous_view = ViewState::FileView;
         }
-        Key::Char('e') => {
-            app.push_state(ViewState::AddView);
-            let entry = app.get_current_selected_entry();
+        FileAction::Rename => {
+            app.open_rename();
+        }
+        FileAction::ExternalEdit => {
+            // the UI suspends itself and opens the note in $VISUAL or $EDITOR
+            match app.get_current_selected_entry() {
+                Some(entry) if entry.is_file() => app.external_edit = Some(entry),
+                _ => {}
+            }
+        }
+        FileAction::Edit => {
+            let entry = match app.get_current_selected_entry() {
+                Some(entry) => entry,
+                None => return,
+            };
             if entry.is_file() {
-                app.set_add_view_ref();
-                let knowledge = Knowledge::from_file(entry.clone());
-                app.input_title.insert_string(&knowledge.title);
-                app.input_text.insert_string(&knowledge.text);
-                app.file_status = FileStatus::Edit(entry.clone());
+                match app.set_edit_view_ref(entry) {
+                    Ok(_) => app.push_state(ViewState::AddView),
+                    Err(e) => app.show_error(e),
+                }
                 // put focus to text
             } else {
+                app.push_state(ViewState::AddView);
                 app.enter_directory();
             }
         }
-        _ => {}
-    }
-}
-
-pub struct BiCycle {
-    pub total_len: usize,
-    pub current_item: usize,
-}
-
-impl BiCycle {
-    pub fn new(total_len: usize) -> Self {
-        BiCycle {
-            total_len,
-            current_item: 0,
-        }
-    }
-
-    pub fn next(&mut self) -> Option<usize> {
-        self.current_item = if self.current_item >= self.total_len - 1 {
-            0
-        } else {
-            self.current_item + 1
-        };
-        Some(self.current_item)
-    }
-
-    pub fn prev(&mut self) -> Option<usize> {
-        self.current_item = if self.current_item == 0 {
-            self.total_len - 1
-        } else {
-            self.current_item - 1
-        };
-        Some(self.current_item)
     }
 }
@@ -0,0 +1,90 @@
+use crate::key::{CtrlKey, Key};
+use crate::views::app::App;
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FinderAction {
+    Down,
+    Up,
+    Open,
+    Help,
+    Back,
+}
+
+impl Action for FinderAction {
+    const VIEW: &'static str = "finder";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            FinderAction::Down,
+            "down",
+            &["<Down>", "<Tab>", "<Ctrl+n>", "<Ctrl+j>"],
+            "select the next note",
+        ),
+        (
+            FinderAction::Up,
+            "up",
+            &["<Up>", "<BackTab>", "<Ctrl+p>", "<Ctrl+k>"],
+            "select the previous note",
+        ),
+        (
+            FinderAction::Open,
+            "open",
+            &["<Enter>"],
+            "show the selected note in the file view",
+        ),
+        (FinderAction::Help, "help", &["<F1>"], "list the keys"),
+        (FinderAction::Back, "back", &["<Esc>"], "close the finder"),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            FinderAction::Help => "views",
+            _ => "navigate",
+        }
+    }
+}
+
+/// Characters are typed into the query, other keys edit it or are looked up in the keymap.
+pub fn handler(app: &mut App, keys: &[Key]) {
+    match keys {
+        [Key::Char(c)] => {
+            app.finder_query.insert(*c);
+            app.refresh_finder();
+        }
+        [Key::Backspace] => {
+            app.finder_query.backspace();
+            app.refresh_finder();
+        }
+        [Key::Ctrl(CtrlKey::Char('w'))] => {
+            app.finder_query.backspace_word();
+            app.refresh_finder();
+        }
+        [Key::Left] => {
+            app.finder_query.move_left();
+        }
+        [Key::Right] => {
+            app.finder_query.move_right();
+        }
+        _ => match app.keymaps.finder.get(keys) {
+            Some(FinderAction::Down) if !app.finder_matches.is_empty() => {
+                app.finder_cycle.next();
+            }
+            Some(FinderAction::Up) if !app.finder_matches.is_empty() => {
+                app.finder_cycle.prev();
+            }
+            Some(FinderAction::Open) => {
+                if let Some(path) = app.get_selected_finder_match().map(|e| e.file.clone()) {
+                    app.pop_to_root();
+                    app.jump_to(path);
+                }
+            }
+            Some(FinderAction::Help) => {
+                app.open_help();
+            }
+            Some(FinderAction::Back) => {
+                app.pop_state();
+            }
+            _ => {}
+        },
+    }
+}
@@ -0,0 +1,43 @@
+use crate::key::{CtrlKey, Key};
+use crate::views::app::App;
+
+/// Characters are typed into the filter, the other keys scroll or close the help.
+pub fn handler(app: &mut App, keys: &[Key]) {
+    let page = app.help_height.max(1);
+    match keys {
+        [Key::Char(c)] => {
+            app.help_query.insert(*c);
+            app.help_scroll = 0;
+        }
+        [Key::Backspace] => {
+            app.help_query.backspace();
+            app.help_scroll = 0;
+        }
+        [Key::Ctrl(CtrlKey::Char('w'))] => {
+            app.help_query.backspace_word();
+            app.help_scroll = 0;
+        }
+        [Key::Left] => {
+            app.help_query.move_left();
+        }
+        [Key::Right] => {
+            app.help_query.move_right();
+        }
+        [Key::Down] | [Key::Ctrl(CtrlKey::Char('n'))] => {
+            app.help_scroll += 1;
+        }
+        [Key::Up] | [Key::Ctrl(CtrlKey::Char('p'))] => {
+            app.help_scroll = app.help_scroll.saturating_sub(1);
+        }
+        [Key::PageDown] => {
+            app.help_scroll += page;
+        }
+        [Key::PageUp] => {
+            app.help_scroll = app.help_scroll.saturating_sub(page);
+        }
+        [Key::Esc] | [Key::Enter] => {
+            app.pop_state();
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,491 @@
+use crate::add_view::EditorAction;
+use crate::backlinks_view::BacklinkAction;
+use crate::config;
+use crate::dialog_view::DialogAction;
+use crate::error::{Error, Result};
+use crate::file_view::FileAction;
+use crate::finder_view::FinderAction;
+use crate::key::{display_keys, parse_keys, Key};
+use crate::reader_view::ReaderAction;
+use crate::search_view::SearchAction;
+use crate::tag_view::TagAction;
+use crate::trash_view::TrashAction;
+use std::collections::BTreeMap;
+
+/// An action with its name in the config, default keys and what it does.
+pub type ActionInfo<A> = (A, &'static str, &'static [&'static str], &'static str);
+
+/// Something keys are bound to in one view.
+pub trait Action: Copy + PartialEq + 'static {
+    /// name of the view in the `[keys]` section of the config
+    const VIEW: &'static str;
+    /// every action of the view
+    const ACTIONS: &'static [ActionInfo<Self>];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ACTIONS
+            .iter()
+            .find(|(_, n, _, _)| *n == name)
+            .map(|&(action, _, _, _)| action)
+    }
+
+    fn name(self) -> &'static str {
+        Self::ACTIONS
+            .iter()
+            .find(|(a, _, _, _)| *a == self)
+            .map_or("", |&(_, name, _, _)| name)
+    }
+
+    fn description(self) -> &'static str {
+        Self::ACTIONS
+            .iter()
+            .find(|(a, _, _, _)| *a == self)
+            .map_or("", |&(_, _, _, description)| description)
+    }
+
+    /// heading of the action in the help
+    fn category(self) -> &'static str;
+}
+
+/// Actions available in every view.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GlobalAction {
+    Quit,
+}
+
+impl Action for GlobalAction {
+    const VIEW: &'static str = "global";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[(
+        GlobalAction::Quit,
+        "quit",
+        &["<Ctrl+q>"],
+        "quit knowledge-base",
+    )];
+
+    fn category(self) -> &'static str {
+        "global"
+    }
+}
+
+/// A line of the help: the keys bound to an action.
+pub struct HelpEntry {
+    pub category: &'static str,
+    pub keys: String,
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Key sequences bound to the actions of one view.
+pub struct Keymap<A> {
+    bindings: Vec<(Vec<Key>, A)>,
+}
+
+impl<A: Action> Default for Keymap<A> {
+    fn default() -> Self {
+        let mut bindings = vec![];
+        for &(action, _, keys, _) in A::ACTIONS {
+            for keys in keys.iter() {
+                let keys = parse_keys(keys, None).expect("invalid default key");
+                bindings.push((keys, action));
+            }
+        }
+        Keymap { bindings }
+    }
+}
+
+impl<A: Action> Keymap<A> {
+    pub fn get(&self, keys: &[Key]) -> Option<A> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| k == keys)
+            .map(|&(_, action)| action)
+    }
+
+    /// Keys bound to `action`, the default ones first.
+    pub fn keys(&self, action: A) -> Vec<Vec<Key>> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(keys, _)| keys.clone())
+            .collect()
+    }
+
+    /// Help entries of the bound actions, without the keys `shadowed` by another view.
+    pub fn help(&self, shadowed: impl Fn(&[Key]) -> bool) -> Vec<HelpEntry> {
+        A::ACTIONS
+            .iter()
+            .filter_map(|&(action, name, _, description)| {
+                let keys: Vec<_> = self
+                    .keys(action)
+                    .iter()
+                    .filter(|keys| !shadowed(keys))
+                    .map(|keys| display_keys(keys))
+                    .collect();
+                if keys.is_empty() {
+                    return None;
+                }
+                Some(HelpEntry {
+                    category: action.category(),
+                    keys: keys.join(" "),
+                    name,
+                    description,
+                })
+            })
+            .collect()
+    }
+
+    /// Bind `keys` to `action` instead of what they were bound to, or unbind them.
+    fn bind(&mut self, keys: Vec<Key>, action: Option<A>) {
+        self.bindings.retain(|(k, _)| *k != keys);
+        if let Some(action) = action {
+            self.bindings.push((keys, action));
+        }
+    }
+}
+
+/// How keys typed so far match the bindings of a view.
+#[derive(Default)]
+pub struct Lookup {
+    /// the keys are bound to an action
+    pub bound: bool,
+    /// longer sequences start with the keys
+    pub prefix: bool,
+}
+
+/// The keymap of a view whatever its actions, for matching key sequences.
+pub trait Bindings {
+    fn lookup(&self, keys: &[Key]) -> Lookup;
+
+    /// The keys that can follow `keys`, with the description of their action.
+    fn continuations(&self, keys: &[Key]) -> Vec<(String, &'static str)>;
+}
+
+impl<A: Action> Bindings for Keymap<A> {
+    fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::default();
+        for (k, _) in &self.bindings {
+            lookup.bound |= k == keys;
+            lookup.prefix |= k.len() > keys.len() && k.starts_with(keys);
+        }
+        lookup
+    }
+
+    fn continuations(&self, keys: &[Key]) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .filter(|(k, _)| k.len() > keys.len() && k.starts_with(keys))
+            .map(|(k, action)| (display_keys(&k[keys.len()..]), action.description()))
+            .collect()
+    }
+}
+
+/// A binding of the config.
+struct Entry {
+    /// the view of the table it is in, None if directly in `[keys]`
+    view: Option<String>,
+    keys: Vec<Key>,
+    action: String,
+}
+
+impl Entry {
+    fn section(&self) -> String {
+        match &self.view {
+            Some(view) => format!("[keys.{}]", view),
+            None => String::from("[keys]"),
+        }
+    }
+}
+
+/// Action name that unbinds a key.
+const UNBOUND: &str = "none";
+
+/// The keymap of every view.
+#[derive(Default)]
+pub struct Keymaps {
+    pub global: Keymap<GlobalAction>,
+    pub file: Keymap<FileAction>,
+    pub reader: Keymap<ReaderAction>,
+    pub backlinks: Keymap<BacklinkAction>,
+    pub tag: Keymap<TagAction>,
+    pub trash: Keymap<TrashAction>,
+    pub search: Keymap<SearchAction>,
+    pub finder: Keymap<FinderAction>,
+    pub editor: Keymap<EditorAction>,
+    pub dialog: Keymap<DialogAction>,
+}
+
+impl Keymaps {
+    /// The default keymaps with the bindings of the `[keys]` section of the config. A key
+    /// directly in `[keys]` is bound in every view having the action, one in a table like
+    /// `[keys.editor]` only in that view, over the keys of every view and the global ones.
+    /// `<leader>` in the keys stands for `leader`. Every unknown key, view or action and every
+    /// key bound to two actions of a view in the same table is reported in the error.
+    pub fn new(keys: &BTreeMap<String, config::Binding>, leader: &str) -> Result<Self> {
+        let mut problems = vec![];
+        let leader = match leader.parse() {
+            Ok(leader) => Some(leader),
+            Err(e) => {
+                problems.push(format!("leader: {}", e));
+                None
+            }
+        };
+        let mut entries = vec![];
+        let mut push = |view: Option<&str>, keys: &str, action: &str| match parse_keys(keys, leader)
+        {
+            Ok(keys) => entries.push(Entry {
+                view: view.map(String::from),
+                keys,
+                action: action.to_owned(),
+            }),
+            Err(e) => problems.push(format!(
+                "{}: {}",
+                view.map_or(String::from("[keys]"), |v| format!("[keys.{}]", v)),
+                e
+            )),
+        };
+        for (name, binding) in keys {
+            match binding {
+                config::Binding::Action(action) => push(None, name, action),
+                config::Binding::View(table) => {
+                    for (key, action) in table {
+                        push(Some(name), key, action);
+                    }
+                }
+            }
+        }
+
+        let views: &[View] = &[
+            (GlobalAction::VIEW, has::<GlobalAction>),
+            (FileAction::VIEW, has::<FileAction>),
+            (ReaderAction::VIEW, has::<ReaderAction>),
+            (BacklinkAction::VIEW, has::<BacklinkAction>),
+            (TagAction::VIEW, has::<TagAction>),
+            (TrashAction::VIEW, has::<TrashAction>),
+            (SearchAction::VIEW, has::<SearchAction>),
+            (FinderAction::VIEW, has::<FinderAction>),
+            (EditorAction::VIEW, has::<EditorAction>),
+            (DialogAction::VIEW, has::<DialogAction>),
+        ];
+        for entry in &entries {
+            let known = match &entry.view {
+                Some(view) => match views.iter().find(|(v, _)| v == view) {
+                    Some((_, has)) => has(&entry.action),
+                    None => {
+                        problems.push(format!("{}: unknown view {:?}", entry.section(), view));
+                        continue;
+                    }
+                },
+                None => views.iter().any(|(_, has)| has(&entry.action)),
+            };
+            if !known && entry.action != UNBOUND {
+                problems.push(format!(
+                    "{}: unknown action {:?} for {}",
+                    entry.section(),
+                    entry.action,
+                    display_keys(&entry.keys)
+                ));
+            }
+        }
+
+        let mut keymaps = Keymaps::default();
+        configure(&mut keymaps.global, &entries, None, &mut problems);
+        let global = Some(&keymaps.global);
+        configure(&mut keymaps.file, &entries, global, &mut problems);
+        configure(&mut keymaps.reader, &entries, global, &mut problems);
+        configure(&mut keymaps.backlinks, &entries, global, &mut problems);
+        configure(&mut keymaps.tag, &entries, global, &mut problems);
+        configure(&mut keymaps.trash, &entries, global, &mut problems);
+        configure(&mut keymaps.search, &entries, global, &mut problems);
+        configure(&mut keymaps.finder, &entries, global, &mut problems);
+        configure(&mut keymaps.editor, &entries, global, &mut problems);
+        configure(&mut keymaps.dialog, &entries, global, &mut problems);
+        if !problems.is_empty() {
+            // a binding directly in [keys] is checked once for every view
+            let mut reported: Vec<String> = vec![];
+            for problem in problems {
+                if !reported.contains(&problem) {
+                    reported.push(problem);
+                }
+            }
+            return Err(Error::Config(reported.join("\n")));
+        }
+        Ok(keymaps)
+    }
+}
+
+/// Whether `bindings`, the keymaps of a view, bind `keys` or a sequence starting with them.
+/// Such keys are not global in the view.
+pub fn binds(bindings: &[&dyn Bindings], keys: &[Key]) -> bool {
+    bindings.iter().any(|bindings| {
+        let lookup = bindings.lookup(keys);
+        lookup.bound || lookup.prefix
+    })
+}
+
+/// A view with a check whether it has an action.
+type View = (&'static str, fn(&str) -> bool);
+
+fn has<A: Action>(name: &str) -> bool {
+    A::from_name(name).is_some()
+}
+
+/// Apply the `entries` of the config for the view of `keymap`, the ones of its table after
+/// those of every view so they take their keys. Reports keys bound twice in the same table,
+/// and keys of every view starting with a key of the `global` bindings, which only a view
+/// table may take over. The global bindings themselves are single keys.
+fn configure<A: Action>(
+    keymap: &mut Keymap<A>,
+    entries: &[Entry],
+    global: Option<&Keymap<GlobalAction>>,
+    problems: &mut Vec<String>,
+) {
+    let (shared, own): (Vec<&Entry>, Vec<&Entry>) =
+        entries.iter().partition(|entry| entry.view.is_none());
+    let mut bound: Vec<(&Option<String>, &[Key], &str)> = vec![];
+    for entry in shared.into_iter().chain(own) {
+        let action = A::from_name(&entry.action);
+        let applies = match &entry.view {
+            Some(view) => view == A::VIEW,
+            None => action.is_some() || entry.action == UNBOUND,
+        };
+        if !applies || (entry.action != UNBOUND && action.is_none()) {
+            continue;
+        }
+        let keys = display_keys(&entry.keys);
+        if let Some((_, _, other)) = bound
+            .iter()
+            .find(|(view, k, _)| **view == entry.view && *k == entry.keys.as_slice())
+        {
+            if *other != entry.action {
+                problems.push(format!(
+                    "{}: {} is bound to both {} and {} in the {} view",
+                    entry.section(),
+                    keys,
+                    other,
+                    entry.action,
+                    A::VIEW
+                ));
+            }
+            continue;
+        }
+        bound.push((&entry.view, &entry.keys, &entry.action));
+        if action.is_some() {
+            if global.is_none() && entry.keys.len() > 1 {
+                problems.push(format!(
+                    "{}: {} is a sequence, global keys are single keys",
+                    entry.section(),
+                    keys
+                ));
+                continue;
+            }
+            let global = global.filter(|_| entry.view.is_none());
+            if let Some(global) = global.and_then(|global| global.get(&entry.keys[..1])) {
+                problems.push(format!(
+                    "{}: {} is bound to {} in every view",
+                    entry.section(),
+                    display_keys(&entry.keys[..1]),
+                    global.name()
+                ));
+                continue;
+            }
+        }
+        keymap.bind(entry.keys.clone(), action);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(s: &str) -> Vec<Key> {
+        parse_keys(s, None).unwrap()
+    }
+
+    fn shared(bindings: &[(&str, &str)]) -> BTreeMap<String, config::Binding> {
+        bindings
+            .iter()
+            .map(|(keys, action)| {
+                (
+                    keys.to_string(),
+                    config::Binding::Action(action.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    fn table(view: &str, bindings: &[(&str, &str)]) -> (String, config::Binding) {
+        let table = bindings
+            .iter()
+            .map(|(keys, action)| (keys.to_string(), action.to_string()))
+            .collect();
+        (view.to_owned(), config::Binding::View(table))
+    }
+
+    fn problems(keys: &BTreeMap<String, config::Binding>, leader: &str) -> String {
+        match Keymaps::new(keys, leader) {
+            Err(Error::Config(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("the keys were accepted"),
+        }
+    }
+
+    #[test]
+    fn every_problem_of_the_config_is_reported() {
+        let mut config = shared(&[("x", "fly"), ("<Nope>", "down")]);
+        config.extend(vec![table("nowhere", &[("y", "down")])]);
+        let problems = problems(&config, "<Bad>");
+        assert!(problems.contains("leader"));
+        assert!(problems.contains("unknown action \"fly\""));
+        assert!(problems.contains("<Nope>"));
+        assert!(problems.contains("unknown view \"nowhere\""));
+    }
+
+    #[test]
+    fn view_tables_override_keys_of_every_view() {
+        let mut config = shared(&[("x", "delete")]);
+        config.extend(vec![table("file", &[("x", "rename")])]);
+        let keymaps = Keymaps::new(&config, "<Space>").unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(keymaps.file.get(&keys("x")), Some(FileAction::Rename));
+        assert_eq!(keymaps.tag.get(&keys("x")), Some(TagAction::Delete));
+    }
+
+    #[test]
+    fn view_tables_override_global_keys() {
+        let config: BTreeMap<_, _> = vec![table("editor", &[("<Ctrl+q>", "save")])]
+            .into_iter()
+            .collect();
+        let keymaps = Keymaps::new(&config, "<Space>").unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(
+            keymaps.editor.get(&keys("<Ctrl+q>")),
+            Some(EditorAction::Save)
+        );
+        assert_eq!(
+            keymaps.global.get(&keys("<Ctrl+q>")),
+            Some(GlobalAction::Quit)
+        );
+        let editor: &[&dyn Bindings] = &[&keymaps.editor];
+        assert!(binds(editor, &keys("<Ctrl+q>")));
+        assert!(!binds(&[&keymaps.file], &keys("<Ctrl+q>")));
+
+        let problems = problems(&shared(&[("<Ctrl+q>", "save")]), "<Space>");
+        assert!(problems.contains("is bound to quit in every view"));
+    }
+
+    #[test]
+    fn conflicts_are_reported_within_a_table() {
+        let config: BTreeMap<_, _> = vec![table(
+            "file",
+            &[("<leader>", "edit"), ("<Space>", "delete")],
+        )]
+        .into_iter()
+        .collect();
+        let problems = problems(&config, "<Space>");
+        assert!(problems.contains("[keys.file]: <Space> is bound to both"));
+
+        let mut config = shared(&[("<Space>", "delete")]);
+        config.extend(vec![table("file", &[("<leader>", "edit")])]);
+        let keymaps = Keymaps::new(&config, "<Space>").unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(keymaps.file.get(&keys("<Space>")), Some(FileAction::Edit));
+        assert_eq!(keymaps.tag.get(&keys("<Space>")), Some(TagAction::Delete));
+    }
+}
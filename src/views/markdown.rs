@@ -0,0 +1,875 @@
+use crate::data::WikiLink;
+use crate::views::app::{text_width, ReaderLink};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans, Text};
+
+/// Render the markdown `text` of a note for an area `width` columns wide. Also returns where
+/// the labels of its `[[wiki links]]` are in the rendered text.
+pub fn render(text: &str, width: u16) -> (Text<'static>, Vec<ReaderLink>) {
+    let mut rendered = Rendered::default();
+    let mut fence: Option<Fence> = None;
+    let mut table: Vec<&str> = vec![];
+    for line in text.lines() {
+        if let Some(code) = fence.as_mut() {
+            if code.closes(line) {
+                rendered.push(Span::styled(line.to_owned(), fence_style()).into());
+                fence = None;
+            } else {
+                rendered.push(code.highlight(line).0.into());
+            }
+            continue;
+        }
+        if line.trim_start().starts_with('|') {
+            table.push(line);
+            continue;
+        }
+        if !table.is_empty() {
+            for line in render_table(&table) {
+                rendered.push(line);
+            }
+            table.clear();
+        }
+        if let Some(code) = Fence::open(line) {
+            rendered.push(Span::styled(line.to_owned(), fence_style()).into());
+            fence = Some(code);
+            continue;
+        }
+        rendered.push(render_line(line, width));
+    }
+    for line in render_table(&table) {
+        rendered.push(line);
+    }
+    (Text::from(rendered.lines), rendered.links)
+}
+
+/// The spans of a rendered line, with the labels of `[[wiki links]]` among them.
+#[derive(Default)]
+struct Line {
+    spans: Vec<Span<'static>>,
+    /// index in `spans` of the label of each link, and where it leads
+    links: Vec<(usize, WikiLink)>,
+}
+
+impl Line {
+    fn push(&mut self, span: Span<'static>) {
+        self.spans.push(span);
+    }
+
+    /// Add the spans and links of `line` after those of this line.
+    fn append(&mut self, line: Line) {
+        let offset = self.spans.len();
+        self.links
+            .extend(line.links.into_iter().map(|(i, link)| (offset + i, link)));
+        self.spans.extend(line.spans);
+    }
+}
+
+impl From<Vec<Span<'static>>> for Line {
+    fn from(spans: Vec<Span<'static>>) -> Self {
+        Line {
+            spans,
+            links: vec![],
+        }
+    }
+}
+
+impl From<Span<'static>> for Line {
+    fn from(span: Span<'static>) -> Self {
+        Line::from(vec![span])
+    }
+}
+
+/// The lines rendered so far and the `[[wiki links]]` in them.
+#[derive(Default)]
+struct Rendered {
+    lines: Vec<Spans<'static>>,
+    links: Vec<ReaderLink>,
+}
+
+impl Rendered {
+    /// Add `line`, turning the indices of its links into byte ranges in it.
+    fn push(&mut self, line: Line) {
+        let row = self.lines.len();
+        for (i, link) in line.links {
+            let start: usize = line.spans[..i].iter().map(|s| s.content.len()).sum();
+            let end = start + line.spans[i].content.len();
+            self.links.push((row, start..end, link));
+        }
+        self.lines.push(Spans::from(line.spans));
+    }
+}
+
+fn fence_style() -> Style {
+    Style::default().fg(Color::DarkGray)
+}
+
+/// Render a line outside of code blocks and tables.
+fn render_line(line: &str, width: u16) -> Line {
+    let indent = line.len() - line.trim_start().len();
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return Line::default();
+    }
+    if indent < 4 {
+        if let Some(line) = heading(trimmed) {
+            return line;
+        }
+        if is_rule(trimmed) {
+            return Span::styled(
+                "─".repeat(width as usize),
+                Style::default().fg(Color::DarkGray),
+            )
+            .into();
+        }
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            let quote = quote.strip_prefix(' ').unwrap_or(quote);
+            let mut line = Line::from(Span::styled("│ ", Style::default().fg(Color::DarkGray)));
+            line.append(render_line(quote, width.saturating_sub(2)));
+            return line;
+        }
+    }
+    if let Some((marker, rest)) = list_item(trimmed) {
+        let mut spans = Line::from(vec![
+            Span::raw(line[..indent].to_owned()),
+            Span::styled(marker, Style::default().fg(Color::Yellow)),
+        ]);
+        let rest = if let Some(rest) = rest.strip_prefix("[ ] ") {
+            spans.push(Span::styled("[ ] ", Style::default().fg(Color::DarkGray)));
+            rest
+        } else if let Some(rest) = rest
+            .strip_prefix("[x] ")
+            .or_else(|| rest.strip_prefix("[X] "))
+        {
+            spans.push(Span::styled("[x] ", Style::default().fg(Color::Green)));
+            rest
+        } else {
+            rest
+        };
+        spans.append(inline(rest, Style::default()));
+        return spans;
+    }
+    inline(line, Style::default())
+}
+
+/// An ATX heading, `#` to `######` followed by a space.
+fn heading(line: &str) -> Option<Line> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let rest = &line[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    let color = match level {
+        1 => Color::Magenta,
+        2 => Color::Cyan,
+        3 => Color::Green,
+        4 => Color::Yellow,
+        _ => Color::Blue,
+    };
+    let mut style = Style::default().fg(color).add_modifier(Modifier::BOLD);
+    if level == 1 {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    // closing hashes are optional and not part of the heading
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    let mut spans = Line::from(Span::styled(
+        "#".repeat(level) + " ",
+        Style::default().fg(Color::DarkGray),
+    ));
+    spans.append(inline(text, style));
+    Some(spans)
+}
+
+/// A thematic break: three or more `-`, `*` or `_`, optionally with spaces between them.
+fn is_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && matches!(marks[0], '-' | '*' | '_') && marks.iter().all(|&c| c == marks[0])
+}
+
+/// Split a list item into the marker to draw in front of it and the item text.
+fn list_item(line: &str) -> Option<(String, &str)> {
+    for bullet in &["- ", "* ", "+ "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            return Some((String::from("• "), rest));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 || digits > 9 {
+        return None;
+    }
+    let rest = &line[digits..];
+    if rest.starts_with(". ") || rest.starts_with(") ") {
+        Some((line[..digits + 2].to_owned(), &rest[2..]))
+    } else {
+        None
+    }
+}
+
+/// Render the inline markup of `text`: emphasis, code spans and links.
+fn inline(text: &str, base: Style) -> Line {
+    let mut spans = Line::default();
+    let mut buffer = String::new();
+    let (mut bold, mut italic, mut strike) = (false, false, false);
+    let style = |bold: bool, italic: bool, strike: bool| {
+        let mut style = base;
+        if bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if italic {
+            style = style.add_modifier(Modifier::ITALIC);
+        }
+        if strike {
+            style = style.add_modifier(Modifier::CROSSED_OUT);
+        }
+        style
+    };
+    macro_rules! flush {
+        () => {
+            if !buffer.is_empty() {
+                spans.push(Span::styled(
+                    std::mem::take(&mut buffer),
+                    style(bold, italic, strike),
+                ));
+            }
+        };
+    }
+    let link_style = base.fg(Color::Blue).add_modifier(Modifier::UNDERLINED);
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+        let previous = text[..i].chars().next_back();
+        match c {
+            '\\' => {
+                if let Some(next) = rest[1..]
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_punctuation())
+                {
+                    buffer.push(next);
+                    i += 2;
+                    continue;
+                }
+            }
+            '`' => {
+                let ticks = rest.chars().take_while(|&c| c == '`').count();
+                let fence = &rest[..ticks];
+                if let Some(end) = rest[ticks..].find(fence) {
+                    flush!();
+                    let code = &rest[ticks..ticks + end];
+                    let code = if code.len() > 2 && code.starts_with(' ') && code.ends_with(' ') {
+                        &code[1..code.len() - 1]
+                    } else {
+                        code
+                    };
+                    spans.push(Span::styled(
+                        code.to_owned(),
+                        base.fg(Color::Yellow).bg(Color::Black),
+                    ));
+                    i += ticks + end + ticks;
+                    continue;
+                }
+                buffer.push_str(fence);
+                i += ticks;
+                continue;
+            }
+            '[' => {
+                if let Some(inner) = rest.strip_prefix("[[") {
+                    if let Some(end) = inner.find("]]").filter(|&end| !inner[..end].is_empty()) {
+                        flush!();
+                        let link = WikiLink::parse(&inner[..end]);
+                        let label = Span::styled(link.label.clone(), wiki_link_style(base));
+                        spans.links.push((spans.spans.len(), link));
+                        spans.push(label);
+                        i += 2 + end + 2;
+                        continue;
+                    }
+                } else if let Some((label, length)) = link(rest) {
+                    flush!();
+                    spans.append(inline(label, link_style));
+                    i += length;
+                    continue;
+                }
+            }
+            '<' => {
+                if let Some(end) = rest.find('>') {
+                    let target = &rest[1..end];
+                    let is_link = ["http://", "https://", "mailto:"]
+                        .iter()
+                        .any(|scheme| target.starts_with(scheme));
+                    if is_link && !target.contains(char::is_whitespace) {
+                        flush!();
+                        spans.push(Span::styled(target.to_owned(), link_style));
+                        i += end + 1;
+                        continue;
+                    }
+                }
+            }
+            '*' | '_' | '~' => {
+                let run = rest.chars().take_while(|&d| d == c).count();
+                let next = rest[run..].chars().next();
+                let mut can_open = matches!(next, Some(n) if !n.is_whitespace());
+                let mut can_close = matches!(previous, Some(p) if !p.is_whitespace());
+                if c == '_' {
+                    // snake_case words are not emphasis
+                    can_open &= !matches!(previous, Some(p) if p.is_alphanumeric());
+                    can_close &= !matches!(next, Some(n) if n.is_alphanumeric());
+                }
+                let toggle = |on: bool| (on && can_close) || (!on && can_open);
+                let used = match (c, run) {
+                    ('~', 2) if toggle(strike) => {
+                        flush!();
+                        strike = !strike;
+                        true
+                    }
+                    ('~', _) => false,
+                    (_, 1) if toggle(italic) => {
+                        flush!();
+                        italic = !italic;
+                        true
+                    }
+                    (_, 2) if toggle(bold) => {
+                        flush!();
+                        bold = !bold;
+                        true
+                    }
+                    (_, 3) if toggle(bold) && toggle(italic) => {
+                        flush!();
+                        bold = !bold;
+                        italic = !italic;
+                        true
+                    }
+                    _ => false,
+                };
+                if !used {
+                    buffer.push_str(&rest[..run]);
+                }
+                i += run;
+                continue;
+            }
+            _ => {}
+        }
+        buffer.push(c);
+        i += c.len_utf8();
+    }
+    flush!();
+    spans
+}
+
+/// Style of the label of a `[[wiki link]]`, on top of the style `base` of the text around it.
+pub fn wiki_link_style(base: Style) -> Style {
+    base.fg(Color::Cyan).add_modifier(Modifier::UNDERLINED)
+}
+
+/// A `[label](target)` link at the start of `text`, with the length of its markup.
+fn link(text: &str) -> Option<(&str, usize)> {
+    let mut depth = 0;
+    let close = text.char_indices().find_map(|(i, c)| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            Some(i)
+        } else {
+            None
+        }
+    })?;
+    let target = text[close + 1..].strip_prefix('(')?;
+    let end = target.find(')')?;
+    Some((&text[1..close], close + 1 + 1 + end + 1))
+}
+
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+fn cells(row: &str) -> Vec<&str> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = row.strip_suffix('|').unwrap_or(row);
+    row.split('|').map(str::trim).collect()
+}
+
+/// Render the rows of a table with aligned columns. Rows that are not a table, for lack of
+/// a delimiter row below the header, are rendered as text.
+fn render_table(rows: &[&str]) -> Vec<Line> {
+    let is_delimiter = |row: &str| {
+        cells(row).iter().all(|cell| {
+            let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+    };
+    if rows.len() < 2 || !is_delimiter(rows[1]) {
+        return rows
+            .iter()
+            .map(|row| inline(row, Style::default()))
+            .collect();
+    }
+    let aligns: Vec<Align> = cells(rows[1])
+        .iter()
+        .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => Align::Center,
+            (false, true) => Align::Right,
+            _ => Align::Left,
+        })
+        .collect();
+    let header = Style::default().add_modifier(Modifier::BOLD);
+    let table: Vec<Vec<Line>> = rows
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != 1)
+        .map(|(i, row)| {
+            let base = if i == 0 { header } else { Style::default() };
+            cells(row).iter().map(|cell| inline(cell, base)).collect()
+        })
+        .collect();
+    let span_width = |spans: &[Span]| spans.iter().map(|s| text_width(&s.content)).sum::<usize>();
+    let mut widths = vec![0; aligns.len()];
+    for row in &table {
+        for (column, cell) in row.iter().enumerate().take(widths.len()) {
+            widths[column] = widths[column].max(span_width(&cell.spans));
+        }
+    }
+    let border = Style::default().fg(Color::DarkGray);
+    let mut lines = vec![];
+    for (i, row) in table.into_iter().enumerate() {
+        let mut spans = Line::default();
+        let mut row = row.into_iter();
+        for (column, (&width, &align)) in widths.iter().zip(&aligns).enumerate() {
+            if column > 0 {
+                spans.push(Span::styled(" │ ", border));
+            }
+            let cell = row.next().unwrap_or_default();
+            let padding = width - span_width(&cell.spans);
+            let left = match align {
+                Align::Left => 0,
+                Align::Center => padding / 2,
+                Align::Right => padding,
+            };
+            spans.push(Span::raw(" ".repeat(left)));
+            spans.append(cell);
+            spans.push(Span::raw(" ".repeat(padding - left)));
+        }
+        lines.push(spans);
+        if i == 0 {
+            let rule: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+            lines.push(Span::styled(rule.join("─┼─"), border).into());
+        }
+    }
+    lines
+}
+
+/// Keywords and comment markers of a language, for highlighting code blocks.
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const C_COMMENT: Option<(&str, &str)> = Some(("/*", "*/"));
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+        line_comments: &["//"],
+        block_comment: C_COMMENT,
+        quotes: &['"'],
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "false",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "null",
+            "of",
+            "return",
+            "static",
+            "switch",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "type",
+            "typeof",
+            "undefined",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        line_comments: &["//"],
+        block_comment: C_COMMENT,
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "false",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "nil",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "true",
+            "type",
+            "var",
+        ],
+        line_comments: &["//"],
+        block_comment: C_COMMENT,
+        quotes: &['"', '\'', '`'],
+    },
+    Language {
+        names: &["c", "h", "cpp", "c++", "cc", "hpp", "java", "cs", "csharp"],
+        keywords: &[
+            "auto",
+            "bool",
+            "break",
+            "case",
+            "catch",
+            "char",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "double",
+            "else",
+            "enum",
+            "extends",
+            "false",
+            "final",
+            "float",
+            "for",
+            "if",
+            "implements",
+            "import",
+            "int",
+            "long",
+            "namespace",
+            "new",
+            "null",
+            "nullptr",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "short",
+            "signed",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "template",
+            "this",
+            "throw",
+            "true",
+            "try",
+            "typedef",
+            "union",
+            "unsigned",
+            "using",
+            "virtual",
+            "void",
+            "volatile",
+            "while",
+        ],
+        line_comments: &["//"],
+        block_comment: C_COMMENT,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["sh", "bash", "shell", "zsh", "console"],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["toml", "yaml", "yml", "json", "ini"],
+        keywords: &["true", "false", "null", "yes", "no"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+    },
+    Language {
+        names: &["sql"],
+        keywords: &[
+            "and", "as", "by", "create", "delete", "from", "group", "insert", "into", "join",
+            "left", "not", "null", "on", "or", "order", "select", "set", "table", "update",
+            "values", "where",
+        ],
+        line_comments: &["--"],
+        block_comment: C_COMMENT,
+        quotes: &['"', '\''],
+    },
+];
+
+/// A fenced code block being rendered.
+struct Fence {
+    marker: char,
+    length: usize,
+    language: Option<&'static Language>,
+    in_comment: bool,
+}
+
+impl Fence {
+    /// The code block opened by `line`, if it is a fence: three or more backticks or tildes
+    /// followed by an optional language.
+    fn open(line: &str) -> Option<Fence> {
+        let trimmed = line.trim_start();
+        if line.len() - trimmed.len() > 3 {
+            return None;
+        }
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~')?;
+        let length = trimmed.chars().take_while(|&c| c == marker).count();
+        if length < 3 {
+            return None;
+        }
+        let info = trimmed[length..].split_whitespace().next().unwrap_or("");
+        let info = info.to_lowercase();
+        let language = LANGUAGES.iter().find(|l| l.names.contains(&info.as_str()));
+        Some(Fence {
+            marker,
+            length,
+            language,
+            in_comment: false,
+        })
+    }
+
+    fn closes(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        let length = trimmed.chars().take_while(|&c| c == self.marker).count();
+        length >= self.length && length == trimmed.chars().count()
+    }
+
+    fn highlight(&mut self, line: &str) -> Spans<'static> {
+        let line = line.replace('\t', "    ");
+        let language = match self.language {
+            Some(language) => language,
+            None => return Spans::from(Span::styled(line, code_style(Token::Plain))),
+        };
+        let mut tokens: Vec<(Token, &str)> = vec![];
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            let (token, length) = self.token(language, rest);
+            match tokens.last_mut() {
+                Some((last, text)) if *last == token => {
+                    *text = &line[i - text.len()..i + length];
+                }
+                _ => tokens.push((token, &rest[..length])),
+            }
+            i += length;
+        }
+        Spans::from(
+            tokens
+                .into_iter()
+                .map(|(token, text)| Span::styled(text.to_owned(), code_style(token)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// The token at the start of `rest` and its length in bytes.
+    fn token(&mut self, language: &Language, rest: &str) -> (Token, usize) {
+        if self.in_comment {
+            let (_, end) = language.block_comment.unwrap_or(("", ""));
+            return match rest.find(end) {
+                Some(i) => {
+                    self.in_comment = false;
+                    (Token::Comment, i + end.len())
+                }
+                None => (Token::Comment, rest.len()),
+            };
+        }
+        if let Some((start, _)) = language.block_comment {
+            if let Some(comment) = rest.strip_prefix(start) {
+                self.in_comment = true;
+                let (token, length) = self.token(language, comment);
+                return (token, start.len() + length);
+            }
+        }
+        if language.line_comments.iter().any(|c| rest.starts_with(c)) {
+            return (Token::Comment, rest.len());
+        }
+        let c = rest.chars().next().unwrap_or(' ');
+        if language.quotes.contains(&c) {
+            let mut escaped = false;
+            for (i, d) in rest.char_indices().skip(1) {
+                if d == c && !escaped {
+                    return (Token::String, i + 1);
+                }
+                escaped = d == '\\' && !escaped;
+            }
+            return (Token::String, rest.len());
+        }
+        let word = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if word == 0 {
+            return (Token::Plain, c.len_utf8());
+        }
+        let token = if c.is_ascii_digit() {
+            Token::Number
+        } else if language.keywords.contains(&&rest[..word]) {
+            Token::Keyword
+        } else if rest[word..].starts_with('(') {
+            Token::Function
+        } else if c.is_uppercase() {
+            Token::Type
+        } else {
+            Token::Plain
+        };
+        (token, word)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token {
+    Plain,
+    Keyword,
+    Function,
+    Type,
+    Number,
+    String,
+    Comment,
+}
+
+fn code_style(token: Token) -> Style {
+    let style = Style::default();
+    match token {
+        Token::Plain => style.fg(Color::White),
+        Token::Keyword => style.fg(Color::Magenta),
+        Token::Function => style.fg(Color::Blue),
+        Token::Type => style.fg(Color::Yellow),
+        Token::Number => style.fg(Color::Cyan),
+        Token::String => style.fg(Color::Green),
+        Token::Comment => style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The rows, rendered text and targets of the links of `text`.
+    fn links(text: &str) -> Vec<(usize, String, String)> {
+        let (rendered, links) = render(text, 20);
+        links
+            .into_iter()
+            .map(|(row, range, link)| {
+                let line: String = rendered.lines[row]
+                    .0
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect();
+                (row, line[range].to_owned(), link.target)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn links_are_found_where_they_are_rendered() {
+        let text = "# About [[Rust]]\n\
+                    - **see** [[python|the snake]] and [[日本]]\n\
+                    > quoted [[deep#Intro]]\n\
+                    ```\n\
+                    [[not a link]]\n\
+                    ```\n\
+                    | a | b |\n\
+                    |---|--:|\n\
+                    | [[plain]] | x |\n";
+        assert_eq!(
+            links(text),
+            vec![
+                (0, "Rust".into(), "Rust".into()),
+                (1, "the snake".into(), "python".into()),
+                (1, "日本".into(), "日本".into()),
+                (2, "deep#Intro".into(), "deep".into()),
+                (8, "plain".into(), "plain".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn same_label_links_keep_their_own_targets() {
+        assert_eq!(
+            links("[[a|same]] [[b|same]]"),
+            vec![
+                (0, "same".into(), "a".into()),
+                (0, "same".into(), "b".into())
+            ]
+        );
+    }
+}
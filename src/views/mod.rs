@@ -1,6 +1,19 @@
 pub mod add_view;
 pub mod app;
+pub mod backlinks_view;
 pub mod dialog_view;
 pub mod file_view;
+pub mod finder_view;
+pub mod help_view;
+pub mod keymap;
+pub mod markdown;
 pub mod nav;
+pub mod reader_view;
+pub mod rename_view;
+pub mod search_view;
+pub mod sequence;
+pub mod tag_view;
+pub mod trash_view;
+pub mod tty;
 pub mod ui;
+pub mod vim;
@@ -1,6 +1,9 @@
 use crate::data::Knowledge;
+use crate::key::display_keys;
 use crate::views::app;
-use crate::views::app::{App, Tab, ViewState};
+use crate::views::app::{text_width, App, SearchFocus, Tab, TagFocus, ViewState};
+use crate::views::markdown;
+use crate::views::vim::Mode;
 use tui::backend::Backend;
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
@@ -8,22 +11,93 @@ use tui::text::{Span, Spans, Text};
 use tui::widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap};
 use tui::Frame;
 
-pub fn draw_views<T: Backend>(f: &mut Frame<T>, app: &App) {
-    if let Some(state) = app.get_latest_state() {
+pub fn draw_views<T: Backend>(f: &mut Frame<T>, app: &mut App) {
+    if let Some(&state) = app.get_latest_state() {
         match state {
             app::ViewState::DialogView => {
                 // draw twice because we want to have nice overlay of confirm dialog
-                _draw_views(f, &app.previous_view, app);
-                _draw_views(f, state, app);
+                let previous = app.previous_view;
+                _draw_views(f, &previous, app);
+                _draw_views(f, &state, app);
             }
-            app::ViewState::FileView | app::ViewState::AddView | app::ViewState::TagView => {
-                _draw_views(f, state, app);
+            app::ViewState::FinderView | app::ViewState::HelpView | app::ViewState::RenameView => {
+                // the help can be open over the finder, draw every view they cover
+                let states = app.get_states().to_vec();
+                let first = states
+                    .iter()
+                    .rposition(|state| {
+                        !matches!(
+                            state,
+                            ViewState::FinderView | ViewState::HelpView | ViewState::RenameView
+                        )
+                    })
+                    .unwrap_or(0);
+                for state in &states[first..] {
+                    _draw_views(f, state, app);
+                }
+            }
+            app::ViewState::FileView
+            | app::ViewState::AddView
+            | app::ViewState::TagView
+            | app::ViewState::TrashView
+            | app::ViewState::SearchView => {
+                _draw_views(f, &state, app);
             }
         }
     }
+    if !app.sequence.keys.is_empty() {
+        draw_sequence(f, app);
+    }
 }
 
-fn _draw_views<T: Backend>(f: &mut Frame<T>, view_state: &ViewState, app: &App) {
+/// Popup in the bottom right corner with the keys that can follow the keys typed so far.
+fn draw_sequence<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let continuations = app.key_context().continuations(&app.sequence.keys);
+    let keys_width = continuations
+        .iter()
+        .map(|(keys, _)| text_width(keys))
+        .max()
+        .unwrap_or(0);
+    let lines: Vec<_> = continuations
+        .iter()
+        .map(|(keys, description)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{}{}  ", keys, " ".repeat(keys_width - text_width(keys))),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(*description),
+            ])
+        })
+        .collect();
+    let title = format!(
+        "{}{}",
+        app.sequence
+            .count
+            .map_or(String::new(), |count| count.to_string()),
+        display_keys(&app.sequence.keys)
+    );
+    let bounds = f.size();
+    let width = lines
+        .iter()
+        .map(|line| line.width())
+        .chain(std::iter::once(text_width(&title)))
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(bounds.width);
+    let height = (lines.len() as u16 + 2).min(bounds.height);
+    let rect = Rect::new(bounds.width - width, bounds.height - height, width, height);
+    f.render_widget(Clear, rect);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(title);
+    f.render_widget(Paragraph::new(lines).block(block), rect);
+}
+
+fn _draw_views<T: Backend>(f: &mut Frame<T>, view_state: &ViewState, app: &mut App) {
     match view_state {
         app::ViewState::FileView => {
             draw_files_view(f, app);
@@ -31,30 +105,62 @@ fn _draw_views<T: Backend>(f: &mut Frame<T>, view_state: &ViewState, app: &App)
         app::ViewState::AddView => {
             draw_add_view(f, app);
         }
+        app::ViewState::TagView => {
+            draw_tag_view(f, app);
+        }
+        app::ViewState::TrashView => {
+            draw_trash_view(f, app);
+        }
+        app::ViewState::SearchView => {
+            draw_search_view(f, app);
+        }
+        app::ViewState::FinderView => {
+            draw_finder_view(f, app);
+        }
+        app::ViewState::HelpView => {
+            draw_help_view(f, app);
+        }
+        app::ViewState::RenameView => {
+            draw_rename_view(f, app);
+        }
         app::ViewState::DialogView => {
             draw_dialog(f, app);
         }
-        _ => {}
     }
 }
 
 macro_rules! all_files {
     ($file:expr) => {{
-        let item: Vec<_> = std::fs::read_dir($file)
-            .unwrap()
-            .map(|e| e.unwrap().file_name().into_string().unwrap())
-            .collect();
-        item
+        std::fs::read_dir($file).map(|dir| {
+            let item: Vec<_> = dir
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .collect();
+            item
+        })
     }};
 }
 
-pub fn draw_files_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+/// Text of the note at `path` rendered for a preview `area`, or the error reading it.
+fn preview_text(path: &std::path::Path, area: Rect) -> Text<'static> {
+    match Knowledge::from_file(path) {
+        Ok(knowledge) => markdown::render(&knowledge.text, area.width.saturating_sub(2)).0,
+        Err(e) => Text::styled(e.to_string(), Style::default().fg(Color::Red)),
+    }
+}
+
+pub fn draw_files_view<T: Backend>(f: &mut Frame<T>, app: &mut App) {
     // TODO: handle multiple data directories
     let main_block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
         .title("knowledge-base!")
         .border_type(BorderType::Rounded);
+    let mut area = f.size();
+    if app.backlinks_shown {
+        app.refresh_backlinks();
+        area = draw_backlinks(f, app, area, main_block.clone());
+    }
     match app.file_mode {
         app::FileMode::Dir => {
             let left_paths: Vec<_> = app
@@ -64,37 +170,27 @@ pub fn draw_files_view<T: Backend>(f: &mut Frame<T>, app: &App) {
                     let mut path = app.base_path.clone();
                     path.push(e);
                     let name = if path.is_file() {
-                        Span::styled(
-                            String::from("📃  ") + &e,
-                            Style::default().fg(Color::Yellow),
-                        )
+                        Span::styled(String::from("📃  ") + e, Style::default().fg(Color::Yellow))
                     } else {
-                        Span::styled(String::from("📁  ") + &e, Style::default().fg(Color::Cyan))
+                        Span::styled(String::from("📁  ") + e, Style::default().fg(Color::Cyan))
                     };
-                    ListItem::new(Span::from(name))
+                    ListItem::new(name)
                 })
                 .collect();
-            let selected_file = app
-                .files
-                .get(app.file_cycle_stack.last().unwrap().current_item)
-                .unwrap();
-
-            let mut selected_file_path = app.base_path.clone();
-            selected_file_path.push(selected_file);
-            let right_item_text = if selected_file_path.is_dir() {
-                let item: Vec<String> = all_files!(selected_file_path);
-                Text::from(item.join("\n"))
-            } else if selected_file_path.is_file() {
-                let knowledge = Knowledge::from_file(selected_file_path);
-                Text::from(knowledge.text)
-            } else {
-                Text::from("error in reading files")
-            };
             let chunks = Layout::default()
                 .direction(Direction::Horizontal)
                 .margin(2)
                 .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-                .split(f.size());
+                .split(area);
+            let right_item_text = match app.get_current_selected_entry() {
+                Some(path) if path.is_dir() => match all_files!(&path) {
+                    Ok(item) => Text::from(item.join("\n")),
+                    Err(e) => Text::styled(e.to_string(), Style::default().fg(Color::Red)),
+                },
+                Some(path) if path.is_file() => preview_text(&path, chunks[1]),
+                Some(_) => Text::from("error in reading files"),
+                None => Text::from("empty directory"),
+            };
             let descriptions_widget =
                 Paragraph::new(right_item_text).block(main_block.clone().title("text"));
             let main_list = List::new(left_paths)
@@ -111,69 +207,910 @@ pub fn draw_files_view<T: Backend>(f: &mut Frame<T>, app: &App) {
             f.render_widget(descriptions_widget, chunks[1])
         }
         app::FileMode::File => {
-            // create one view
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints([Constraint::Percentage(10), Constraint::Percentage(90)].as_ref())
-                .split(f.size());
-            let knowledge = Knowledge::from_file(&app.base_path);
-            let title_widget = Paragraph::new(Text::from(knowledge.title.as_ref()))
-                .block(main_block.clone().title("Title"));
-            let content_widget = Paragraph::new(Text::from(knowledge.text))
-                .block(main_block.clone().title("Content"));
-            f.render_widget(title_widget, chunks[0]);
-            f.render_widget(content_widget, chunks[1]);
+            draw_reader(f, app, main_block, area);
         }
     }
 }
 
-pub fn draw_add_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+/// Draw the notes linking to the note at the bottom of `area` with the line of each link,
+/// returning the part of `area` left above it.
+fn draw_backlinks<T: Backend>(f: &mut Frame<T>, app: &App, area: Rect, block: Block) -> Rect {
+    const MAX_ROWS: usize = 8;
+    let height = (app.backlinks.len().clamp(1, MAX_ROWS) as u16 + 2).min(area.height / 2);
+    let rect = Rect::new(
+        area.x + 2,
+        (area.y + area.height).saturating_sub(height + 2),
+        area.width.saturating_sub(4),
+        height,
+    );
+    let mut block = block.title(format!("backlinks ({})", app.backlinks.len()));
+    if app.backlinks_focus {
+        block = block.border_style(Style::default().fg(Color::Cyan));
+    }
+    if app.backlinks.is_empty() {
+        let empty = Span::styled("no note links here", Style::default().fg(Color::DarkGray));
+        f.render_widget(Paragraph::new(empty).block(block), rect);
+    } else {
+        let items: Vec<_> = app
+            .backlinks
+            .iter()
+            .map(|e| {
+                let (start, end) = e.matched;
+                let link = markdown::wiki_link_style(Style::default());
+                ListItem::new(Spans::from(vec![
+                    Span::styled(
+                        String::from("📃  ") + &e.title,
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    Span::raw("  "),
+                    Span::raw(e.line[..start].to_string()),
+                    Span::styled(e.line[start..end].to_string(), link),
+                    Span::raw(e.line[end..].to_string()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default();
+        if app.backlinks_focus {
+            state.select(Some(app.backlink));
+        }
+        f.render_stateful_widget(list, rect, &mut state);
+    }
+    Rect {
+        height: area.height - height,
+        ..area
+    }
+}
+
+/// Draw the note open in the reader in `area` from `reader_scroll` on, with the matches of the search
+/// highlighted and the position in the note below it.
+fn draw_reader<T: Backend>(f: &mut Frame<T>, app: &mut App, block: Block, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let title_widget =
+        Paragraph::new(Text::from(app.reader_title.as_ref())).block(block.clone().title("Title"));
+    f.render_widget(title_widget, chunks[0]);
+
+    let height = chunks[1].height.saturating_sub(2) as usize;
+    app.reader_height = height;
+    // the terminal may have grown since the last scroll
+    app.reader_scroll = app.reader_scroll.min(app.reader_max_scroll());
+    let scroll = app.reader_scroll;
+    let (text, _) = markdown::render(&app.reader_text, chunks[1].width.saturating_sub(2));
+    let total = text.lines.len();
+    let lines: Vec<Spans> = text
+        .lines
+        .into_iter()
+        .enumerate()
+        .skip(scroll)
+        .take(height)
+        .map(|(row, spans)| {
+            let mut matches: Vec<_> = app
+                .reader_matches
+                .iter()
+                .enumerate()
+                .filter(|(_, (r, _))| *r == row)
+                .map(|(i, (_, range))| {
+                    let background = if i == app.reader_match {
+                        Color::LightRed
+                    } else {
+                        Color::Yellow
+                    };
+                    (
+                        range.clone(),
+                        Style::default().bg(background).fg(Color::Black),
+                    )
+                })
+                .collect();
+            if let Some((_, range, _)) = app
+                .reader_link
+                .and_then(|i| app.reader_links.get(i))
+                .filter(|(r, _, _)| *r == row)
+            {
+                matches.push((
+                    range.clone(),
+                    Style::default().add_modifier(Modifier::REVERSED),
+                ));
+                matches.sort_by_key(|(range, _)| range.start);
+            }
+            highlight_matches(spans, &matches)
+        })
+        .collect();
+    f.render_widget(
+        Paragraph::new(lines).block(block.title("Content")),
+        chunks[1],
+    );
+
+    let position = if total <= height {
+        String::from("All")
+    } else if scroll == 0 {
+        String::from("Top")
+    } else if scroll >= app.reader_max_scroll() {
+        String::from("Bot")
+    } else {
+        format!("{}%", scroll * 100 / (total - height))
+    };
+    let position = format!(
+        "{}-{}/{}  {}",
+        scroll + 1,
+        (scroll + height).min(total),
+        total,
+        position
+    );
+    let status = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Min(0),
+                Constraint::Length(position.len() as u16),
+            ]
+            .as_ref(),
+        )
+        .split(chunks[2]);
+    f.render_widget(
+        Paragraph::new(Span::styled(position, Style::default().fg(Color::DarkGray))),
+        status[1],
+    );
+    let status = status[0];
+    let query = app.reader_query.get_string();
+    let search = if app.reader_searching {
+        app.input_cursor = (
+            status.x + 1 + app.reader_query.cursor_column() as u16,
+            status.y,
+        );
+        Span::raw(format!("/{}", query))
+    } else if app.backlinks_focus {
+        Span::styled(
+            "Enter opens the backlink  Tab back to the note  b hide backlinks",
+            Style::default().fg(Color::DarkGray),
+        )
+    } else if let (true, Some((_, _, link))) = (
+        query.is_empty(),
+        app.reader_link.and_then(|i| app.reader_links.get(i)),
+    ) {
+        let target = match &link.heading {
+            Some(heading) => format!("{}#{}", link.target, heading),
+            None => link.target.clone(),
+        };
+        Span::styled(
+            format!("Enter opens {}  Tab next link", target),
+            Style::default().fg(Color::DarkGray),
+        )
+    } else if query.is_empty() {
+        Span::styled(
+            "/ search  n/N next/previous match  Tab links",
+            Style::default().fg(Color::DarkGray),
+        )
+    } else if app.reader_matches.is_empty() {
+        Span::styled(
+            format!("no match for {}", query),
+            Style::default().fg(Color::Red),
+        )
+    } else {
+        Span::raw(format!(
+            "/{}  match {} of {}",
+            query,
+            app.reader_match + 1,
+            app.reader_matches.len()
+        ))
+    };
+    f.render_widget(Paragraph::new(search), status);
+}
+
+/// Style the byte `matches` of the line `spans`, sorted by their start, with the style
+/// given for each on top of the style of the text.
+fn highlight_matches(
+    spans: Spans<'static>,
+    matches: &[(std::ops::Range<usize>, Style)],
+) -> Spans<'static> {
+    if matches.is_empty() {
+        return spans;
+    }
+    let mut result = vec![];
+    let mut offset = 0;
+    for span in spans.0 {
+        let content = span.content.as_ref();
+        let mut start = 0;
+        for (range, style) in matches {
+            // a range overlapping the one before only styles what is left of it
+            let from = range.start.max(offset + start);
+            let to = range.end.min(offset + content.len());
+            if from >= to {
+                continue;
+            }
+            let (from, to) = (from - offset, to - offset);
+            if from > start {
+                result.push(Span::styled(content[start..from].to_owned(), span.style));
+            }
+            result.push(Span::styled(
+                content[from..to].to_owned(),
+                span.style.patch(*style),
+            ));
+            start = to;
+        }
+        if start < content.len() {
+            result.push(Span::styled(content[start..].to_owned(), span.style));
+        }
+        offset += content.len();
+    }
+    Spans::from(result)
+}
+
+pub fn draw_tag_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let default_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .border_type(BorderType::Rounded);
+    let highlight_style = Style::default()
+        .bg(Color::Yellow)
+        .fg(Color::Black)
+        .add_modifier(Modifier::BOLD);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(2)
+        .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+        .split(f.size());
+    let right_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(chunks[1]);
+
+    let mut tag_block = default_block.clone().title("tags");
+    let mut note_block = default_block.clone().title("notes");
+    match app.tag_focus {
+        TagFocus::Tags => tag_block = tag_block.border_style(Style::default().fg(Color::Cyan)),
+        TagFocus::Notes => note_block = note_block.border_style(Style::default().fg(Color::Cyan)),
+    }
+
+    let tags: Vec<_> = app
+        .tags
+        .iter()
+        .map(|e| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(
+                    String::from("🏷  ") + &e.tag,
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(format!(" ({})", e.notes.len())),
+            ]))
+        })
+        .collect();
+    let tag_list = List::new(tags)
+        .block(tag_block)
+        .highlight_style(highlight_style);
+    let mut tag_state = ListState::default();
+    if !app.tags.is_empty() {
+        tag_state.select(Some(app.tag_cycle.current_item));
+    }
+    f.render_stateful_widget(tag_list, chunks[0], &mut tag_state);
+
+    let notes: Vec<_> = app
+        .get_selected_tag()
+        .map(|e| {
+            e.notes
+                .iter()
+                .map(|(title, _)| {
+                    ListItem::new(Span::styled(
+                        String::from("📃  ") + title,
+                        Style::default().fg(Color::Yellow),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let note_list = List::new(notes)
+        .block(note_block)
+        .highlight_style(highlight_style);
+    let mut note_state = ListState::default();
+    if let TagFocus::Notes = app.tag_focus {
+        note_state.select(Some(app.tag_note_cycle.current_item));
+    }
+    f.render_stateful_widget(note_list, right_chunks[0], &mut note_state);
+
+    let preview = match app.get_selected_tag_note() {
+        Some((_, path)) if path.is_file() => preview_text(path, right_chunks[1]),
+        Some(_) => Text::from("error in reading files"),
+        None => Text::from("no tagged notes"),
+    };
+    let preview_widget = Paragraph::new(preview).block(default_block.clone().title("text"));
+    f.render_widget(preview_widget, right_chunks[1]);
+}
+
+/// The deleted entries with where they were and when they were deleted, next to the
+/// selected one.
+pub fn draw_trash_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let default_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .border_type(BorderType::Rounded);
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(2)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(f.size());
+
+    let retention = match app.handler.config.trash_retention {
+        0 => String::from("trash"),
+        days => format!("trash, purged after {} days", days),
+    };
+    let entries: Vec<_> = app
+        .trash
+        .iter()
+        .map(|e| {
+            let name = if e.location().is_dir() {
+                Span::styled(
+                    String::from("📁  ") + &e.path.to_string_lossy(),
+                    Style::default().fg(Color::Cyan),
+                )
+            } else {
+                Span::styled(
+                    String::from("📃  ") + &e.path.to_string_lossy(),
+                    Style::default().fg(Color::Yellow),
+                )
+            };
+            ListItem::new(Spans::from(vec![
+                name,
+                Span::styled(
+                    e.deleted.format("  %Y-%m-%d %H:%M").to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let list = List::new(entries)
+        .block(default_block.clone().title(retention))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+    let mut state = ListState::default();
+    if !app.trash.is_empty() {
+        state.select(Some(app.trash_cycle.current_item));
+    }
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let preview = match app.get_selected_trash().map(|e| e.location()) {
+        Some(path) if path.is_dir() => match all_files!(&path) {
+            Ok(item) => Text::from(item.join("\n")),
+            Err(e) => Text::styled(e.to_string(), Style::default().fg(Color::Red)),
+        },
+        Some(path) => preview_text(&path, chunks[1]),
+        None => Text::styled("the trash is empty", Style::default().fg(Color::DarkGray)),
+    };
+    let preview_widget = Paragraph::new(preview).block(default_block.title("text"));
+    f.render_widget(preview_widget, chunks[1]);
+}
+
+pub fn draw_search_view<T: Backend>(f: &mut Frame<T>, app: &App) {
     let default_block = Block::default()
         .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
         .border_type(BorderType::Rounded);
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints(
             [
-                Constraint::Percentage(10),
-                Constraint::Percentage(10),
-                Constraint::Percentage(80),
+                Constraint::Length(3),
+                Constraint::Min(3),
+                Constraint::Length(1),
             ]
             .as_ref(),
         )
         .split(f.size());
+
+    let mut query_block = default_block.clone().title("search");
+    let mut result_block = default_block
+        .clone()
+        .title(format!("results ({})", app.search_results.len()));
+    match app.search_focus {
+        SearchFocus::Query => {
+            query_block = query_block.border_style(Style::default().fg(Color::Cyan))
+        }
+        SearchFocus::Results => {
+            result_block = result_block.border_style(Style::default().fg(Color::Cyan))
+        }
+    }
+    let query = Paragraph::new(app.search_query.get_string()).block(query_block);
+    f.render_widget(query, chunks[0]);
+
+    let results: Vec<_> = app
+        .search_results
+        .iter()
+        .map(|e| {
+            let mut title = vec![Span::styled(
+                String::from("📃  ") + &e.title,
+                Style::default().fg(Color::Yellow),
+            )];
+            if let Some(tag) = &e.tag {
+                title.push(Span::styled(
+                    String::from("  🏷 ") + tag,
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            let line = match e.matched {
+                Some((start, end)) => Spans::from(vec![
+                    Span::raw("    "),
+                    Span::raw(e.line[..start].to_string()),
+                    Span::styled(
+                        e.line[start..end].to_string(),
+                        Style::default()
+                            .bg(Color::Yellow)
+                            .fg(Color::Black)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(e.line[end..].to_string()),
+                ]),
+                None => Spans::from(vec![
+                    Span::raw("    "),
+                    Span::styled(e.line.clone(), Style::default().fg(Color::DarkGray)),
+                ]),
+            };
+            ListItem::new(vec![Spans::from(title), line])
+        })
+        .collect();
+    let result_list = List::new(results)
+        .block(result_block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut result_state = ListState::default();
+    if !app.search_results.is_empty() {
+        result_state.select(Some(app.search_cycle.current_item));
+    }
+    f.render_stateful_widget(result_list, chunks[1], &mut result_state);
+
+    let status = match &app.search_error {
+        Some(e) => Span::styled(e.clone(), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            "tag:rust  title:\"a phrase\"  body:/regex/  updated:>YYYY-MM-DD  AND OR NOT -  ( )",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    f.render_widget(Paragraph::new(Spans::from(status)), chunks[2]);
+}
+
+/// Area of the fuzzy finder popup in a terminal of size `bounds`.
+pub fn finder_rect(bounds: Rect) -> Rect {
+    let width = bounds.width * 7 / 10;
+    let height = bounds.height * 6 / 10;
+    Rect::new(
+        (bounds.width - width) / 2,
+        (bounds.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+pub fn draw_finder_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let rect = finder_rect(f.size());
+    f.render_widget(Clear, rect);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title(format!(
+            "find note ({}/{})",
+            app.finder_matches.len(),
+            app.finder_paths.len()
+        ));
+    let inner = block.inner(rect);
+    f.render_widget(block, rect);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+        .split(inner);
+
+    let query = Paragraph::new(Spans::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Cyan)),
+        Span::raw(app.finder_query.get_string()),
+    ]))
+    .block(Block::default().borders(Borders::BOTTOM));
+    f.render_widget(query, chunks[0]);
+
+    let matched_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let items: Vec<_> = app
+        .finder_matches
+        .iter()
+        .map(|m| {
+            let mut indices = m.indices.iter().peekable();
+            let text = if m.in_title { &m.title } else { &m.path };
+            let mut spans: Vec<_> = text
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if indices.peek() == Some(&&i) {
+                        indices.next();
+                        Span::styled(c.to_string(), matched_style)
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect();
+            if m.in_title {
+                spans.push(Span::styled(
+                    format!("  {}", m.path),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            ListItem::new(Spans::from(spans))
+        })
+        .collect();
+    let list = List::new(items).highlight_style(
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD),
+    );
+    let mut state = ListState::default();
+    if !app.finder_matches.is_empty() {
+        state.select(Some(app.finder_cycle.current_item));
+    }
+    f.render_stateful_widget(list, chunks[1], &mut state);
+}
+
+/// Area of the rename popup in a terminal of size `bounds`.
+pub fn rename_rect(bounds: Rect) -> Rect {
+    let width = bounds.width * 7 / 10;
+    let height = bounds.height.min(4);
+    Rect::new((bounds.width - width) / 2, bounds.height / 4, width, height)
+}
+
+pub fn draw_rename_view<T: Backend>(f: &mut Frame<T>, app: &App) {
+    let rect = rename_rect(f.size());
+    f.render_widget(Clear, rect);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title("rename or move note");
+    let inner = block.inner(rect);
+    f.render_widget(block, rect);
+    let status = match &app.rename_error {
+        Some(e) => Span::styled(e.clone(), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            format!(
+                "path in the data directory without .{}  Enter preview  Esc cancel",
+                app.handler.config.extension
+            ),
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    let lines = vec![
+        Spans::from(vec![
+            Span::styled("> ", Style::default().fg(Color::Cyan)),
+            Span::raw(app.rename_input.get_string()),
+        ]),
+        Spans::from(status),
+    ];
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// Area of the help popup in a terminal of size `bounds`.
+pub fn help_rect(bounds: Rect) -> Rect {
+    let width = bounds.width * 8 / 10;
+    let height = bounds.height * 8 / 10;
+    Rect::new(
+        (bounds.width - width) / 2,
+        (bounds.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+pub fn draw_help_view<T: Backend>(f: &mut Frame<T>, app: &mut App) {
+    let entries = app.help_entries();
+    let rect = help_rect(f.size());
+    f.render_widget(Clear, rect);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Cyan))
+        .title("keys (Esc to close)");
+    let inner = block.inner(rect);
+    f.render_widget(block, rect);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
+        .split(inner);
+
+    let query = Paragraph::new(Spans::from(vec![
+        Span::styled("> ", Style::default().fg(Color::Cyan)),
+        Span::raw(app.help_query.get_string()),
+    ]))
+    .block(Block::default().borders(Borders::BOTTOM));
+    f.render_widget(query, chunks[0]);
+
+    // entries of a category are listed together, in the order the categories first appear
+    let mut categories: Vec<&str> = vec![];
+    for entry in &entries {
+        if !categories.contains(&entry.category) {
+            categories.push(entry.category);
+        }
+    }
+    let keys_width = entries
+        .iter()
+        .map(|entry| text_width(&entry.keys))
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![];
+    for category in categories {
+        if !lines.is_empty() {
+            lines.push(Spans::default());
+        }
+        lines.push(Spans::from(Span::styled(
+            category,
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )));
+        for entry in entries.iter().filter(|entry| entry.category == category) {
+            let padding = " ".repeat(keys_width - text_width(&entry.keys));
+            lines.push(Spans::from(vec![
+                Span::styled(
+                    format!("  {}{}  ", entry.keys, padding),
+                    Style::default().fg(Color::Cyan),
+                ),
+                Span::raw(entry.description),
+                Span::styled(
+                    format!("  {}", entry.name),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Spans::from(Span::styled(
+            "no key matches",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+
+    app.help_height = chunks[1].height as usize;
+    app.help_scroll = app
+        .help_scroll
+        .min(lines.len().saturating_sub(app.help_height));
+    let lines: Vec<_> = lines.into_iter().skip(app.help_scroll).collect();
+    f.render_widget(Paragraph::new(lines), chunks[1]);
+}
+
+pub fn draw_add_view<T: Backend>(f: &mut Frame<T>, app: &mut App) {
+    let default_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let mut constraints = vec![
+        // title and tags are one line
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Min(3),
+    ];
+    if app.vim.is_some() {
+        // status line with the mode
+        constraints.push(Constraint::Length(1));
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints(constraints)
+        .split(f.size());
     let mut title_block = default_block.clone().title("Title");
     let mut tag_block = default_block.clone().title("Tag");
     let mut text_block = default_block.clone().title("Text");
-    if let Some(current_tab) = app.input_tabs.get(app.input_current_tab.current_item) {
-        match current_tab {
-            Tab::Title => {
-                title_block = title_block.border_style(Style::default().fg(Color::Cyan));
-            }
-            Tab::Text => {
-                text_block = text_block.border_style(Style::default().fg(Color::Cyan));
-            }
-            Tab::Tags => {
-                tag_block = tag_block.border_style(Style::default().fg(Color::Cyan));
-            }
+    let current_tab = app.input_tabs.get(app.input_current_tab.current_item);
+    match current_tab {
+        Some(Tab::Title) => {
+            title_block = title_block.border_style(Style::default().fg(Color::Cyan));
+        }
+        Some(Tab::Text) => {
+            text_block = text_block.border_style(Style::default().fg(Color::Cyan));
+        }
+        Some(Tab::Tags) => {
+            tag_block = tag_block.border_style(Style::default().fg(Color::Cyan));
         }
+        None => {}
     }
-    let title = Paragraph::new(app.input_title.get_string()).block(title_block);
-    let tag = Paragraph::new(app.input_tags.get_string()).block(tag_block);
-    let text = Paragraph::new(app.input_text.get_string()).block(text_block);
-    f.render_widget(title, chunks[0]);
-    f.render_widget(tag, chunks[1]);
-    f.render_widget(text, chunks[2]);
+    let line_numbers = app.handler.config.line_numbers;
+    let title_cursor = draw_input(
+        f,
+        &mut app.input_title,
+        title_block,
+        chunks[0],
+        false,
+        false,
+    );
+    let tag_cursor = draw_input(f, &mut app.input_tags, tag_block, chunks[1], false, false);
+    let text_cursor = draw_input(
+        f,
+        &mut app.input_text,
+        text_block,
+        chunks[2],
+        app.soft_wrap,
+        line_numbers,
+    );
+    app.input_cursor = match current_tab {
+        Some(Tab::Title) | None => title_cursor,
+        Some(Tab::Tags) => tag_cursor,
+        Some(Tab::Text) => text_cursor,
+    };
+    if let Some(vim) = &app.vim {
+        let status = chunks[3];
+        let mode = match vim.mode {
+            Mode::Normal => "-- NORMAL --",
+            Mode::Insert => "-- INSERT --",
+            Mode::Visual => "-- VISUAL --",
+            Mode::Command => "",
+        };
+        let line = match (&vim.message, vim.mode) {
+            (Some(message), _) => Spans::from(Span::styled(
+                message.clone(),
+                Style::default().fg(Color::Red),
+            )),
+            (None, Mode::Command) => {
+                app.input_cursor = (status.x + 1 + text_width(&vim.command) as u16, status.y);
+                Spans::from(format!(":{}", vim.command))
+            }
+            (None, _) => Spans::from(vec![
+                Span::styled(mode, Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("  {}", vim.pending())),
+            ]),
+        };
+        f.render_widget(Paragraph::new(line), status);
+    }
+}
+
+/// Draw `input` in `block`, scrolled to show the cursor, and return where the cursor is.
+fn draw_input<T: Backend>(
+    f: &mut Frame<T>,
+    input: &mut app::Input,
+    block: Block,
+    area: Rect,
+    wrap: bool,
+    line_numbers: bool,
+) -> (u16, u16) {
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    let gutter = if line_numbers {
+        input.input.len().to_string().len() + 1
+    } else {
+        0
+    };
+    let width = (inner.width as usize).saturating_sub(gutter).max(1);
+    let height = (inner.height as usize).max(1);
+    let lines = input.screen_lines(width, wrap);
+    let cursor = input.screen_cursor(&lines);
+    if wrap {
+        input.viewport.left = 0;
+    }
+    input.scroll_to(cursor, lines.len(), width, height);
+    let viewport = input.viewport;
+    let selection = input.selection();
+    let text: Vec<Spans> = lines
+        .iter()
+        .enumerate()
+        .skip(viewport.top)
+        .take(height)
+        .map(|(i, line)| {
+            let mut spans = vec![];
+            if line_numbers {
+                // only the first line of a wrapped row is numbered
+                let number = if i == 0 || lines[i - 1].row != line.row {
+                    (line.row + 1).to_string()
+                } else {
+                    String::new()
+                };
+                let color = if line.row == input.vertical_idx {
+                    Color::Yellow
+                } else {
+                    Color::DarkGray
+                };
+                spans.push(Span::styled(
+                    format!("{:>width$} ", number, width = gutter - 1),
+                    Style::default().fg(color),
+                ));
+            }
+            let text = &input.input[line.row];
+            let (range, padding) =
+                app::visible_range(&text[line.start..line.end], viewport.left, width);
+            spans.push(Span::raw(" ".repeat(padding)));
+            let range = line.start + range.start..line.start + range.end;
+            spans.extend(row_spans(text, line.row, range, selection));
+            Spans::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(text), inner);
+    (
+        inner.x + (gutter + cursor.1 - viewport.left) as u16,
+        inner.y + (cursor.0 - viewport.top) as u16,
+    )
+}
+
+/// Bytes `range` of row number `row`, with the part inside `selection` in reverse video.
+fn row_spans(
+    text: &str,
+    row: usize,
+    range: std::ops::Range<usize>,
+    selection: Option<((usize, usize), (usize, usize))>,
+) -> Vec<Span<'static>> {
+    let part = |start: usize, end: usize| text[start..end].to_owned();
+    let (start, end) = match selection {
+        Some((start, end)) if start.0 <= row && row <= end.0 => (
+            if start.0 == row { start.1 } else { 0 },
+            if end.0 == row { end.1 } else { text.len() },
+        ),
+        _ => return vec![Span::raw(part(range.start, range.end))],
+    };
+    let start = start.max(range.start).min(range.end);
+    let end = end.max(start).min(range.end);
+    vec![
+        Span::raw(part(range.start, start)),
+        Span::styled(
+            part(start, end),
+            Style::default().add_modifier(Modifier::REVERSED),
+        ),
+        Span::raw(part(end, range.end)),
+    ]
+}
+
+pub fn draw_error_dialog<T: Backend>(f: &mut Frame<T>, error: &str) {
+    let bounds = f.size();
+    let width = std::cmp::min(bounds.width - 2, 60);
+    let height = std::cmp::min(bounds.height, 10);
+    let left = (bounds.width - width) / 2;
+    let top = bounds.height / 4;
+
+    let rect = Rect::new(left, top, width, height);
+    f.render_widget(Clear, rect);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .title(Span::styled("error", Style::default().fg(Color::Red)));
+    f.render_widget(block, rect);
+    let vchunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([Constraint::Min(3), Constraint::Length(1)].as_ref())
+        .split(rect);
+    let text = Paragraph::new(Text::from(error))
+        .wrap(Wrap { trim: true })
+        .alignment(Alignment::Center);
+    f.render_widget(text, vchunks[0]);
+
+    let ok = Paragraph::new(Span::raw("Ok"))
+        .style(Style::default().fg(Color::Cyan))
+        .alignment(Alignment::Center);
+    f.render_widget(ok, vchunks[1]);
 }
 
 pub fn draw_dialog<T: Backend>(f: &mut Frame<T>, app: &App) {
+    if let Some(error) = &app.error {
+        draw_error_dialog(f, error);
+        return;
+    }
     let bounds = f.size();
-    let width = std::cmp::min(bounds.width - 2, 45);
-    let height = 8;
+    let details = app.confirm_details.len() as u16;
+    let (width, height) = if details == 0 {
+        (std::cmp::min(bounds.width - 2, 45), 8)
+    } else {
+        // a line more for a long question, and one between it and the details
+        (std::cmp::min(bounds.width - 2, 70), 10 + details)
+    };
     let left = (bounds.width - width) / 2;
     let top = bounds.height / 4;
+    let height = std::cmp::min(height, bounds.height - top);
 
     let rect = Rect::new(left, top, width, height);
     f.render_widget(Clear, rect);
@@ -187,7 +1124,16 @@ pub fn draw_dialog<T: Backend>(f: &mut Frame<T>, app: &App) {
         .margin(2)
         .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
         .split(rect);
-    let text = vec![Spans::from(Span::raw(app.confirm_text.clone()))];
+    let mut text = vec![Spans::from(Span::raw(app.confirm_text.clone()))];
+    if details > 0 {
+        text.push(Spans::default());
+        text.extend(app.confirm_details.iter().map(|line| {
+            Spans::from(Span::styled(
+                line.clone(),
+                Style::default().fg(Color::DarkGray),
+            ))
+        }));
+    }
 
     let text = Paragraph::new(text)
         .wrap(Wrap { trim: true })
@@ -0,0 +1,237 @@
+use crate::key::{CtrlKey, Key};
+use crate::views::app::{App, Input};
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReaderAction {
+    Down,
+    Up,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    Top,
+    Bottom,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    ClearSearch,
+    NextLink,
+    PreviousLink,
+    FollowLink,
+    LinkBack,
+    LinkForward,
+    Back,
+}
+
+impl Action for ReaderAction {
+    const VIEW: &'static str = "reader";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            ReaderAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "scroll down a line",
+        ),
+        (ReaderAction::Up, "up", &["k", "<Up>"], "scroll up a line"),
+        (
+            ReaderAction::PageDown,
+            "page-down",
+            &["<PageDown>", "<Space>", "<Ctrl+f>"],
+            "scroll down a screen",
+        ),
+        (
+            ReaderAction::PageUp,
+            "page-up",
+            &["<PageUp>", "<Ctrl+b>"],
+            "scroll up a screen",
+        ),
+        (
+            ReaderAction::HalfPageDown,
+            "half-page-down",
+            &["<Ctrl+d>"],
+            "scroll down half a screen",
+        ),
+        (
+            ReaderAction::HalfPageUp,
+            "half-page-up",
+            &["<Ctrl+u>"],
+            "scroll up half a screen",
+        ),
+        (
+            ReaderAction::Top,
+            "top",
+            &["gg", "<Home>"],
+            "go to the top, or to the line of the count",
+        ),
+        (
+            ReaderAction::Bottom,
+            "bottom",
+            &["G", "<End>"],
+            "go to the bottom, or to the line of the count",
+        ),
+        (ReaderAction::Search, "search", &["/"], "search the note"),
+        (
+            ReaderAction::NextMatch,
+            "next-match",
+            &["n"],
+            "go to the next match",
+        ),
+        (
+            ReaderAction::PreviousMatch,
+            "previous-match",
+            &["N"],
+            "go to the previous match",
+        ),
+        (
+            ReaderAction::ClearSearch,
+            "clear-search",
+            &["<Esc>"],
+            "clear the search",
+        ),
+        (
+            ReaderAction::NextLink,
+            "next-link",
+            &["<Tab>"],
+            "select the next link",
+        ),
+        (
+            ReaderAction::PreviousLink,
+            "previous-link",
+            &["<BackTab>"],
+            "select the previous link",
+        ),
+        (
+            ReaderAction::FollowLink,
+            "follow-link",
+            &["<Enter>"],
+            "open the note of the selected link",
+        ),
+        (
+            ReaderAction::LinkBack,
+            "link-back",
+            &["<Ctrl+o>", "H"],
+            "go back to the note the link was followed from",
+        ),
+        (
+            ReaderAction::LinkForward,
+            "link-forward",
+            &["L"],
+            "go forward again to the note of the link",
+        ),
+        (
+            ReaderAction::Back,
+            "back",
+            &["<Left>"],
+            "go back to the file list",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            ReaderAction::Search
+            | ReaderAction::NextMatch
+            | ReaderAction::PreviousMatch
+            | ReaderAction::ClearSearch => "search",
+            ReaderAction::NextLink
+            | ReaderAction::PreviousLink
+            | ReaderAction::FollowLink
+            | ReaderAction::LinkBack
+            | ReaderAction::LinkForward => "links",
+            ReaderAction::Back => "navigate",
+            _ => "scroll",
+        }
+    }
+}
+
+/// Keys of the reader showing a note in FileMode::File. Returns false for the keys left to
+/// the file view.
+pub fn handler(app: &mut App, keys: &[Key]) -> bool {
+    if app.reader_searching {
+        if let [key] = keys {
+            query_handler(app, key);
+        }
+        return true;
+    }
+    let page = app.reader_height.max(1) as isize;
+    let count = app.count() as isize;
+    match app.keymaps.reader.get(keys) {
+        Some(ReaderAction::Down) => app.scroll_reader(count),
+        Some(ReaderAction::Up) => app.scroll_reader(-count),
+        Some(ReaderAction::PageDown) => app.scroll_reader(count * page),
+        Some(ReaderAction::PageUp) => app.scroll_reader(-count * page),
+        Some(ReaderAction::HalfPageDown) => app.scroll_reader(count * page / 2),
+        Some(ReaderAction::HalfPageUp) => app.scroll_reader(-count * page / 2),
+        Some(action @ ReaderAction::Top) | Some(action @ ReaderAction::Bottom) => {
+            app.reader_scroll = match app.sequence.count {
+                Some(line) => (line - 1).min(app.reader_max_scroll()),
+                None if action == ReaderAction::Top => 0,
+                None => app.reader_max_scroll(),
+            };
+        }
+        Some(ReaderAction::Search) => {
+            app.reader_query = Input::default();
+            app.reader_searching = true;
+            app.refresh_reader_search();
+        }
+        Some(ReaderAction::NextMatch) => app.next_reader_match(true),
+        Some(ReaderAction::PreviousMatch) => app.next_reader_match(false),
+        // without a search the key is left to the file view
+        Some(ReaderAction::ClearSearch) if !app.reader_matches.is_empty() => {
+            app.reader_query = Input::default();
+            app.refresh_reader_search();
+        }
+        Some(ReaderAction::NextLink) => app.next_reader_link(true),
+        Some(ReaderAction::PreviousLink) => app.next_reader_link(false),
+        // without a selected link the key is left to the file view
+        Some(ReaderAction::FollowLink) if app.reader_link.is_some() => {
+            if let Err(e) = app.follow_reader_link() {
+                app.show_error(e);
+            }
+        }
+        Some(ReaderAction::LinkBack) => app.jump_link_history(true),
+        Some(ReaderAction::LinkForward) => app.jump_link_history(false),
+        Some(ReaderAction::Back) => app.leave_directory(),
+        _ => return false,
+    }
+    true
+}
+
+fn query_handler(app: &mut App, event: &Key) {
+    match event {
+        Key::Char(c) => {
+            app.reader_query.insert(*c);
+            app.refresh_reader_search();
+            app.show_reader_match();
+        }
+        Key::Backspace => {
+            if app.reader_query.get_string().is_empty() {
+                app.reader_searching = false;
+                return;
+            }
+            app.reader_query.backspace();
+            app.refresh_reader_search();
+            app.show_reader_match();
+        }
+        Key::Ctrl(CtrlKey::Char('w')) => {
+            app.reader_query.backspace_word();
+            app.refresh_reader_search();
+            app.show_reader_match();
+        }
+        Key::Left => {
+            app.reader_query.move_left();
+        }
+        Key::Right => {
+            app.reader_query.move_right();
+        }
+        Key::Enter => {
+            app.reader_searching = false;
+        }
+        Key::Esc => {
+            app.reader_searching = false;
+            app.reader_query = Input::default();
+            app.refresh_reader_search();
+        }
+        _ => {}
+    }
+}
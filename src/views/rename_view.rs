@@ -0,0 +1,34 @@
+use crate::key::{CtrlKey, Key};
+use crate::views::app::App;
+
+/// Characters are typed into the new path of the note, Enter asks to confirm the rename with
+/// a preview of the notes whose links are rewritten.
+pub fn handler(app: &mut App, keys: &[Key]) {
+    match keys {
+        [Key::Char(c)] => {
+            app.rename_input.insert(*c);
+            app.rename_error = None;
+        }
+        [Key::Backspace] => {
+            app.rename_input.backspace();
+            app.rename_error = None;
+        }
+        [Key::Ctrl(CtrlKey::Char('w'))] => {
+            app.rename_input.backspace_word();
+            app.rename_error = None;
+        }
+        [Key::Left] => {
+            app.rename_input.move_left();
+        }
+        [Key::Right] => {
+            app.rename_input.move_right();
+        }
+        [Key::Enter] => {
+            app.preview_rename();
+        }
+        [Key::Esc] => {
+            app.pop_state();
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,179 @@
+use crate::key::{CtrlKey, Key};
+use crate::views::app::{App, SearchFocus, ViewState};
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchAction {
+    Down,
+    Up,
+    Open,
+    Edit,
+    Focus,
+    Finder,
+    Help,
+    Back,
+}
+
+impl Action for SearchAction {
+    const VIEW: &'static str = "search";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            SearchAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "select the next result",
+        ),
+        (
+            SearchAction::Up,
+            "up",
+            &["k", "<Up>"],
+            "select the previous result",
+        ),
+        (
+            SearchAction::Open,
+            "open",
+            &["<Enter>", "l"],
+            "show the selected note in the file view",
+        ),
+        (SearchAction::Edit, "edit", &["e"], "edit the selected note"),
+        (
+            SearchAction::Focus,
+            "focus",
+            &["<Tab>", "<BackTab>", "/"],
+            "switch between the query and the results",
+        ),
+        (
+            SearchAction::Finder,
+            "finder",
+            &["<Ctrl+p>"],
+            "find a note by name",
+        ),
+        (SearchAction::Help, "help", &["?", "<F1>"], "list the keys"),
+        (
+            SearchAction::Back,
+            "back",
+            &["<Esc>", "q"],
+            "go back to the file view",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            SearchAction::Edit => "notes",
+            SearchAction::Finder | SearchAction::Help => "views",
+            _ => "navigate",
+        }
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    match app.search_focus {
+        SearchFocus::Query => query_handler(app, keys),
+        SearchFocus::Results => results_handler(app, keys),
+    }
+}
+
+/// Characters are typed into the query, other keys edit it or are looked up in the keymap.
+fn query_handler(app: &mut App, keys: &[Key]) {
+    match keys {
+        [Key::Char(c)] => {
+            app.search_query.insert(*c);
+            app.search_cycle.current_item = 0;
+            app.refresh_search();
+        }
+        [Key::Backspace] => {
+            app.search_query.backspace();
+            app.search_cycle.current_item = 0;
+            app.refresh_search();
+        }
+        [Key::Ctrl(CtrlKey::Char('w'))] => {
+            app.search_query.backspace_word();
+            app.search_cycle.current_item = 0;
+            app.refresh_search();
+        }
+        [Key::Left] => {
+            app.search_query.move_left();
+        }
+        [Key::Right] => {
+            app.search_query.move_right();
+        }
+        [Key::Home] => {
+            app.search_query.beginning_of_line();
+        }
+        [Key::End] => {
+            app.search_query.end_of_line();
+        }
+        _ => match app.keymaps.search.get(keys) {
+            Some(SearchAction::Down) | Some(SearchAction::Focus)
+                if !app.search_results.is_empty() =>
+            {
+                app.search_focus = SearchFocus::Results;
+            }
+            Some(SearchAction::Open) => {
+                open_selected(app);
+            }
+            Some(SearchAction::Finder) => {
+                app.open_finder();
+            }
+            Some(SearchAction::Help) => {
+                app.open_help();
+            }
+            Some(SearchAction::Back) => {
+                app.pop_state();
+            }
+            _ => {}
+        },
+    }
+}
+
+fn results_handler(app: &mut App, keys: &[Key]) {
+    let action = match app.keymaps.search.get(keys) {
+        Some(action) => action,
+        None => return,
+    };
+    match action {
+        SearchAction::Down => {
+            for _ in 0..app.count() {
+                app.search_cycle.next();
+            }
+        }
+        SearchAction::Up => {
+            for _ in 0..app.count() {
+                app.search_cycle.prev();
+            }
+        }
+        SearchAction::Open => {
+            open_selected(app);
+        }
+        SearchAction::Edit => {
+            if let Some(path) = app.get_selected_search_result().map(|e| e.path.clone()) {
+                // base_path has to be the note's directory for the save path in add_view
+                app.jump_to(&path);
+                match app.set_edit_view_ref(path) {
+                    Ok(_) => app.push_state(ViewState::AddView),
+                    Err(e) => app.show_error(e),
+                }
+            }
+        }
+        SearchAction::Focus => {
+            app.search_focus = SearchFocus::Query;
+        }
+        SearchAction::Finder => {
+            app.open_finder();
+        }
+        SearchAction::Help => {
+            app.open_help();
+        }
+        SearchAction::Back => {
+            app.pop_state();
+        }
+    }
+}
+
+/// Leave the search and select the note in the file view.
+fn open_selected(app: &mut App) {
+    if let Some(path) = app.get_selected_search_result().map(|e| e.path.clone()) {
+        app.pop_state();
+        app.jump_to(&path);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::key::Key;
+use crate::views::keymap::{Bindings, Lookup};
+use std::time::{Duration, Instant};
+
+/// The keymaps keys are matched against in the current view.
+#[derive(Default)]
+pub struct Context<'a> {
+    /// keymaps in the order the view looks keys up
+    pub bindings: Vec<&'a dyn Bindings>,
+    /// digits typed before the keys are a count
+    pub counts: bool,
+    /// characters are typed into a field rather than starting a sequence
+    pub typing: bool,
+}
+
+impl Context<'_> {
+    fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::default();
+        for bindings in &self.bindings {
+            let l = bindings.lookup(keys);
+            lookup.bound |= l.bound;
+            lookup.prefix |= l.prefix;
+        }
+        lookup
+    }
+
+    /// The keys that can follow `keys` with what they do, the first keymap first.
+    pub fn continuations(&self, keys: &[Key]) -> Vec<(String, &'static str)> {
+        let mut continuations: Vec<(String, &'static str)> = vec![];
+        for bindings in &self.bindings {
+            for (next, description) in bindings.continuations(keys) {
+                // a view shadows the keys of the keymaps after it
+                if !continuations.iter().any(|(n, _)| *n == next) {
+                    continuations.push((next, description));
+                }
+            }
+        }
+        continuations
+    }
+}
+
+/// Keys typed of a sequence like `gg` or `<Space>ft` that is not complete yet, and the count
+/// typed before it.
+#[derive(Default)]
+pub struct Sequence {
+    pub keys: Vec<Key>,
+    pub count: Option<usize>,
+    /// when the last key was typed
+    typed: Option<Instant>,
+}
+
+/// Counts are capped, a long row of digits would overflow.
+const MAX_COUNT: usize = 99_999;
+
+impl Sequence {
+    /// Add `key` to the keys typed so far. Returns the key sequences the view should handle
+    /// now, in order: the bound sequence the keys complete, or every key of a sequence that
+    /// turned out not to be bound on its own.
+    pub fn push(&mut self, key: Key, context: &Context) -> Vec<Vec<Key>> {
+        if self.keys.is_empty() {
+            match key {
+                Key::Char(c @ '0'..='9')
+                    if context.counts && (c != '0' || self.count.is_some()) =>
+                {
+                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                    let count = self.count.unwrap_or(0) * 10 + digit;
+                    self.count = Some(count.min(MAX_COUNT));
+                    return vec![];
+                }
+                Key::Char(_) if context.typing => return vec![vec![key]],
+                _ => {}
+            }
+        }
+        self.keys.push(key);
+        let lookup = context.lookup(&self.keys);
+        if lookup.prefix {
+            // wait for the next key, or for the timeout if the keys are bound as they are
+            self.typed = Some(Instant::now());
+            return vec![];
+        }
+        if lookup.bound || self.keys.len() == 1 {
+            self.typed = None;
+            return vec![std::mem::take(&mut self.keys)];
+        }
+        // the key does not continue the sequence, it may start the next one
+        self.keys.pop();
+        let mut ready = self.flush(context);
+        ready.extend(self.push(key, context));
+        ready
+    }
+
+    /// Whether the keys typed have waited `timeout` for the next one.
+    pub fn expired(&self, timeout: Duration) -> bool {
+        matches!(self.typed, Some(typed) if typed.elapsed() >= timeout)
+    }
+
+    /// Stop waiting for more keys: the keys typed are handled as they are if they are bound,
+    /// one by one otherwise.
+    pub fn flush(&mut self, context: &Context) -> Vec<Vec<Key>> {
+        self.typed = None;
+        let keys = std::mem::take(&mut self.keys);
+        if keys.is_empty() {
+            vec![]
+        } else if context.lookup(&keys).bound {
+            vec![keys]
+        } else {
+            keys.into_iter().map(|key| vec![key]).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Binding;
+    use crate::key::parse_keys;
+    use crate::views::keymap::Keymaps;
+    use std::collections::BTreeMap;
+
+    /// The default keymaps with `g` bound on its own in the reader and a leader sequence.
+    fn keymaps() -> Keymaps {
+        let mut keys = BTreeMap::new();
+        let reader = vec![("g".to_owned(), "bottom".to_owned())];
+        keys.insert(
+            "reader".to_owned(),
+            Binding::View(reader.into_iter().collect()),
+        );
+        let file = vec![("<leader>ft".to_owned(), "tags".to_owned())];
+        keys.insert("file".to_owned(), Binding::View(file.into_iter().collect()));
+        Keymaps::new(&keys, ",").unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn keys(s: &str) -> Vec<Key> {
+        parse_keys(s, Some(Key::Char(','))).unwrap()
+    }
+
+    fn push(sequence: &mut Sequence, s: &str, context: &Context) -> Vec<Vec<Key>> {
+        keys(s)
+            .into_iter()
+            .flat_map(|key| sequence.push(key, context))
+            .collect()
+    }
+
+    #[test]
+    fn sequences_complete_when_their_last_key_is_typed() {
+        let keymaps = keymaps();
+        let context = Context {
+            bindings: vec![&keymaps.reader],
+            ..Context::default()
+        };
+        let mut sequence = Sequence::default();
+        assert!(push(&mut sequence, "g", &context).is_empty());
+        assert_eq!(push(&mut sequence, "g", &context), vec![keys("gg")]);
+        assert!(sequence.keys.is_empty());
+        assert_eq!(push(&mut sequence, "j", &context), vec![keys("j")]);
+    }
+
+    #[test]
+    fn ambiguous_prefixes_wait_for_the_timeout() {
+        let keymaps = keymaps();
+        let context = Context {
+            bindings: vec![&keymaps.reader],
+            ..Context::default()
+        };
+        let mut sequence = Sequence::default();
+        assert!(push(&mut sequence, "g", &context).is_empty());
+        assert!(!sequence.expired(Duration::from_secs(60)));
+        assert!(sequence.expired(Duration::from_millis(0)));
+        // a tick after the timeout handles the keys as they are
+        assert_eq!(sequence.flush(&context), vec![keys("g")]);
+        assert!(!sequence.expired(Duration::from_millis(0)));
+        assert!(sequence.flush(&context).is_empty());
+    }
+
+    #[test]
+    fn keys_not_continuing_a_sequence_are_handled_on_their_own() {
+        let keymaps = keymaps();
+        let context = Context {
+            bindings: vec![&keymaps.reader],
+            ..Context::default()
+        };
+        let mut sequence = Sequence::default();
+        assert!(push(&mut sequence, "g", &context).is_empty());
+        assert_eq!(
+            push(&mut sequence, "j", &context),
+            vec![keys("g"), keys("j")]
+        );
+
+        // keys only bound as a sequence are handled one by one
+        let context = Context {
+            bindings: vec![&keymaps.file],
+            ..Context::default()
+        };
+        assert!(push(&mut sequence, ",f", &context).is_empty());
+        assert_eq!(
+            push(&mut sequence, "x", &context),
+            vec![keys(","), keys("f"), keys("x")]
+        );
+    }
+
+    #[test]
+    fn the_leader_starts_sequences() {
+        let keymaps = keymaps();
+        let context = Context {
+            bindings: vec![&keymaps.file],
+            ..Context::default()
+        };
+        assert_eq!(keys("<leader>ft"), keys(",ft"));
+        let mut sequence = Sequence::default();
+        assert!(push(&mut sequence, ",f", &context).is_empty());
+        assert_eq!(context.continuations(&keys(",f")).len(), 1);
+        assert_eq!(push(&mut sequence, "t", &context), vec![keys(",ft")]);
+    }
+
+    #[test]
+    fn digits_are_counts_only_where_the_view_takes_them() {
+        let keymaps = keymaps();
+        let context = Context {
+            bindings: vec![&keymaps.reader],
+            counts: true,
+            ..Context::default()
+        };
+        let mut sequence = Sequence::default();
+        // a leading 0 is not a count
+        assert_eq!(push(&mut sequence, "0", &context), vec![keys("0")]);
+        assert!(push(&mut sequence, "120", &context).is_empty());
+        assert_eq!(sequence.count, Some(120));
+        assert_eq!(push(&mut sequence, "j", &context), vec![keys("j")]);
+
+        let context = Context {
+            bindings: vec![&keymaps.reader],
+            typing: true,
+            ..Context::default()
+        };
+        let mut sequence = Sequence::default();
+        assert_eq!(
+            push(&mut sequence, "5g", &context),
+            vec![keys("5"), keys("g")]
+        );
+        assert_eq!(sequence.count, None);
+    }
+}
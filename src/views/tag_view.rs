@@ -0,0 +1,198 @@
+use crate::error::Result;
+use crate::key::Key;
+use crate::views::app::{App, TagFocus, ViewState};
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TagAction {
+    Down,
+    Up,
+    Notes,
+    Tags,
+    Open,
+    Edit,
+    Delete,
+    Refresh,
+    Finder,
+    Help,
+    Back,
+}
+
+impl Action for TagAction {
+    const VIEW: &'static str = "tag";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            TagAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "select the next tag or note",
+        ),
+        (
+            TagAction::Up,
+            "up",
+            &["k", "<Up>"],
+            "select the previous tag or note",
+        ),
+        (
+            TagAction::Notes,
+            "notes",
+            &["l", "<Right>", "<Tab>"],
+            "go to the notes of the tag",
+        ),
+        (
+            TagAction::Tags,
+            "tags",
+            &["h", "<Left>", "<BackTab>"],
+            "go back to the tags",
+        ),
+        (
+            TagAction::Open,
+            "open",
+            &["<Enter>"],
+            "go to the notes, or open the selected note",
+        ),
+        (TagAction::Edit, "edit", &["e"], "edit the selected note"),
+        (
+            TagAction::Delete,
+            "delete",
+            &["D"],
+            "move the selected note to the trash",
+        ),
+        (
+            TagAction::Refresh,
+            "refresh",
+            &["r"],
+            "read the notes again",
+        ),
+        (
+            TagAction::Finder,
+            "finder",
+            &["<Ctrl+p>"],
+            "find a note by name",
+        ),
+        (TagAction::Help, "help", &["?", "<F1>"], "list the keys"),
+        (
+            TagAction::Back,
+            "back",
+            &["<Esc>", "q"],
+            "go back to the file view",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            TagAction::Edit | TagAction::Delete | TagAction::Refresh => "notes",
+            TagAction::Finder | TagAction::Help => "views",
+            _ => "navigate",
+        }
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    let action = match app.keymaps.tag.get(keys) {
+        Some(action) => action,
+        None => return,
+    };
+    match action {
+        TagAction::Down => match app.tag_focus {
+            TagFocus::Tags => {
+                if !app.tags.is_empty() {
+                    for _ in 0..app.count() {
+                        app.tag_cycle.next();
+                    }
+                    app.tag_note_cycle.current_item = 0;
+                    app.refresh_tag_notes();
+                }
+            }
+            TagFocus::Notes => {
+                if app.tag_note_cycle.total_len > 0 {
+                    for _ in 0..app.count() {
+                        app.tag_note_cycle.next();
+                    }
+                }
+            }
+        },
+        TagAction::Up => match app.tag_focus {
+            TagFocus::Tags => {
+                if !app.tags.is_empty() {
+                    for _ in 0..app.count() {
+                        app.tag_cycle.prev();
+                    }
+                    app.tag_note_cycle.current_item = 0;
+                    app.refresh_tag_notes();
+                }
+            }
+            TagFocus::Notes => {
+                if app.tag_note_cycle.total_len > 0 {
+                    for _ in 0..app.count() {
+                        app.tag_note_cycle.prev();
+                    }
+                }
+            }
+        },
+        TagAction::Notes => {
+            if app.tag_note_cycle.total_len > 0 {
+                app.tag_focus = TagFocus::Notes;
+            }
+        }
+        TagAction::Tags => {
+            app.tag_focus = TagFocus::Tags;
+        }
+        TagAction::Open => match app.tag_focus {
+            TagFocus::Tags => {
+                if app.tag_note_cycle.total_len > 0 {
+                    app.tag_focus = TagFocus::Notes;
+                }
+            }
+            TagFocus::Notes => {
+                // open the note in the file view
+                if let Some((_, path)) = app.get_selected_tag_note().cloned() {
+                    app.jump_to(&path);
+                    app.pop_state();
+                    app.enter_directory();
+                }
+            }
+        },
+        TagAction::Edit => {
+            if let TagFocus::Notes = app.tag_focus {
+                if let Some((_, path)) = app.get_selected_tag_note().cloned() {
+                    // base_path has to be the note's directory for the save path in add_view
+                    app.jump_to(&path);
+                    match app.set_edit_view_ref(path) {
+                        Ok(_) => app.push_state(ViewState::AddView),
+                        Err(e) => app.show_error(e),
+                    }
+                }
+            }
+        }
+        TagAction::Delete => {
+            if let TagFocus::Notes = app.tag_focus {
+                if let Some((title, path)) = app.get_selected_tag_note().cloned() {
+                    fn action(app: &mut App) -> Result<()> {
+                        app.remove_directory()
+                    }
+                    app.jump_to(&path);
+                    app.push_state(ViewState::DialogView);
+                    app.confirm_action = Some(action);
+                    app.confirm_text = format!("move to the trash: {}?", title);
+                    app.confirm = false;
+                    app.previous_view = ViewState::TagView;
+                }
+            }
+        }
+        TagAction::Refresh => {
+            if let Err(e) = app.set_tag_view_ref() {
+                app.show_error(e);
+            }
+        }
+        TagAction::Finder => {
+            app.open_finder();
+        }
+        TagAction::Help => {
+            app.open_help();
+        }
+        TagAction::Back => {
+            app.pop_state();
+        }
+    }
+}
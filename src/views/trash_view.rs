@@ -0,0 +1,129 @@
+use crate::error::Result;
+use crate::key::Key;
+use crate::views::app::{App, ViewState};
+use crate::views::keymap::{Action, ActionInfo};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrashAction {
+    Down,
+    Up,
+    Restore,
+    Purge,
+    Empty,
+    Help,
+    Back,
+}
+
+impl Action for TrashAction {
+    const VIEW: &'static str = "trash";
+    const ACTIONS: &'static [ActionInfo<Self>] = &[
+        (
+            TrashAction::Down,
+            "down",
+            &["j", "<Down>"],
+            "select the next deleted entry",
+        ),
+        (
+            TrashAction::Up,
+            "up",
+            &["k", "<Up>"],
+            "select the previous deleted entry",
+        ),
+        (
+            TrashAction::Restore,
+            "restore",
+            &["r", "<Enter>"],
+            "move the selected entry back where it was",
+        ),
+        (
+            TrashAction::Purge,
+            "purge",
+            &["D"],
+            "delete the selected entry for good",
+        ),
+        (
+            TrashAction::Empty,
+            "empty",
+            &["X"],
+            "delete everything in the trash for good",
+        ),
+        (TrashAction::Help, "help", &["?", "<F1>"], "list the keys"),
+        (
+            TrashAction::Back,
+            "back",
+            &["<Esc>", "q"],
+            "go back to the file view",
+        ),
+    ];
+
+    fn category(self) -> &'static str {
+        match self {
+            TrashAction::Restore | TrashAction::Purge | TrashAction::Empty => "trash",
+            TrashAction::Help => "views",
+            _ => "navigate",
+        }
+    }
+}
+
+pub fn handler(app: &mut App, keys: &[Key]) {
+    let action = match app.keymaps.trash.get(keys) {
+        Some(action) => action,
+        None => return,
+    };
+    match action {
+        TrashAction::Down => {
+            if !app.trash.is_empty() {
+                for _ in 0..app.count() {
+                    app.trash_cycle.next();
+                }
+            }
+        }
+        TrashAction::Up => {
+            if !app.trash.is_empty() {
+                for _ in 0..app.count() {
+                    app.trash_cycle.prev();
+                }
+            }
+        }
+        TrashAction::Restore => {
+            if let Err(e) = app.restore_trash() {
+                app.show_error(e);
+            }
+        }
+        TrashAction::Purge => {
+            fn action(app: &mut App) -> Result<()> {
+                app.purge_trash()
+            }
+            let path = match app.get_selected_trash() {
+                Some(entry) => entry.path.display().to_string(),
+                None => return,
+            };
+            app.push_state(ViewState::DialogView);
+            app.confirm_action = Some(action);
+            app.confirm_text = format!("delete {} for good?", path);
+            app.confirm = false;
+            app.previous_view = ViewState::TrashView;
+        }
+        TrashAction::Empty => {
+            fn action(app: &mut App) -> Result<()> {
+                app.empty_trash()
+            }
+            let count = app.trash.len();
+            if count == 0 {
+                return;
+            }
+            let entries = if count == 1 { "entry" } else { "entries" };
+            app.push_state(ViewState::DialogView);
+            app.confirm_action = Some(action);
+            app.confirm_text = format!("delete the {} {} in the trash for good?", count, entries);
+            app.confirm = false;
+            app.previous_view = ViewState::TrashView;
+        }
+        TrashAction::Help => {
+            app.open_help();
+        }
+        TrashAction::Back => {
+            app.pop_state();
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use crate::error::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::io::AsRawFd;
+use std::time::Duration;
+
+// crossterm 0.19 drops the bracketed paste markers it cannot parse, so the terminal is read
+// here: pastes are split off and everything else is parsed into keys like crossterm does.
+pub const ENABLE_BRACKETED_PASTE: &str = "\x1b[?2004h";
+pub const DISABLE_BRACKETED_PASTE: &str = "\x1b[?2004l";
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+/// What the terminal sent.
+#[derive(PartialEq, Debug)]
+pub enum Input {
+    Key(KeyEvent),
+    /// text pasted into the terminal, between the bracketed paste markers
+    Paste(String),
+}
+
+/// Reads the input of the terminal the program runs in.
+pub struct Reader {
+    tty: File,
+    parser: Parser,
+}
+
+impl Reader {
+    pub fn open() -> Result<Self> {
+        Ok(Reader {
+            tty: File::open("/dev/tty")?,
+            parser: Parser::default(),
+        })
+    }
+
+    /// Wait up to `timeout` for input, and read everything that arrived.
+    pub fn read(&mut self, timeout: Duration) -> Result<Vec<Input>> {
+        let mut buffer = [0; 1024];
+        let mut timeout = timeout;
+        while self.ready(timeout) {
+            let read = self.tty.read(&mut buffer)?;
+            self.parser.advance(&buffer[..read]);
+            timeout = Duration::from_millis(0);
+        }
+        // nothing more is waiting, a lone Esc is the key and not the start of a sequence
+        self.parser.flush();
+        Ok(std::mem::take(&mut self.parser.inputs))
+    }
+
+    /// Whether there is something to read within `timeout`.
+    fn ready(&self, timeout: Duration) -> bool {
+        let mut fd = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+        // safe: one valid pollfd is passed with its count
+        unsafe { libc::poll(&mut fd, 1, timeout) > 0 }
+    }
+}
+
+/// Turns the bytes read from the terminal into keys and pastes. A sequence or paste may be
+/// split across reads.
+#[derive(Default)]
+struct Parser {
+    /// bytes of the key being read
+    pending: Vec<u8>,
+    /// bytes of the paste being read, None outside of a paste
+    paste: Option<Vec<u8>>,
+    inputs: Vec<Input>,
+}
+
+/// Result of parsing the bytes of one key.
+enum Parsed {
+    Key(KeyEvent),
+    /// more bytes are needed
+    Incomplete,
+    /// not a key we know, the bytes are dropped
+    Invalid,
+}
+
+impl Parser {
+    fn advance(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if let Some(paste) = &mut self.paste {
+                paste.push(byte);
+                if paste.ends_with(PASTE_END) {
+                    paste.truncate(paste.len() - PASTE_END.len());
+                    let text = String::from_utf8_lossy(paste).into_owned();
+                    self.inputs.push(Input::Paste(text));
+                    self.paste = None;
+                }
+                continue;
+            }
+            self.pending.push(byte);
+            if self.pending == PASTE_START {
+                self.pending.clear();
+                self.paste = Some(vec![]);
+                continue;
+            }
+            match parse_key(&self.pending) {
+                Parsed::Key(key) => {
+                    self.inputs.push(Input::Key(key));
+                    self.pending.clear();
+                }
+                Parsed::Incomplete => {}
+                Parsed::Invalid => self.pending.clear(),
+            }
+        }
+    }
+
+    /// Nothing more is waiting: an Esc on its own is the key, an unfinished sequence is
+    /// dropped. A paste goes on until its end marker.
+    fn flush(&mut self) {
+        if self.pending == b"\x1b" {
+            self.inputs.push(Input::Key(KeyCode::Esc.into()));
+        }
+        self.pending.clear();
+    }
+}
+
+fn parse_key(bytes: &[u8]) -> Parsed {
+    let key = |code, modifiers| Parsed::Key(KeyEvent::new(code, modifiers));
+    match bytes[0] {
+        b'\x1b' => match bytes.get(1) {
+            None => Parsed::Incomplete,
+            Some(b'\x1b') => key(KeyCode::Esc, KeyModifiers::NONE),
+            Some(b'[') => parse_csi(bytes),
+            Some(b'O') => match bytes.get(2) {
+                None => Parsed::Incomplete,
+                Some(&c @ b'P'..=b'S') => key(KeyCode::F(1 + c - b'P'), KeyModifiers::NONE),
+                Some(&c) => match cursor_key(c) {
+                    Some(code) => key(code, KeyModifiers::NONE),
+                    None => Parsed::Invalid,
+                },
+            },
+            // Esc before a key is Alt
+            Some(_) => match parse_key(&bytes[1..]) {
+                Parsed::Key(mut event) => {
+                    event.modifiers |= KeyModifiers::ALT;
+                    Parsed::Key(event)
+                }
+                parsed => parsed,
+            },
+        },
+        b'\r' => key(KeyCode::Enter, KeyModifiers::NONE),
+        b'\t' => key(KeyCode::Tab, KeyModifiers::NONE),
+        b'\x7f' => key(KeyCode::Backspace, KeyModifiers::NONE),
+        b'\0' => key(KeyCode::Char(' '), KeyModifiers::CONTROL),
+        c @ b'\x01'..=b'\x1a' => key(KeyCode::Char((c - 1 + b'a') as char), KeyModifiers::CONTROL),
+        c @ b'\x1c'..=b'\x1f' => key(
+            KeyCode::Char((c - 0x1c + b'4') as char),
+            KeyModifiers::CONTROL,
+        ),
+        c => {
+            let len = match c {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Parsed::Invalid,
+            };
+            if bytes.len() < len {
+                return Parsed::Incomplete;
+            }
+            match std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) if c.is_uppercase() => key(KeyCode::Char(c), KeyModifiers::SHIFT),
+                Some(c) => key(KeyCode::Char(c), KeyModifiers::NONE),
+                None => Parsed::Invalid,
+            }
+        }
+    }
+}
+
+/// The key of the last byte of an arrow, Home or End sequence.
+fn cursor_key(byte: u8) -> Option<KeyCode> {
+    match byte {
+        b'A' => Some(KeyCode::Up),
+        b'B' => Some(KeyCode::Down),
+        b'C' => Some(KeyCode::Right),
+        b'D' => Some(KeyCode::Left),
+        b'H' => Some(KeyCode::Home),
+        b'F' => Some(KeyCode::End),
+        _ => None,
+    }
+}
+
+/// Parse `ESC [ parameters final`, like `ESC [ 1 ; 5 C` for Ctrl+Right.
+fn parse_csi(bytes: &[u8]) -> Parsed {
+    let last = bytes[bytes.len() - 1];
+    if bytes.len() < 3 || !(0x40..=0x7e).contains(&last) {
+        return Parsed::Incomplete;
+    }
+    let parameters: Vec<u8> = match std::str::from_utf8(&bytes[2..bytes.len() - 1]) {
+        Ok("") => vec![],
+        Ok(s) => match s.split(';').map(str::parse).collect() {
+            Ok(parameters) => parameters,
+            Err(_) => return Parsed::Invalid,
+        },
+        Err(_) => return Parsed::Invalid,
+    };
+    let modifiers = match parameters.get(1) {
+        Some(mask) => modifiers(*mask),
+        None => KeyModifiers::NONE,
+    };
+    let code = match last {
+        b'Z' => return Parsed::Key(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)),
+        c @ b'P'..=b'S' => KeyCode::F(1 + c - b'P'),
+        b'~' => match parameters.first() {
+            Some(1) | Some(7) => KeyCode::Home,
+            Some(2) => KeyCode::Insert,
+            Some(3) => KeyCode::Delete,
+            Some(4) | Some(8) => KeyCode::End,
+            Some(5) => KeyCode::PageUp,
+            Some(6) => KeyCode::PageDown,
+            Some(&n @ 11..=15) => KeyCode::F(n - 10),
+            Some(&n @ 17..=21) => KeyCode::F(n - 11),
+            Some(&n @ 23..=24) => KeyCode::F(n - 12),
+            _ => return Parsed::Invalid,
+        },
+        c => match cursor_key(c) {
+            Some(code) => code,
+            None => return Parsed::Invalid,
+        },
+    };
+    Parsed::Key(KeyEvent::new(code, modifiers))
+}
+
+/// The modifiers of the parameter `1 + mask`, the mask being Shift 1, Alt 2 and Ctrl 4.
+fn modifiers(parameter: u8) -> KeyModifiers {
+    let mask = parameter.saturating_sub(1);
+    let mut modifiers = KeyModifiers::NONE;
+    if mask & 1 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+    if mask & 2 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+    if mask & 4 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+    modifiers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse `reads` one after another, flushing after each like `Reader::read` does.
+    fn parse(reads: &[&[u8]]) -> Vec<Input> {
+        let mut parser = Parser::default();
+        for bytes in reads {
+            parser.advance(bytes);
+            parser.flush();
+        }
+        parser.inputs
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Input {
+        Input::Key(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn paste_between_keys() {
+        assert_eq!(
+            parse(&[b"a\x1b[200~one\r\ntwo\tq\x1b[201~\x1b"]),
+            vec![
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                Input::Paste("one\r\ntwo\tq".into()),
+                key(KeyCode::Esc, KeyModifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn paste_split_across_reads() {
+        assert_eq!(
+            parse(&[b"\x1b[200~h\xc3", b"\xa9llo\x1b[2", b"01~x"]),
+            vec![
+                Input::Paste("héllo".into()),
+                key(KeyCode::Char('x'), KeyModifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_like_crossterm() {
+        assert_eq!(
+            parse(&[b"\x1b[1;5C\x1b[1;2H\x1b[3~\x1bOP\x1b[Z\x1bx\x07\rJ\xe6\x97\xa5"]),
+            vec![
+                key(KeyCode::Right, KeyModifiers::CONTROL),
+                key(KeyCode::Home, KeyModifiers::SHIFT),
+                key(KeyCode::Delete, KeyModifiers::NONE),
+                key(KeyCode::F(1), KeyModifiers::NONE),
+                key(KeyCode::BackTab, KeyModifiers::SHIFT),
+                key(KeyCode::Char('x'), KeyModifiers::ALT),
+                key(KeyCode::Char('g'), KeyModifiers::CONTROL),
+                key(KeyCode::Enter, KeyModifiers::NONE),
+                key(KeyCode::Char('J'), KeyModifiers::SHIFT),
+                key(KeyCode::Char('日'), KeyModifiers::NONE),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_sequences_are_dropped() {
+        assert_eq!(
+            parse(&[b"\x1b[99~a\x1b[201~b"]),
+            vec![
+                key(KeyCode::Char('a'), KeyModifiers::NONE),
+                key(KeyCode::Char('b'), KeyModifiers::NONE),
+            ]
+        );
+    }
+}
@@ -1,83 +1,204 @@
 use crate::data::Handler;
-use crate::key::{CtrlKey, Key};
+use crate::editor;
+use crate::error::Result;
+use crate::key::Key;
+use crate::keymap::GlobalAction;
 use crate::nav;
-use crate::views::app::{App, ViewState};
+use crate::views::app::{App, FileMode, SearchFocus, ViewState};
+use crate::views::tty::{self, Reader, DISABLE_BRACKETED_PASTE, ENABLE_BRACKETED_PASTE};
 use crossterm::{
-    event::{self, Event as CEvent},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::Show,
+    execute,
+    style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
 enum Event<I> {
     Input(I),
+    /// text pasted into the terminal
+    Paste(String),
     Tick,
 }
 
-pub fn ui(h: Handler) {
-    enable_raw_mode().expect("Enabling raw mode!");
+/// Stops the input thread from reading the terminal, while an external editor runs.
+#[derive(Clone, Default)]
+struct InputGate {
+    paused: Arc<AtomicBool>,
+    /// held by the input thread while it polls and reads
+    reading: Arc<Mutex<()>>,
+}
+
+fn setup_terminal() -> Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        std::io::stdout(),
+        EnterAlternateScreen,
+        Print(ENABLE_BRACKETED_PASTE)
+    )?;
+    Ok(())
+}
+
+/// Leave raw mode and show the cursor, so the shell is usable after we exit or panic.
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(
+        std::io::stdout(),
+        LeaveAlternateScreen,
+        Show,
+        Print(DISABLE_BRACKETED_PASTE)
+    );
+}
+
+pub fn ui(h: Handler) -> Result<()> {
+    let program_state = App::default().set_file_view(h)?;
+    // restore the terminal before the panic message is printed
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+    setup_terminal()?;
+    let mut reader = Reader::open()?;
     let (tx, rx) = std::sync::mpsc::channel();
     let tick_rate = std::time::Duration::from_millis(200);
+    let gate = InputGate::default();
+    let input_gate = gate.clone();
     std::thread::spawn(move || {
         let mut last_tick = Instant::now();
         loop {
+            if input_gate.paused.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(20));
+                continue;
+            }
+            let _reading = input_gate.reading.lock();
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
-            if event::poll(timeout).expect("works") {
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    let key = Key::from(key);
-                    tx.send(Event::Input(key)).expect("error in sending inputs");
-                }
+            let inputs = match reader.read(timeout) {
+                Ok(inputs) => inputs,
+                // the terminal is gone
+                Err(_) => break,
+            };
+            let sent = inputs.into_iter().all(|input| {
+                let event = match input {
+                    tty::Input::Key(key) => Event::Input(Key::from(key)),
+                    tty::Input::Paste(text) => Event::Paste(text),
+                };
+                tx.send(event).is_ok()
+            });
+            if !sent {
+                // the UI is gone
+                break;
             }
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
+            if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
             }
         }
     });
+    let result = run(program_state, rx, gate);
+    restore_terminal();
+    result
+}
+
+/// Hand the terminal to the external editor to edit `path`, and take it back afterwards.
+fn edit_externally(
+    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>,
+    gate: &InputGate,
+    path: &std::path::Path,
+    handler: &mut Handler,
+) -> Result<std::path::PathBuf> {
+    gate.paused.store(true, Ordering::SeqCst);
+    // wait for the input thread to finish reading, the editor gets every key from now on
+    let reading = gate.reading.lock();
+    restore_terminal();
+    let result = editor::edit_note(handler, path);
+    setup_terminal()?;
+    // the editor drew over everything
+    terminal.clear()?;
+    drop(reading);
+    gate.paused.store(false, Ordering::SeqCst);
+    result
+}
+
+fn run(mut program_state: App, rx: Receiver<Event<Key>>, gate: InputGate) -> Result<()> {
     let stdout = std::io::stdout();
     let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend).expect("Error in creating new terminal");
-    terminal.clear().expect("Error in clearing terminal");
-    let mut program_state = App::default().set_file_view(h.config);
+    let mut terminal = Terminal::new(backend)?;
+    terminal.clear()?;
     loop {
-        match terminal.draw(|rect| {
+        terminal.draw(|rect| {
             nav::draw_views(rect, &mut program_state);
-        }) {
-            std::io::Result::Ok(()) => {}
-            std::io::Result::Err(_e) => {
-                panic!("Error in writing into terminal!");
-            }
-        }
+        })?;
 
-        match program_state.get_latest_state() {
-            Some(ViewState::AddView) => {
-                let (x, y) = program_state.get_cursor_position();
-                terminal.show_cursor().unwrap();
-                terminal.set_cursor(x, y).expect("Error in setting cursor");
+        let cursor = match program_state.get_latest_state() {
+            Some(ViewState::AddView) => Some(program_state.get_cursor_position()),
+            Some(ViewState::FileView)
+                if program_state.reader_searching
+                    && matches!(program_state.file_mode, FileMode::File) =>
+            {
+                Some(program_state.get_cursor_position())
             }
-            _ => {
-                terminal.hide_cursor().unwrap();
+            Some(ViewState::SearchView) => match program_state.search_focus {
+                SearchFocus::Query => Some(program_state.get_cursor_position()),
+                SearchFocus::Results => None,
+            },
+            Some(ViewState::FinderView) => {
+                let rect = nav::finder_rect(terminal.size()?);
+                // inside the border, after the "> " prompt
+                Some((
+                    rect.x + 3 + program_state.finder_query.cursor_column() as u16,
+                    rect.y + 1,
+                ))
             }
+            Some(ViewState::HelpView) => {
+                let rect = nav::help_rect(terminal.size()?);
+                Some((
+                    rect.x + 3 + program_state.help_query.cursor_column() as u16,
+                    rect.y + 1,
+                ))
+            }
+            Some(ViewState::RenameView) => {
+                let rect = nav::rename_rect(terminal.size()?);
+                Some((
+                    rect.x + 3 + program_state.rename_input.cursor_column() as u16,
+                    rect.y + 1,
+                ))
+            }
+            _ => None,
+        };
+        if let Some((x, y)) = cursor {
+            terminal.show_cursor()?;
+            terminal.set_cursor(x, y)?;
+        } else {
+            terminal.hide_cursor()?;
         }
-        match rx.recv().unwrap() {
-            Event::Input(event) => {
-                if let Key::Ctrl(CtrlKey::Char('q')) = event {
-                    disable_raw_mode().expect("Error in disabling raw mode");
-                    match terminal.show_cursor() {
-                        std::io::Result::Ok(()) => {}
-                        std::io::Result::Err(_e) => {
-                            panic!("Error in showing cursor!");
-                        }
-                    }
+        match rx.recv() {
+            Ok(Event::Input(event)) => {
+                if let Some(GlobalAction::Quit) = program_state.global_action(event) {
                     break;
                 }
                 program_state.update_state(&event);
             }
-            _ => {}
+            Ok(Event::Paste(text)) => {
+                program_state.paste(&text);
+            }
+            Ok(Event::Tick) => {
+                // a key waiting for the rest of a sequence is handled on its own
+                program_state.tick();
+            }
+            // the input thread is gone, nothing more to read
+            Err(_) => break,
+        }
+        if let Some(path) = program_state.external_edit.take() {
+            let result = edit_externally(&mut terminal, &gate, &path, &mut program_state.handler);
+            program_state.finish_external_edit(&path, result);
         }
     }
+    Ok(())
 }
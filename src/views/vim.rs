@@ -0,0 +1,710 @@
+use crate::add_view;
+use crate::error::Result;
+use crate::key::{CtrlKey, Key};
+use crate::views::app::{App, Input, Position};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Insert,
+    Visual,
+    /// typing a `:` command
+    Command,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    WordStart,
+    WordBack,
+    WordEnd,
+    LineStart,
+    LineEnd,
+    FirstLine,
+    LastLine,
+}
+
+impl Motion {
+    /// whether an operator works on whole rows
+    fn linewise(self) -> bool {
+        matches!(
+            self,
+            Motion::Up | Motion::Down | Motion::FirstLine | Motion::LastLine
+        )
+    }
+
+    /// whether an operator includes the character the motion ends on
+    fn inclusive(self) -> bool {
+        self == Motion::WordEnd
+    }
+
+    fn apply(self, input: &mut Input, count: Option<usize>) {
+        let repeat = count.unwrap_or(1);
+        match self {
+            Motion::Left => {
+                for _ in 0..repeat {
+                    // h and l stay on the row
+                    if input.horizontal_idx > 0 {
+                        input.move_left();
+                    }
+                }
+            }
+            Motion::Right => {
+                for _ in 0..repeat {
+                    if input.horizontal_idx < input.input[input.vertical_idx].len() {
+                        input.move_right();
+                    }
+                }
+            }
+            Motion::Up => (0..repeat).for_each(|_| input.move_up()),
+            Motion::Down => (0..repeat).for_each(|_| input.move_down()),
+            Motion::WordStart => (0..repeat).for_each(|_| input.next_word_start()),
+            Motion::WordBack => (0..repeat).for_each(|_| input.previous_word_start()),
+            Motion::WordEnd => (0..repeat).for_each(|_| input.word_end()),
+            Motion::LineStart => input.beginning_of_line(),
+            Motion::LineEnd => {
+                (1..repeat).for_each(|_| input.move_down());
+                input.end_of_line();
+            }
+            Motion::FirstLine | Motion::LastLine => {
+                let last = input.input.len() - 1;
+                let row = match (self, count) {
+                    (_, Some(count)) => std::cmp::min(count.saturating_sub(1), last),
+                    (Motion::FirstLine, None) => 0,
+                    _ => last,
+                };
+                input.jump_to((row, 0));
+            }
+        }
+    }
+}
+
+/// What the add view should do after vim has seen a key.
+enum Action {
+    /// handle the key like without vim mode
+    PassThrough,
+    Done,
+    /// replay the keys of the last change this many times
+    Repeat(Vec<Key>, usize),
+    Save,
+    Quit,
+}
+
+/// State of the vim mode of the editor, enabled with `vim = true` in the config.
+pub struct Vim {
+    pub mode: Mode,
+    /// text typed after `:`
+    pub command: String,
+    /// shown in the status line until the next key
+    pub message: Option<String>,
+    count: usize,
+    operator: Option<(char, Option<usize>)>,
+    /// `g` was typed, waiting for the second `g`
+    pending_g: bool,
+    /// keys of the command being typed without its count, kept as `last_change` if it
+    /// changes the text
+    keys: Vec<Key>,
+    /// count the command being typed works with
+    change_count: Option<usize>,
+    /// keys repeated by `.`
+    last_change: Vec<Key>,
+    /// count of the last change, replaced by a count given to `.`
+    last_count: Option<usize>,
+    /// text last yanked or deleted by whole rows, pasted as rows while it is the latest kill
+    linewise: Option<String>,
+}
+
+impl Default for Vim {
+    fn default() -> Self {
+        Vim {
+            mode: Mode::Normal,
+            command: String::new(),
+            message: None,
+            count: 0,
+            operator: None,
+            pending_g: false,
+            keys: vec![],
+            change_count: None,
+            last_change: vec![],
+            last_count: None,
+            linewise: None,
+        }
+    }
+}
+
+/// Handle `key` in vim mode. Returns false if the editor should handle it as usual.
+pub fn handler(app: &mut App, key: &Key) -> bool {
+    let mut vim = match app.vim.take() {
+        Some(vim) => vim,
+        None => return false,
+    };
+    vim.message = None;
+    let action = match vim.mode {
+        Mode::Normal => vim.normal(app, key),
+        Mode::Insert => vim.insert(app, key),
+        Mode::Visual => vim.visual(app, key),
+        Mode::Command => Ok(vim.command(key)),
+    };
+    let action = action.and_then(|action| {
+        if vim.mode == Mode::Normal {
+            clamp(app.get_current_input()?);
+        }
+        Ok(action)
+    });
+    app.vim = Some(vim);
+    match action {
+        Err(e) => app.show_error(e),
+        Ok(Action::PassThrough) => return false,
+        Ok(Action::Done) => {}
+        Ok(Action::Repeat(keys, count)) => {
+            for _ in 0..count {
+                for key in keys.iter() {
+                    add_view::handler(app, &[*key]);
+                }
+            }
+        }
+        Ok(Action::Save) => add_view::save(app),
+        Ok(Action::Quit) => add_view::quit(app),
+    }
+    true
+}
+
+/// The count of an operator and its motion: 2d3w deletes 6 words.
+fn combine(operator_count: Option<usize>, count: Option<usize>) -> Option<usize> {
+    match (operator_count, count) {
+        (Some(a), Some(b)) => Some(a * b),
+        (a, b) => a.or(b),
+    }
+}
+
+/// In normal mode the cursor is on a character, not after the last one.
+fn clamp(input: &mut Input) {
+    if input.horizontal_idx > 0 && input.horizontal_idx == input.input[input.vertical_idx].len() {
+        input.move_left();
+    }
+}
+
+impl Vim {
+    /// The pending count and operator, for the status line.
+    pub fn pending(&self) -> String {
+        let mut pending = String::new();
+        if let Some((operator, count)) = self.operator {
+            if let Some(count) = count {
+                pending.push_str(&count.to_string());
+            }
+            pending.push(operator);
+        }
+        if self.count > 0 {
+            pending.push_str(&self.count.to_string());
+        }
+        if self.pending_g {
+            pending.push('g');
+        }
+        pending
+    }
+
+    fn take_count(&mut self) -> Option<usize> {
+        let count = self.count;
+        self.count = 0;
+        if count > 0 {
+            Some(count)
+        } else {
+            None
+        }
+    }
+
+    /// The motion `key` stands for. `g` is kept pending until the next key.
+    fn motion(&mut self, key: &Key) -> Option<Motion> {
+        if self.pending_g {
+            self.pending_g = false;
+            return match key {
+                Key::Char('g') => Some(Motion::FirstLine),
+                _ => None,
+            };
+        }
+        match key {
+            Key::Char('h') | Key::Left | Key::Backspace => Some(Motion::Left),
+            Key::Char('l') | Key::Right | Key::Char(' ') => Some(Motion::Right),
+            Key::Char('k') | Key::Up => Some(Motion::Up),
+            Key::Char('j') | Key::Down | Key::Enter => Some(Motion::Down),
+            Key::Char('w') => Some(Motion::WordStart),
+            Key::Char('b') => Some(Motion::WordBack),
+            Key::Char('e') => Some(Motion::WordEnd),
+            Key::Char('0') | Key::Home => Some(Motion::LineStart),
+            Key::Char('$') | Key::End => Some(Motion::LineEnd),
+            Key::Char('G') => Some(Motion::LastLine),
+            _ => None,
+        }
+    }
+
+    /// Keep the keys of the finished command for `.` if it changed the text.
+    fn finish(&mut self, change: bool) {
+        if change {
+            self.last_change = std::mem::take(&mut self.keys);
+            self.last_count = self.change_count.take();
+        } else {
+            self.keys.clear();
+            self.change_count = None;
+        }
+    }
+
+    fn normal(&mut self, app: &mut App, key: &Key) -> Result<Action> {
+        match key {
+            Key::Char(c @ '1'..='9') => {
+                self.count = self.count * 10 + c.to_digit(10).unwrap_or(0) as usize;
+                return Ok(Action::Done);
+            }
+            Key::Char('0') if self.count > 0 => {
+                self.count *= 10;
+                return Ok(Action::Done);
+            }
+            _ => {}
+        }
+        // the count is kept apart, a count given to `.` replaces it
+        self.keys.push(*key);
+        if *key == Key::Char('g') && !self.pending_g {
+            self.pending_g = true;
+            return Ok(Action::Done);
+        }
+        let was_pending_g = self.pending_g;
+        let count = self.take_count();
+        if let Some(motion) = self.motion(key) {
+            if let Some((operator, operator_count)) = self.operator.take() {
+                self.change_count = combine(operator_count, count);
+                return self.operate(app, operator, motion, self.change_count);
+            }
+            app.get_current_input()?.deselect();
+            motion.apply(app.get_current_input()?, count);
+            self.finish(false);
+            return Ok(Action::Done);
+        }
+        if was_pending_g {
+            // not a command starting with g
+            self.operator = None;
+            self.finish(false);
+            return Ok(Action::Done);
+        }
+        if let Some((operator, operator_count)) = self.operator.take() {
+            if *key == Key::Char(operator) {
+                // dd, cc and yy work on count rows
+                self.change_count = combine(operator_count, count);
+                let count = self.change_count.unwrap_or(1);
+                let input = app.get_current_input()?;
+                let first = input.vertical_idx;
+                let last = std::cmp::min(first + count, input.input.len()) - 1;
+                let (start, end) = input.rows_range(first, last, operator == 'd');
+                return self.apply_operator(app, operator, start, end, true);
+            }
+            self.finish(false);
+            return Ok(Action::Done);
+        }
+        let input = app.get_current_input()?;
+        input.deselect();
+        match key {
+            Key::Char(c @ 'd') | Key::Char(c @ 'c') | Key::Char(c @ 'y') => {
+                self.operator = Some((*c, count));
+                return Ok(Action::Done);
+            }
+            Key::Char('D') => {
+                self.change_count = count;
+                return self.operate(app, 'd', Motion::LineEnd, count);
+            }
+            Key::Char('C') => {
+                self.change_count = count;
+                return self.operate(app, 'c', Motion::LineEnd, count);
+            }
+            Key::Char('x') | Key::Delete => {
+                self.change_count = count;
+                let start = input.cursor();
+                Motion::Right.apply(input, count);
+                let end = input.cursor();
+                return self.apply_operator(app, 'd', start, end, false);
+            }
+            Key::Char(c @ 'p') | Key::Char(c @ 'P') => {
+                self.paste(app, *c == 'p', count.unwrap_or(1))?;
+                self.change_count = count;
+                self.finish(true);
+                return Ok(Action::Done);
+            }
+            Key::Char('i') => {}
+            Key::Char('a') => {
+                if input.horizontal_idx < input.input[input.vertical_idx].len() {
+                    input.move_right();
+                }
+            }
+            Key::Char('I') => input.beginning_of_line(),
+            Key::Char('A') => input.end_of_line(),
+            Key::Char('o') => {
+                input.end_of_line();
+                input.new_line();
+            }
+            Key::Char('O') => {
+                input.beginning_of_line();
+                input.new_line();
+                input.move_up();
+            }
+            Key::Char('v') => {
+                let cursor = input.cursor();
+                input.select(cursor, cursor);
+                self.mode = Mode::Visual;
+                self.finish(false);
+                return Ok(Action::Done);
+            }
+            Key::Char('u') => {
+                input.undo();
+                self.finish(false);
+                return Ok(Action::Done);
+            }
+            Key::Ctrl(CtrlKey::Char('r')) => {
+                input.redo();
+                self.finish(false);
+                return Ok(Action::Done);
+            }
+            Key::Char('.') => {
+                self.keys.clear();
+                return Ok(match self.last_count {
+                    // 3dw then 2. deletes 2 words
+                    Some(last) => {
+                        let count = count.unwrap_or(last).to_string();
+                        let mut keys: Vec<Key> = count.chars().map(Key::Char).collect();
+                        keys.extend(self.last_change.iter());
+                        Action::Repeat(keys, 1)
+                    }
+                    // a change without a count is done count times
+                    None => Action::Repeat(self.last_change.clone(), count.unwrap_or(1)),
+                });
+            }
+            Key::Char(':') => {
+                self.mode = Mode::Command;
+                self.command.clear();
+                self.finish(false);
+                return Ok(Action::Done);
+            }
+            Key::Ctrl(_) | Key::Alt(_) | Key::Tab | Key::BackTab | Key::PageUp | Key::PageDown => {
+                self.finish(false);
+                return Ok(Action::PassThrough);
+            }
+            _ => {
+                // Esc or a key without a command cancels what was typed
+                self.finish(false);
+                return Ok(Action::Done);
+            }
+        }
+        // i, a, I, A, o and O: the keys typed until Esc are part of the change
+        self.mode = Mode::Insert;
+        Ok(Action::Done)
+    }
+
+    fn insert(&mut self, app: &mut App, key: &Key) -> Result<Action> {
+        self.keys.push(*key);
+        if *key != Key::Esc {
+            return Ok(Action::PassThrough);
+        }
+        self.mode = Mode::Normal;
+        self.finish(true);
+        let input = app.get_current_input()?;
+        input.deselect();
+        if input.horizontal_idx > 0 {
+            input.move_left();
+        }
+        Ok(Action::Done)
+    }
+
+    fn visual(&mut self, app: &mut App, key: &Key) -> Result<Action> {
+        if let Key::Char(c @ '1'..='9') = key {
+            self.count = self.count * 10 + c.to_digit(10).unwrap_or(0) as usize;
+            return Ok(Action::Done);
+        }
+        if *key == Key::Char('g') && !self.pending_g {
+            self.pending_g = true;
+            return Ok(Action::Done);
+        }
+        let count = self.take_count();
+        if let Some(motion) = self.motion(key) {
+            // moving keeps the start of the selection
+            motion.apply(app.get_current_input()?, count);
+            return Ok(Action::Done);
+        }
+        let input = app.get_current_input()?;
+        let cursor = input.cursor();
+        let (start, end) = input.selection().unwrap_or((cursor, cursor));
+        // the character under the cursor is selected too
+        let end = input.next_position(end).unwrap_or(end);
+        let operator = match key {
+            Key::Char('d') | Key::Char('x') | Key::Delete => 'd',
+            Key::Char('c') => 'c',
+            Key::Char('y') => 'y',
+            Key::Esc | Key::Char('v') => {
+                input.deselect();
+                self.mode = Mode::Normal;
+                return Ok(Action::Done);
+            }
+            Key::Ctrl(_) | Key::Tab | Key::BackTab => return Ok(Action::PassThrough),
+            _ => return Ok(Action::Done),
+        };
+        self.mode = Mode::Normal;
+        self.keys.clear();
+        self.apply_operator(app, operator, start, end, false)
+    }
+
+    fn command(&mut self, key: &Key) -> Action {
+        match key {
+            Key::Char(c) => {
+                self.command.push(*c);
+                return Action::Done;
+            }
+            Key::Backspace if !self.command.is_empty() => {
+                self.command.pop();
+                return Action::Done;
+            }
+            Key::Enter => {}
+            _ => {
+                self.mode = Mode::Normal;
+                return Action::Done;
+            }
+        }
+        self.mode = Mode::Normal;
+        match self.command.trim() {
+            "w" | "wq" | "x" => Action::Save,
+            "q" | "q!" => Action::Quit,
+            "" => Action::Done,
+            command => {
+                self.message = Some(format!("Not an editor command: {}", command));
+                Action::Done
+            }
+        }
+    }
+
+    /// Apply `operator` from the cursor to where `motion` moves it.
+    fn operate(
+        &mut self,
+        app: &mut App,
+        operator: char,
+        motion: Motion,
+        count: Option<usize>,
+    ) -> Result<Action> {
+        let input = app.get_current_input()?;
+        input.deselect();
+        let start = input.cursor();
+        let on_word = matches!(
+            input.input[start.0][start.1..].chars().next(),
+            Some(c) if !c.is_whitespace()
+        );
+        // like vim, cw changes to the end of the word and leaves the space after it
+        let motion = match motion {
+            Motion::WordStart if operator == 'c' && on_word => Motion::WordEnd,
+            motion => motion,
+        };
+        motion.apply(input, count);
+        let end = input.cursor();
+        let (first, last) = if start <= end {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        if motion.linewise() {
+            let (first, last) = input.rows_range(first.0, last.0, operator == 'd');
+            return self.apply_operator(app, operator, first, last, true);
+        }
+        let last = if motion.inclusive() {
+            input.next_position(last).unwrap_or(last)
+        } else {
+            last
+        };
+        self.apply_operator(app, operator, first, last, false)
+    }
+
+    /// Yank, delete or change the text between `start` and `end`.
+    fn apply_operator(
+        &mut self,
+        app: &mut App,
+        operator: char,
+        start: Position,
+        end: Position,
+        linewise: bool,
+    ) -> Result<Action> {
+        let input = app.get_current_input()?;
+        // the cursor stays at the start, where undo puts it back
+        input.select(end, start);
+        let text = if operator == 'y' {
+            let text = input.selected_text();
+            input.jump_to(start);
+            text
+        } else {
+            input.cut()
+        };
+        let text = text.unwrap_or_default();
+        let text = if linewise && operator == 'd' {
+            // the row after the deleted ones, or before them at the end of the text
+            input.jump_to((start.0, 0));
+            // the line break that went with the rows is not part of them
+            match text.strip_suffix('\n') {
+                Some(rows) => rows.to_owned(),
+                None => text.strip_prefix('\n').unwrap_or(&text).to_owned(),
+            }
+        } else {
+            text
+        };
+        self.linewise = if linewise { Some(text.clone()) } else { None };
+        app.kill(text);
+        if operator == 'c' {
+            self.mode = Mode::Insert;
+        } else {
+            self.finish(operator == 'd');
+        }
+        Ok(Action::Done)
+    }
+
+    /// Put the latest kill `count` times after the cursor with `p`, before it with `P`.
+    fn paste(&mut self, app: &mut App, after: bool, count: usize) -> Result<()> {
+        let text = match app.kill_ring.front() {
+            Some(text) => text.clone(),
+            None => return Ok(()),
+        };
+        let linewise = self.linewise.as_ref() == Some(&text);
+        let input = app.get_current_input()?;
+        let text = vec![text; count].join(if linewise { "\n" } else { "" });
+        if linewise {
+            let row = if after {
+                input.end_of_line();
+                input.insert_text(&format!("\n{}", text));
+                input.vertical_idx - text.matches('\n').count()
+            } else {
+                input.beginning_of_line();
+                let row = input.vertical_idx;
+                input.insert_text(&format!("{}\n", text));
+                row
+            };
+            input.jump_to((row, 0));
+        } else {
+            if after && input.horizontal_idx < input.input[input.vertical_idx].len() {
+                input.move_right();
+            }
+            input.insert_text(&text);
+            // the cursor ends on the last pasted character
+            input.move_left();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::views::app::ViewState;
+
+    /// An editor in vim normal mode on a text holding `text`, the cursor at its start.
+    fn editor(text: &str) -> App {
+        let mut app = App::default();
+        app.vim = Some(Vim::default());
+        app.set_add_view_ref();
+        app.input_current_tab.current_item = 2;
+        app.input_text.insert_string(text);
+        app.input_text.top_beginning_of_line();
+        app
+    }
+
+    /// Type `keys` in the editor, `\x1b` being Esc and `\n` Enter.
+    fn press(app: &mut App, keys: &str) {
+        for c in keys.chars() {
+            let key = match c {
+                '\x1b' => Key::Esc,
+                '\n' => Key::Enter,
+                c => Key::Char(c),
+            };
+            add_view::handler(app, &[key]);
+        }
+    }
+
+    fn mode(app: &App) -> Mode {
+        app.vim.as_ref().map_or(Mode::Normal, |vim| vim.mode)
+    }
+
+    /// The text after typing `keys` in an editor holding `text`.
+    fn edit(text: &str, keys: &str) -> String {
+        let mut app = editor(text);
+        press(&mut app, keys);
+        app.input_text.get_string()
+    }
+
+    #[test]
+    fn operators_with_motions() {
+        assert_eq!(edit("one two three", "dw"), "two three");
+        assert_eq!(edit("one two three", "wde"), "one  three");
+        assert_eq!(edit("one two three", "wd$"), "one ");
+        assert_eq!(edit("one two", "cwnew\x1b"), "new two");
+        assert_eq!(edit("one two", "ywP"), "one one two");
+        assert_eq!(edit("a\nb\nc", "jdd"), "a\nc");
+        assert_eq!(edit("a\nb", "yyp"), "a\na\nb");
+        assert_eq!(edit("a\nb\nc", "dG"), "");
+        assert_eq!(edit("abc", "x"), "bc");
+    }
+
+    #[test]
+    fn counts_multiply() {
+        assert_eq!(edit("a b c d e", "2dw"), "c d e");
+        assert_eq!(edit("a b c d e", "d2w"), "c d e");
+        assert_eq!(edit("a b c d e", "2d2w"), "e");
+        assert_eq!(edit("abcd", "3x"), "d");
+        assert_eq!(edit("1\n2\n3", "2dd"), "3");
+        assert_eq!(edit("1\n2\n3\n4", "3Gdd"), "1\n2\n4");
+        assert_eq!(edit("ab", "yl3p"), "aaaab");
+    }
+
+    #[test]
+    fn dot_repeats_the_last_change() {
+        assert_eq!(edit("a b c d e f g h", "dw."), "c d e f g h");
+        assert_eq!(edit("a b c d e f g h", "3dw."), "g h");
+        assert_eq!(edit("abc", "ix\x1b."), "xxabc");
+        // moving and yanking are not changes
+        assert_eq!(edit("a b c d", "dwwyw."), "b d");
+    }
+
+    #[test]
+    fn count_given_to_dot_replaces_the_one_of_the_change() {
+        assert_eq!(edit("a b c d e f g h i", "3dw2."), "f g h i");
+        assert_eq!(edit("a b c d e f g h i", "d3w2."), "f g h i");
+        // without a count of its own the change is done count times
+        assert_eq!(edit("a b c d e f g h i", "dw3."), "e f g h i");
+        assert_eq!(edit("abcdef", "2x3.."), "");
+    }
+
+    #[test]
+    fn mode_transitions() {
+        let mut app = editor("abc");
+        press(&mut app, "A");
+        assert!(mode(&app) == Mode::Insert);
+        press(&mut app, "d\x1b");
+        assert!(mode(&app) == Mode::Normal);
+        assert_eq!(app.input_text.get_string(), "abcd");
+        // the cursor goes back on the last character typed
+        assert_eq!(app.input_text.cursor(), (0, 3));
+
+        press(&mut app, "0vl");
+        assert!(mode(&app) == Mode::Visual);
+        press(&mut app, "d");
+        assert!(mode(&app) == Mode::Normal);
+        assert_eq!(app.input_text.get_string(), "cd");
+
+        press(&mut app, "v\x1b");
+        assert!(mode(&app) == Mode::Normal);
+        press(&mut app, ":wrong");
+        assert!(mode(&app) == Mode::Command);
+        press(&mut app, "\n");
+        assert!(mode(&app) == Mode::Normal);
+        assert_eq!(
+            app.vim.as_ref().and_then(|vim| vim.message.clone()),
+            Some(String::from("Not an editor command: wrong"))
+        );
+
+        press(&mut app, ":q\n");
+        assert!(mode(&app) == Mode::Normal);
+        assert!(matches!(
+            app.get_latest_state(),
+            Some(ViewState::DialogView)
+        ));
+    }
+}